@@ -1,18 +1,83 @@
 package zerobus
 
 import (
-	"sync"
+	"errors"
+	"sort"
+	"time"
+	"unsafe"
 )
 
+// errAckNotTracked is returned when awaiting an ack of a fire-and-forget stream
+var errAckNotTracked = &ZerobusError{
+	Message:     "acknowledgments are not tracked in fire-and-forget mode; use Flush instead",
+	IsRetryable: false,
+	Code:        ErrorCodeInvalidState,
+}
+
+// errWaitTimedOut is returned by AwaitTimeout when the ack stays pending
+var errWaitTimedOut = &ZerobusError{
+	Message:     "Timed out waiting for the acknowledgment",
+	IsRetryable: true,
+	Code:        ErrorCodeWaitTimedOut,
+}
+
 // RecordAck represents a pending acknowledgment for an ingested record.
 // It allows ingestion with deferred acknowledgment handling.
 type RecordAck struct {
+	stream *ZerobusStream
 	ackID  uint64
-	once   sync.Once
+	// Held by the goroutine awaiting the ack; a channel rather than a mutex so that
+	// AwaitTimeout can stop waiting for it at its deadline
+	sem    chan struct{}
+	done   bool
 	offset int64
 	err    error
 }
 
+// newRecordAck returns the pending acknowledgment ackID of stream
+func newRecordAck(stream *ZerobusStream, ackID uint64) *RecordAck {
+	return &RecordAck{stream: stream, ackID: ackID, sem: make(chan struct{}, 1)}
+}
+
+func (a *RecordAck) lock() {
+	a.sem <- struct{}{}
+}
+
+func (a *RecordAck) unlock() {
+	<-a.sem
+}
+
+func (a *RecordAck) tryLock() bool {
+	select {
+	case a.sem <- struct{}{}:
+		return true
+	default:
+		return false
+	}
+}
+
+// lockTimeout waits at most timeout for the lock and reports whether it was acquired
+func (a *RecordAck) lockTimeout(timeout time.Duration) bool {
+	if a.tryLock() {
+		return true
+	}
+	timer := time.NewTimer(timeout)
+	defer timer.Stop()
+	select {
+	case a.sem <- struct{}{}:
+		return true
+	case <-timer.C:
+		return false
+	}
+}
+
+// ID returns the acknowledgment ID, unique within the stream.
+// It matches UnackedRecord.AckID of records returned by GetUnackedRecords.
+// Fire-and-forget streams always return 0.
+func (a *RecordAck) ID() uint64 {
+	return a.ackID
+}
+
 // Await blocks until the record is acknowledged by the server and returns the offset.
 // This method can only be called once. Subsequent calls return the cached result.
 //
@@ -22,16 +87,249 @@ type RecordAck struct {
 //	// Do other work...
 //	offset, err := ack.Await()
 func (a *RecordAck) Await() (int64, error) {
-	a.once.Do(func() {
-		a.offset, a.err = streamAwaitAck(a.ackID)
-	})
+	a.lock()
+	defer a.unlock()
+	open := a.stream.acquire()
+	if open {
+		defer a.stream.release()
+	}
+
+	if !a.settleUntracked(open) {
+		a.offset, a.err = streamAwaitAck(a.stream.ptr, a.ackID)
+		a.done = true
+	}
+	return a.offset, a.err
+}
+
+// AwaitTimeout is like Await, but gives up after timeout. If the record is still unacknowledged
+// by then, it returns a retryable error with code ErrorCodeWaitTimedOut and the ack stays
+// pending, so it can be awaited again later. Other results are cached like Await's.
+// AwaitTimeout never waits past timeout, neither for another goroutine awaiting the same ack
+// nor for a stream that is being closed, which is reported with ErrorCodeStreamClosed.
+//
+// Example:
+//
+//	offset, err := ack.AwaitTimeout(time.Until(deadline))
+//	var zerr *zerobus.ZerobusError
+//	if errors.As(err, &zerr) && zerr.Code == zerobus.ErrorCodeWaitTimedOut {
+//		// Not acknowledged yet: report it and check again later
+//	}
+func (a *RecordAck) AwaitTimeout(timeout time.Duration) (int64, error) {
+	deadline := time.Now().Add(timeout)
+	// Another goroutine may be awaiting this ack; wait for it only until the deadline
+	if !a.lockTimeout(timeout) {
+		return -1, errWaitTimedOut
+	}
+	defer a.unlock()
+	// Never blocks: a closing stream is reported as closed instead of waited for
+	open := a.stream.acquire()
+	if open {
+		defer a.stream.release()
+	}
+
+	if a.settleUntracked(open) {
+		return a.offset, a.err
+	}
+	offset, err := streamAwaitAckTimeout(a.stream.ptr, a.ackID, time.Until(deadline))
+	var zerr *ZerobusError
+	if errors.As(err, &zerr) && zerr.Code == ErrorCodeWaitTimedOut {
+		return -1, err
+	}
+	a.offset, a.err, a.done = offset, err, true
 	return a.offset, a.err
 }
 
+// settleUntracked caches the result of an ack that is already done or cannot be awaited
+// through its stream, and reports whether the ack is settled. The ack must be locked; open
+// reports whether the caller acquired the stream.
+func (a *RecordAck) settleUntracked(open bool) bool {
+	switch {
+	case a.done:
+		return true
+	case a.ackID == 0:
+		a.offset, a.err = -1, errAckNotTracked
+	case !open:
+		a.offset, a.err = -1, errStreamClosed
+	default:
+		return false
+	}
+	a.done = true
+	return true
+}
+
+// AwaitAcks waits for several acknowledgments of the same stream with a single call into the
+// native library, which is much cheaper than awaiting them one by one.
+//
+// offsets[i] is the offset of acks[i], or -1 if it failed. The returned error is the first
+// failure in acks order; the error of every ack stays available through its Await method,
+// which returns the cached result without blocking.
+//
+// Example:
+//
+//	offsets, err := zerobus.AwaitAcks(acks)
+func AwaitAcks(acks []*RecordAck) ([]int64, error) {
+	stream, err := commonStream(acks)
+	if err != nil {
+		return nil, err
+	}
+	unique, unlock := lockAcks(acks)
+	defer unlock()
+	open := stream.acquire()
+	if open {
+		defer stream.release()
+	}
+
+	var pending []*RecordAck
+	var ids []uint64
+	for _, a := range unique {
+		if !a.settleUntracked(open) {
+			pending = append(pending, a)
+			ids = append(ids, a.ackID)
+		}
+	}
+	if len(pending) > 0 {
+		offsets, errs, err := streamAwaitAcks(stream.ptr, ids)
+		if err != nil {
+			return nil, err
+		}
+		for i, a := range pending {
+			a.offset, a.err, a.done = offsets[i], errs[i], true
+		}
+	}
+
+	offsets := make([]int64, len(acks))
+	var firstErr error
+	for i, a := range acks {
+		offsets[i] = a.offset
+		if firstErr == nil {
+			firstErr = a.err
+		}
+	}
+	return offsets, firstErr
+}
+
+// AwaitAny blocks until one of several acknowledgments of the same stream completes and
+// returns it. Its result is cached, so Await on the returned ack does not block.
+// Acks that already completed are returned first. The others stay pending and keep counting
+// in OutstandingAcks. While AwaitAny waits, Await on one of the given acks blocks until it
+// returns, and TryGet reports it as pending; acks not passed to AwaitAny are unaffected.
+//
+// The error only reports invalid arguments; the outcome of the ack itself is returned by Await.
+//
+// Example:
+//
+//	// Keep a window of at most 100 unacknowledged records
+//	for len(window) >= 100 {
+//		done, _ := zerobus.AwaitAny(window)
+//		if _, err := done.Await(); err != nil {
+//			// Handle the failed record
+//		}
+//		window = slices.DeleteFunc(window, func(a *zerobus.RecordAck) bool { return a == done })
+//	}
+func AwaitAny(acks []*RecordAck) (*RecordAck, error) {
+	stream, err := commonStream(acks)
+	if err != nil {
+		return nil, err
+	}
+	unique, unlock := lockAcks(acks)
+	defer unlock()
+	open := stream.acquire()
+	if open {
+		defer stream.release()
+	}
+
+	for _, a := range acks {
+		if a.settleUntracked(open) {
+			return a, nil
+		}
+	}
+
+	ids := make([]uint64, len(unique))
+	for i, a := range unique {
+		ids[i] = a.ackID
+	}
+	ackID, offset, ackErr, err := streamAwaitAnyAck(stream.ptr, ids)
+	if err != nil {
+		return nil, err
+	}
+	for _, a := range unique {
+		if a.ackID == ackID {
+			a.offset, a.err, a.done = offset, ackErr, true
+			return a, nil
+		}
+	}
+	return nil, &ZerobusError{Message: "Unexpected ack ID", Code: ErrorCodeInternal}
+}
+
+// commonStream returns the stream all acks belong to
+func commonStream(acks []*RecordAck) (*ZerobusStream, error) {
+	if len(acks) == 0 {
+		return nil, &ZerobusError{Message: "no acks given", Code: ErrorCodeInvalidArgument}
+	}
+	for _, a := range acks {
+		if a == nil || a.stream != acks[0].stream {
+			return nil, &ZerobusError{Message: "acks must be non-nil and belong to the same stream", Code: ErrorCodeInvalidArgument}
+		}
+	}
+	return acks[0].stream, nil
+}
+
+// lockAcks locks each distinct ack once, in ack ID order so concurrent callers cannot deadlock
+// Returns the distinct acks and a function releasing the locks
+func lockAcks(acks []*RecordAck) ([]*RecordAck, func()) {
+	seen := make(map[*RecordAck]bool, len(acks))
+	unique := make([]*RecordAck, 0, len(acks))
+	for _, a := range acks {
+		if !seen[a] {
+			seen[a] = true
+			unique = append(unique, a)
+		}
+	}
+	sort.Slice(unique, func(i, j int) bool {
+		if unique[i].ackID != unique[j].ackID {
+			return unique[i].ackID < unique[j].ackID
+		}
+		return uintptr(unsafe.Pointer(unique[i])) < uintptr(unsafe.Pointer(unique[j]))
+	})
+
+	for _, a := range unique {
+		a.lock()
+	}
+	return unique, func() {
+		for _, a := range unique {
+			a.unlock()
+		}
+	}
+}
+
 // TryGet attempts to get the acknowledgment without blocking.
 // Returns (offset, nil, true) if the acknowledgment is ready.
 // Returns (0, nil, false) if still pending.
 // Returns (0, error, true) if there was an error.
+// Once ready, the result is cached and also returned by Await.
 func (a *RecordAck) TryGet() (int64, error, bool) {
-	return streamTryGetAck(a.ackID)
+	// Another goroutine is awaiting this ack, so it is still pending
+	if !a.tryLock() {
+		return 0, nil, false
+	}
+	defer a.unlock()
+	open := a.stream.acquire()
+	if open {
+		defer a.stream.release()
+	}
+
+	if !a.settleUntracked(open) {
+		offset, err, ready := streamTryGetAck(a.stream.ptr, a.ackID)
+		if !ready {
+			return 0, nil, false
+		}
+		if err != nil {
+			offset = -1
+		}
+		a.offset, a.err, a.done = offset, err, true
+	}
+	if a.err != nil {
+		return 0, a.err, true
+	}
+	return a.offset, nil, true
 }
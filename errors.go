@@ -2,10 +2,55 @@ package zerobus
 
 import "fmt"
 
+// ErrorCode identifies the category of a ZerobusError.
+// The numeric values match the CErrorCode enum of the Rust FFI layer.
+type ErrorCode int32
+
+const (
+	// ErrorCodeOk indicates that no error occurred
+	ErrorCodeOk ErrorCode = 0
+	// ErrorCodeInternal indicates a failure inside the SDK itself
+	ErrorCodeInternal ErrorCode = 1
+	// ErrorCodeInvalidArgument indicates that an argument or record was rejected
+	ErrorCodeInvalidArgument ErrorCode = 2
+	// ErrorCodeAuthFailure indicates invalid credentials, tokens or insufficient permissions
+	ErrorCodeAuthFailure ErrorCode = 3
+	// ErrorCodeChannelError indicates that the gRPC channel could not be opened
+	ErrorCodeChannelError ErrorCode = 4
+	// ErrorCodeTLSError indicates that the TLS handshake failed
+	ErrorCodeTLSError ErrorCode = 5
+	// ErrorCodeInvalidEndpoint indicates a malformed Zerobus or Unity Catalog endpoint
+	ErrorCodeInvalidEndpoint ErrorCode = 6
+	// ErrorCodeInvalidTableName indicates an invalid or unknown table name
+	ErrorCodeInvalidTableName ErrorCode = 7
+	// ErrorCodeCreateStreamFailed indicates that the server refused to create the stream
+	ErrorCodeCreateStreamFailed ErrorCode = 8
+	// ErrorCodeStreamClosed indicates that the stream has been closed
+	ErrorCodeStreamClosed ErrorCode = 9
+	// ErrorCodeTimeout indicates that a deadline was exceeded
+	ErrorCodeTimeout ErrorCode = 10
+	// ErrorCodeUnexpectedResponse indicates an unexpected response from the server
+	ErrorCodeUnexpectedResponse ErrorCode = 11
+	// ErrorCodeInvalidState indicates an operation not allowed in the current state
+	ErrorCodeInvalidState ErrorCode = 12
+	// ErrorCodeWouldBlock indicates that the stream could not take a record before the deadline of
+	// TryIngestRecord, IngestRecordTimeout or their batch variants
+	ErrorCodeWouldBlock ErrorCode = 13
+	// ErrorCodeWaitTimedOut indicates that RecordAck.AwaitTimeout gave up while the ack is still pending,
+	// or that RecreateStream gave up waiting for the old stream's requests to settle
+	ErrorCodeWaitTimedOut ErrorCode = 14
+	// ErrorCodeSpilled indicates that the stream failed but the records are kept in its SpillDir
+	// and sent by RecreateStream; they must not be ingested again
+	ErrorCodeSpilled ErrorCode = 15
+	// ErrorCodeSpillFull indicates that SpillDir reached SpillMaxBytes
+	ErrorCodeSpillFull ErrorCode = 16
+)
+
 // ZerobusError represents an error from the Zerobus SDK
 type ZerobusError struct {
 	Message     string
 	IsRetryable bool
+	Code        ErrorCode
 }
 
 func (e *ZerobusError) Error() string {
@@ -22,6 +22,7 @@ typedef struct CResult {
     bool success;
     char *error_message;
     bool is_retryable;
+    int32_t error_code;
 } CResult;
 
 // Define headers types for callback
@@ -38,6 +39,12 @@ typedef struct CHeaders {
 
 typedef struct CHeaders (*HeadersProviderCallback)(void *user_data);
 
+// Define acknowledgment callback type
+typedef void (*AckCallback)(void *user_data, uint64_t ack_id, int64_t offset, const CResult *result);
+
+// Define dead-letter callback type
+typedef void (*DeadLetterCallback)(void *user_data, uint64_t ack_id, int32_t record_type, const uint8_t *data, uintptr_t data_len, const CResult *result);
+
 // Define stream configuration options
 typedef struct CStreamConfigurationOptions {
     uintptr_t max_inflight_requests;
@@ -48,9 +55,89 @@ typedef struct CStreamConfigurationOptions {
     uint64_t server_lack_of_ack_timeout_ms;
     uint64_t flush_timeout_ms;
     int32_t record_type;
+    AckCallback ack_callback;
+    void *ack_callback_user_data;
+    bool fire_and_forget;
+    bool validate_records;
+    bool transcode_json;
+    const char *table_schema_json;
+    const char *spill_dir;
+    uint64_t spill_max_bytes;
+    DeadLetterCallback dead_letter_callback;
+    void *dead_letter_user_data;
+    const char *dead_letter_path;
 } CStreamConfigurationOptions;
 
+// Define log callback types
+typedef struct CLogField {
+    const char *key;
+    const char *value;
+} CLogField;
+
+typedef void (*LogCallback)(void *user_data,
+                            int32_t level,
+                            const char *target,
+                            const char *message,
+                            const CLogField *fields,
+                            uintptr_t field_count);
+
+// Define runtime configuration
+typedef struct CRuntimeConfig {
+    uintptr_t worker_threads;
+    const char *thread_name_prefix;
+    uintptr_t thread_stack_size;
+    uintptr_t max_blocking_threads;
+    bool current_thread;
+} CRuntimeConfig;
+
+// Define stream metrics snapshot
+typedef struct CStreamMetrics {
+    uint64_t records_ingested;
+    uint64_t bytes_ingested;
+    uint64_t records_acked;
+    uint64_t records_failed;
+    uint64_t records_inflight;
+    uint64_t ack_latency_avg_us;
+    uint64_t ack_latency_max_us;
+    uint64_t recoveries;
+} CStreamMetrics;
+
+// Define unacknowledged record types
+typedef struct CUnackedRecord {
+    uint64_t ack_id;
+    int32_t record_type;
+    uint8_t *data;
+    uintptr_t data_len;
+} CUnackedRecord;
+
+typedef struct CUnackedRecords {
+    struct CUnackedRecord *records;
+    uintptr_t count;
+} CUnackedRecords;
+
+// Define byte buffer type
+typedef struct CBytes {
+    uint8_t *data;
+    uintptr_t len;
+} CBytes;
+
+// Define ack ID mapping types
+typedef struct CAckIdMapping {
+    uint64_t old_ack_id;
+    uint64_t new_ack_id;
+} CAckIdMapping;
+
+typedef struct CAckIdMappings {
+    struct CAckIdMapping *mappings;
+    uintptr_t count;
+} CAckIdMappings;
+
 // Forward declare functions we need
+extern bool zerobus_runtime_init(const CRuntimeConfig* config, CResult* result);
+extern bool zerobus_set_log_callback(int32_t level,
+                                     LogCallback callback,
+                                     void* user_data,
+                                     CResult* result);
 extern CZerobusSdk* zerobus_sdk_new(const char* zerobus_endpoint,
                                      const char* unity_catalog_url,
                                      CResult* result);
@@ -73,16 +160,121 @@ extern CZerobusStream* zerobus_sdk_create_stream_with_headers_provider(
     void* user_data,
     const CStreamConfigurationOptions* options,
     CResult* result);
+extern CZerobusStream* zerobus_sdk_create_stream_from_descriptor_set(
+    CZerobusSdk* sdk,
+    const char* table_name,
+    const uint8_t* descriptor_set_bytes,
+    uintptr_t descriptor_set_len,
+    const char* message_name,
+    const char* client_id,
+    const char* client_secret,
+    const CStreamConfigurationOptions* options,
+    CResult* result);
+extern CZerobusStream* zerobus_sdk_create_stream_with_headers_provider_from_descriptor_set(
+    CZerobusSdk* sdk,
+    const char* table_name,
+    const uint8_t* descriptor_set_bytes,
+    uintptr_t descriptor_set_len,
+    const char* message_name,
+    HeadersProviderCallback headers_callback,
+    void* user_data,
+    const CStreamConfigurationOptions* options,
+    CResult* result);
+extern CZerobusStream* zerobus_sdk_recreate_stream(CZerobusSdk* sdk,
+                                                     CZerobusStream* old_stream,
+                                                     uint64_t settle_timeout_ms,
+                                                     CAckIdMappings* ack_id_mapping,
+                                                     CResult* result);
+extern void zerobus_free_ack_id_mappings(CAckIdMappings mappings);
 extern void zerobus_stream_free(CZerobusStream* stream);
 extern uint64_t zerobus_stream_ingest_proto_record(CZerobusStream* stream,
                                                      const uint8_t* data,
                                                      uintptr_t data_len,
                                                      CResult* result);
+extern uint8_t* zerobus_record_buffer_alloc(uintptr_t capacity);
+extern void zerobus_record_buffer_free(uint8_t* buffer, uintptr_t capacity);
+extern uint64_t zerobus_stream_ingest_proto_record_buffer(CZerobusStream* stream,
+                                                          uint8_t* buffer,
+                                                          uintptr_t len,
+                                                          uintptr_t capacity,
+                                                          CResult* result);
+extern uint64_t zerobus_stream_ingest_proto_record_timeout(CZerobusStream* stream,
+                                                           const uint8_t* data,
+                                                           uintptr_t data_len,
+                                                           uint64_t timeout_ms,
+                                                           CResult* result);
+extern uint64_t zerobus_stream_ingest_proto_record_buffer_timeout(CZerobusStream* stream,
+                                                                  uint8_t* buffer,
+                                                                  uintptr_t len,
+                                                                  uintptr_t capacity,
+                                                                  uint64_t timeout_ms,
+                                                                  CResult* result);
+extern uint64_t zerobus_stream_ingest_proto_batch(CZerobusStream* stream,
+                                                  const uint8_t* data,
+                                                  uintptr_t data_len,
+                                                  const uintptr_t* offsets,
+                                                  uintptr_t num_records,
+                                                  CResult* result);
+extern uint64_t zerobus_stream_ingest_json_batch(CZerobusStream* stream,
+                                                 const uint8_t* data,
+                                                 uintptr_t data_len,
+                                                 const uintptr_t* offsets,
+                                                 uintptr_t num_records,
+                                                 CResult* result);
+extern uint64_t zerobus_stream_ingest_proto_batch_timeout(CZerobusStream* stream,
+                                                          const uint8_t* data,
+                                                          uintptr_t data_len,
+                                                          const uintptr_t* offsets,
+                                                          uintptr_t num_records,
+                                                          uint64_t timeout_ms,
+                                                          CResult* result);
+extern uint64_t zerobus_stream_ingest_json_batch_timeout(CZerobusStream* stream,
+                                                         const uint8_t* data,
+                                                         uintptr_t data_len,
+                                                         const uintptr_t* offsets,
+                                                         uintptr_t num_records,
+                                                         uint64_t timeout_ms,
+                                                         CResult* result);
 extern uint64_t zerobus_stream_ingest_json_record(CZerobusStream* stream,
                                                     const char* json_data,
                                                     CResult* result);
-extern int64_t zerobus_stream_await_ack(uint64_t ack_id, CResult* result);
-extern int64_t zerobus_stream_try_get_ack(uint64_t ack_id, bool* is_ready, CResult* result);
+extern uint64_t zerobus_stream_ingest_json_record_bytes(CZerobusStream* stream,
+                                                        const uint8_t* json_data,
+                                                        uintptr_t json_len,
+                                                        CResult* result);
+extern uint64_t zerobus_stream_ingest_json_record_bytes_timeout(CZerobusStream* stream,
+                                                                const uint8_t* json_data,
+                                                                uintptr_t json_len,
+                                                                uint64_t timeout_ms,
+                                                                CResult* result);
+extern int64_t zerobus_stream_await_ack(CZerobusStream* stream, uint64_t ack_id, CResult* result);
+extern int64_t zerobus_stream_await_ack_timeout(CZerobusStream* stream,
+                                               uint64_t ack_id,
+                                               uint64_t timeout_ms,
+                                               CResult* result);
+extern bool zerobus_stream_await_acks(CZerobusStream* stream,
+                                      const uint64_t* ack_ids,
+                                      uintptr_t count,
+                                      int64_t* offsets_out,
+                                      CResult* results_out,
+                                      CResult* result);
+extern uint64_t zerobus_stream_await_any_ack(CZerobusStream* stream,
+                                             const uint64_t* ack_ids,
+                                             uintptr_t count,
+                                             int64_t* offset_out,
+                                             CResult* result);
+extern int64_t zerobus_stream_try_get_ack(CZerobusStream* stream,
+                                          uint64_t ack_id,
+                                          bool* is_ready,
+                                          CResult* result);
+extern uintptr_t zerobus_stream_get_outstanding_acks(CZerobusStream* stream);
+extern bool zerobus_stream_get_metrics(CZerobusStream* stream, CStreamMetrics* metrics, CResult* result);
+extern CUnackedRecords zerobus_stream_get_unacked_records(CZerobusStream* stream, CResult* result);
+extern void zerobus_free_unacked_records(CUnackedRecords records);
+extern CBytes zerobus_descriptor_proto_from_table_schema(const char* table_schema_json,
+                                                         const char* message_name,
+                                                         CResult* result);
+extern void zerobus_free_bytes(CBytes bytes);
 extern bool zerobus_stream_flush(CZerobusStream* stream, CResult* result);
 extern bool zerobus_stream_close(CZerobusStream* stream, CResult* result);
 extern void zerobus_free_error_message(char* error_message);
@@ -111,23 +303,99 @@ static CHeaders cHeadersCallback(void* userData) {
 static HeadersProviderCallback getHeadersCallback() {
     return (HeadersProviderCallback)cHeadersCallback;
 }
+
+// Forward declaration of Go log function
+extern void goLogEvent(void* userData, int32_t level, char* target, char* message, CLogField* fields, uintptr_t count);
+
+// C callback that matches the LogCallback signature
+static void cLogCallback(void* userData,
+                         int32_t level,
+                         const char* target,
+                         const char* message,
+                         const CLogField* fields,
+                         uintptr_t count) {
+    goLogEvent(userData, level, (char*)target, (char*)message, (CLogField*)fields, count);
+}
+
+// Helper function to get the C log callback function pointer
+static LogCallback getLogCallback() {
+    return (LogCallback)cLogCallback;
+}
+
+// Forward declaration of Go ack function
+extern void goAckCallback(void* userData, uint64_t ackID, int64_t offset, CResult* result);
+
+// C callback that matches the AckCallback signature
+static void cAckCallback(void* userData, uint64_t ackID, int64_t offset, const CResult* result) {
+    goAckCallback(userData, ackID, offset, (CResult*)result);
+}
+
+// Helper function to get the C ack callback function pointer
+static AckCallback getAckCallback() {
+    return (AckCallback)cAckCallback;
+}
+
+// Forward declaration of Go dead-letter function
+extern void goDeadLetterCallback(void* userData, uint64_t ackID, int32_t recordType, uint8_t* data, uintptr_t dataLen, CResult* result);
+
+// C callback that matches the DeadLetterCallback signature
+static void cDeadLetterCallback(void* userData, uint64_t ackID, int32_t recordType, const uint8_t* data, uintptr_t dataLen, const CResult* result) {
+    goDeadLetterCallback(userData, ackID, recordType, (uint8_t*)data, dataLen, (CResult*)result);
+}
+
+// Helper function to get the C dead-letter callback function pointer
+static DeadLetterCallback getDeadLetterCallback() {
+    return (DeadLetterCallback)cDeadLetterCallback;
+}
 */
 import "C"
 import (
 	"runtime/cgo"
 	"sync"
+	"time"
 	"unsafe"
 )
 
 // Registry to map stream pointers to their handles for cleanup
 // This allows us to properly release cgo.Handle when streams are freed
+// A recreated stream shares the headers provider of the old one, so the handle is released once
+// the last stream using it has been freed
 var (
-	streamHandleRegistry   = make(map[unsafe.Pointer]cgo.Handle)
+	streamHandleRegistry   = make(map[unsafe.Pointer]*sharedHandle)
 	streamHandleRegistryMu sync.Mutex
 )
 
-// ffiResult converts a C.CResult to a Go error
+// Registry to map stream pointers to the handle of their Go callbacks
+// A recreated stream shares its handlers with the old one, so the handle is released once the
+// last stream using it has been freed
+var (
+	streamCallbackRegistry   = make(map[unsafe.Pointer]*sharedHandle)
+	streamCallbackRegistryMu sync.Mutex
+)
+
+// sharedHandle is a cgo.Handle used by several streams
+type sharedHandle struct {
+	handle cgo.Handle
+	refs   int
+}
+
+// streamCallbacks holds the Go handlers a stream's native callbacks are forwarded to
+type streamCallbacks struct {
+	onAck        AckHandler
+	onDeadLetter DeadLetterHandler
+}
+
+// ffiResult converts a C.CResult to a Go error, releasing its error message
 func ffiResult(cres C.CResult) error {
+	err := borrowedResult(cres)
+	if cres.error_message != nil {
+		C.zerobus_free_error_message(cres.error_message)
+	}
+	return err
+}
+
+// borrowedResult converts a C.CResult owned by the native library to a Go error
+func borrowedResult(cres C.CResult) error {
 	if cres.success {
 		return nil
 	}
@@ -135,7 +403,6 @@ func ffiResult(cres C.CResult) error {
 	var message string
 	if cres.error_message != nil {
 		message = C.GoString(cres.error_message)
-		C.zerobus_free_error_message(cres.error_message)
 	} else {
 		message = "unknown error"
 	}
@@ -143,16 +410,18 @@ func ffiResult(cres C.CResult) error {
 	return &ZerobusError{
 		Message:     message,
 		IsRetryable: bool(cres.is_retryable),
+		Code:        ErrorCode(cres.error_code),
 	}
 }
 
 // convertConfigToC converts Go config to C config
+// The result must be released with freeConfigC once the stream is created
 func convertConfigToC(opts *StreamConfigurationOptions) C.CStreamConfigurationOptions {
 	if opts == nil {
 		return C.zerobus_get_default_config()
 	}
 
-	return C.CStreamConfigurationOptions{
+	cOpts := C.CStreamConfigurationOptions{
 		max_inflight_requests:         C.size_t(opts.MaxInflightRequests),
 		recovery:                      C.bool(opts.Recovery),
 		recovery_timeout_ms:           C.uint64_t(opts.RecoveryTimeoutMs),
@@ -161,7 +430,212 @@ func convertConfigToC(opts *StreamConfigurationOptions) C.CStreamConfigurationOp
 		server_lack_of_ack_timeout_ms: C.uint64_t(opts.ServerLackOfAckTimeoutMs),
 		flush_timeout_ms:              C.uint64_t(opts.FlushTimeoutMs),
 		record_type:                   C.int(opts.RecordType),
+		fire_and_forget:               C.bool(opts.FireAndForget),
+		validate_records:              C.bool(opts.ValidateRecords),
+		transcode_json:                C.bool(opts.TranscodeJSON),
+		spill_max_bytes:               C.uint64_t(opts.SpillMaxBytes),
+	}
+	if opts.TableSchemaJSON != "" {
+		cOpts.table_schema_json = C.CString(opts.TableSchemaJSON)
+	}
+	if opts.SpillDir != "" {
+		cOpts.spill_dir = C.CString(opts.SpillDir)
+	}
+	if opts.DeadLetterFile != "" {
+		cOpts.dead_letter_path = C.CString(opts.DeadLetterFile)
+	}
+	return cOpts
+}
+
+// setCallbacksC points the native callbacks of cOpts at the Go handlers set in opts
+// Returns the handle passed as their user data, 0 if no handler is set; it must be released
+// with releaseStreamCallbacks once the stream is freed, or deleted if no stream was created
+func setCallbacksC(cOpts *C.CStreamConfigurationOptions, opts *StreamConfigurationOptions) cgo.Handle {
+	if opts == nil || (opts.AckHandler == nil && opts.DeadLetterHandler == nil) {
+		return 0
+	}
+
+	handle := cgo.NewHandle(&streamCallbacks{
+		onAck:        opts.AckHandler,
+		onDeadLetter: opts.DeadLetterHandler,
+	})
+	handlePtr := *(*unsafe.Pointer)(unsafe.Pointer(&handle))
+	if opts.AckHandler != nil {
+		cOpts.ack_callback = C.getAckCallback()
+		cOpts.ack_callback_user_data = handlePtr
+	}
+	if opts.DeadLetterHandler != nil {
+		cOpts.dead_letter_callback = C.getDeadLetterCallback()
+		cOpts.dead_letter_user_data = handlePtr
+	}
+	return handle
+}
+
+// registerStreamCallbacks keeps a stream's callback handle until the stream is freed
+func registerStreamCallbacks(streamPtr unsafe.Pointer, handle cgo.Handle) {
+	if handle == 0 {
+		return
+	}
+	streamCallbackRegistryMu.Lock()
+	streamCallbackRegistry[streamPtr] = &sharedHandle{handle: handle, refs: 1}
+	streamCallbackRegistryMu.Unlock()
+}
+
+// shareStreamCallbacks lets a recreated stream use the callback handle of the old stream
+func shareStreamCallbacks(oldStreamPtr, newStreamPtr unsafe.Pointer) {
+	shareHandle(streamCallbackRegistry, &streamCallbackRegistryMu, oldStreamPtr, newStreamPtr)
+}
+
+// releaseStreamCallbacks drops a freed stream's reference to its callback handle
+func releaseStreamCallbacks(streamPtr unsafe.Pointer) {
+	releaseHandle(streamCallbackRegistry, &streamCallbackRegistryMu, streamPtr)
+}
+
+// shareHandle registers the handle of the old stream for the new one as well
+func shareHandle(registry map[unsafe.Pointer]*sharedHandle, mu *sync.Mutex, oldStreamPtr, newStreamPtr unsafe.Pointer) {
+	mu.Lock()
+	if shared, exists := registry[oldStreamPtr]; exists {
+		shared.refs++
+		registry[newStreamPtr] = shared
+	}
+	mu.Unlock()
+}
+
+// releaseHandle drops a freed stream's reference, deleting the handle with the last one
+func releaseHandle(registry map[unsafe.Pointer]*sharedHandle, mu *sync.Mutex, streamPtr unsafe.Pointer) {
+	mu.Lock()
+	if shared, exists := registry[streamPtr]; exists {
+		delete(registry, streamPtr)
+		shared.refs--
+		if shared.refs == 0 {
+			shared.handle.Delete()
+		}
+	}
+	mu.Unlock()
+}
+
+//export goAckCallback
+func goAckCallback(userData unsafe.Pointer, ackID C.uint64_t, offset C.int64_t, result *C.CResult) {
+	callbacks, ok := cgo.Handle(userData).Value().(*streamCallbacks)
+	if !ok || callbacks.onAck == nil {
+		return
+	}
+
+	// The result is only borrowed for the duration of the call
+	callbacks.onAck(uint64(ackID), int64(offset), borrowedResult(*result))
+}
+
+//export goDeadLetterCallback
+func goDeadLetterCallback(userData unsafe.Pointer, ackID C.uint64_t, recordType C.int32_t, data *C.uint8_t, dataLen C.uintptr_t, result *C.CResult) {
+	callbacks, ok := cgo.Handle(userData).Value().(*streamCallbacks)
+	if !ok || callbacks.onDeadLetter == nil {
+		return
+	}
+
+	// The record and result are only borrowed for the duration of the call
+	callbacks.onDeadLetter(DeadLetter{
+		AckID:      uint64(ackID),
+		RecordType: RecordType(recordType),
+		Payload:    C.GoBytes(unsafe.Pointer(data), C.int(dataLen)),
+		Err:        borrowedResult(*result),
+	})
+}
+
+// freeConfigC releases the strings allocated by convertConfigToC
+func freeConfigC(cOpts *C.CStreamConfigurationOptions) {
+	if cOpts.table_schema_json != nil {
+		C.free(unsafe.Pointer(cOpts.table_schema_json))
+	}
+	if cOpts.spill_dir != nil {
+		C.free(unsafe.Pointer(cOpts.spill_dir))
+	}
+	if cOpts.dead_letter_path != nil {
+		C.free(unsafe.Pointer(cOpts.dead_letter_path))
+	}
+}
+
+// runtimeInit configures the global runtime of the FFI layer
+func runtimeInit(config RuntimeConfig) error {
+	cConfig := C.CRuntimeConfig{
+		worker_threads:       C.size_t(config.WorkerThreads),
+		thread_stack_size:    C.size_t(config.ThreadStackSize),
+		max_blocking_threads: C.size_t(config.MaxBlockingThreads),
+		current_thread:       C.bool(config.CurrentThread),
+	}
+	if config.ThreadNamePrefix != "" {
+		cConfig.thread_name_prefix = C.CString(config.ThreadNamePrefix)
+		defer C.free(unsafe.Pointer(cConfig.thread_name_prefix))
+	}
+
+	var cres C.CResult
+	if !C.zerobus_runtime_init(&cConfig, &cres) {
+		return ffiResult(cres)
+	}
+
+	return nil
+}
+
+// logHandle is the handle of the LogHandler passed as user data of the FFI log callback
+var (
+	logHandle   cgo.Handle
+	logHandleMu sync.RWMutex
+)
+
+//export goLogEvent
+func goLogEvent(userData unsafe.Pointer, level C.int32_t, target *C.char, message *C.char, fields *C.CLogField, count C.uintptr_t) {
+	// Events already being delivered when the handler was replaced carry the deleted handle
+	logHandleMu.RLock()
+	var handler LogHandler
+	if handle := cgo.Handle(userData); handle != 0 && handle == logHandle {
+		handler, _ = handle.Value().(LogHandler)
+	}
+	logHandleMu.RUnlock()
+
+	if handler == nil {
+		return
+	}
+
+	entry := LogEntry{
+		Level:   LogLevel(level),
+		Target:  C.GoString(target),
+		Message: C.GoString(message),
+	}
+	if count > 0 {
+		entry.Fields = make(map[string]string, int(count))
+		for _, field := range unsafe.Slice(fields, int(count)) {
+			entry.Fields[C.GoString(field.key)] = C.GoString(field.value)
+		}
+	}
+
+	handler(entry)
+}
+
+// setLogCallback installs or removes the FFI log callback
+func setLogCallback(level LogLevel, handler LogHandler) error {
+	var callback C.LogCallback
+	var handle cgo.Handle
+	if handler != nil {
+		callback = C.getLogCallback()
+		handle = cgo.NewHandle(handler)
+	}
+	handlePtr := *(*unsafe.Pointer)(unsafe.Pointer(&handle))
+
+	logHandleMu.Lock()
+	defer logHandleMu.Unlock()
+
+	var cres C.CResult
+	if !C.zerobus_set_log_callback(C.int32_t(level), callback, handlePtr, &cres) {
+		if handle != 0 {
+			handle.Delete()
+		}
+		return ffiResult(cres)
+	}
+
+	if logHandle != 0 {
+		logHandle.Delete()
 	}
+	logHandle = handle
+	return nil
 }
 
 // sdkNew creates a new SDK instance via FFI
@@ -197,13 +671,12 @@ func sdkFree(ptr unsafe.Pointer) {
 // sdkCreateStream creates a stream via FFI
 func sdkCreateStream(
 	sdkPtr unsafe.Pointer,
-	tableName string,
-	descriptorProto []byte,
+	tableProps TableProperties,
 	clientID string,
 	clientSecret string,
 	options *StreamConfigurationOptions,
 ) (unsafe.Pointer, error) {
-	cTableName := C.CString(tableName)
+	cTableName := C.CString(tableProps.TableName)
 	defer C.free(unsafe.Pointer(cTableName))
 
 	cClientID := C.CString(clientID)
@@ -212,31 +685,49 @@ func sdkCreateStream(
 	cClientSecret := C.CString(clientSecret)
 	defer C.free(unsafe.Pointer(cClientSecret))
 
-	var cDescriptor *C.uint8_t
-	var descriptorLen C.size_t
-
-	if len(descriptorProto) > 0 {
-		cDescriptor = (*C.uint8_t)(unsafe.Pointer(&descriptorProto[0]))
-		descriptorLen = C.size_t(len(descriptorProto))
-	}
-
 	cOpts := convertConfigToC(options)
+	defer freeConfigC(&cOpts)
+	callbacks := setCallbacksC(&cOpts, options)
 
 	var cres C.CResult
-	ptr := C.zerobus_sdk_create_stream(
-		(*C.CZerobusSdk)(sdkPtr),
-		cTableName,
-		cDescriptor,
-		descriptorLen,
-		cClientID,
-		cClientSecret,
-		&cOpts,
-		&cres,
-	)
+	var ptr *C.CZerobusStream
+
+	if len(tableProps.DescriptorSet) > 0 {
+		cMessageName := C.CString(tableProps.MessageName)
+		defer C.free(unsafe.Pointer(cMessageName))
+
+		ptr = C.zerobus_sdk_create_stream_from_descriptor_set(
+			(*C.CZerobusSdk)(sdkPtr),
+			cTableName,
+			(*C.uint8_t)(unsafe.Pointer(&tableProps.DescriptorSet[0])),
+			C.size_t(len(tableProps.DescriptorSet)),
+			cMessageName,
+			cClientID,
+			cClientSecret,
+			&cOpts,
+			&cres,
+		)
+	} else {
+		cDescriptor, descriptorLen := descriptorToC(tableProps.DescriptorProto)
+		ptr = C.zerobus_sdk_create_stream(
+			(*C.CZerobusSdk)(sdkPtr),
+			cTableName,
+			cDescriptor,
+			descriptorLen,
+			cClientID,
+			cClientSecret,
+			&cOpts,
+			&cres,
+		)
+	}
 
 	if ptr == nil {
+		if callbacks != 0 {
+			callbacks.Delete()
+		}
 		return nil, ffiResult(cres)
 	}
+	registerStreamCallbacks(unsafe.Pointer(ptr), callbacks)
 
 	return unsafe.Pointer(ptr), nil
 }
@@ -287,25 +778,24 @@ func goGetHeaders(userData unsafe.Pointer, headers **C.CHeader, count *C.uintptr
 	*errorMsg = nil
 }
 
+// descriptorToC passes a serialized DescriptorProto to C (NULL when empty)
+func descriptorToC(descriptorProto []byte) (*C.uint8_t, C.size_t) {
+	if len(descriptorProto) == 0 {
+		return nil, 0
+	}
+	return (*C.uint8_t)(unsafe.Pointer(&descriptorProto[0])), C.size_t(len(descriptorProto))
+}
+
 // sdkCreateStreamWithHeadersProvider creates a stream with custom headers provider via FFI
 func sdkCreateStreamWithHeadersProvider(
 	sdkPtr unsafe.Pointer,
-	tableName string,
-	descriptorProto []byte,
+	tableProps TableProperties,
 	headersProvider HeadersProvider,
 	options *StreamConfigurationOptions,
 ) (unsafe.Pointer, error) {
-	cTableName := C.CString(tableName)
+	cTableName := C.CString(tableProps.TableName)
 	defer C.free(unsafe.Pointer(cTableName))
 
-	var cDescriptor *C.uint8_t
-	var descriptorLen C.size_t
-
-	if len(descriptorProto) > 0 {
-		cDescriptor = (*C.uint8_t)(unsafe.Pointer(&descriptorProto[0]))
-		descriptorLen = C.size_t(len(descriptorProto))
-	}
-
 	// Create a cgo.Handle for the provider
 	// This keeps it alive and gives us a safe uintptr to pass to C
 	handle := cgo.NewHandle(headersProvider)
@@ -313,45 +803,99 @@ func sdkCreateStreamWithHeadersProvider(
 	handlePtr := *(*unsafe.Pointer)(unsafe.Pointer(&handle))
 
 	cOpts := convertConfigToC(options)
+	defer freeConfigC(&cOpts)
+	callbacks := setCallbacksC(&cOpts, options)
 
 	var cres C.CResult
-	ptr := C.zerobus_sdk_create_stream_with_headers_provider(
-		(*C.CZerobusSdk)(sdkPtr),
-		cTableName,
-		cDescriptor,
-		descriptorLen,
-		C.getHeadersCallback(),
-		handlePtr,
-		&cOpts,
-		&cres,
-	)
+	var ptr *C.CZerobusStream
+
+	if len(tableProps.DescriptorSet) > 0 {
+		cMessageName := C.CString(tableProps.MessageName)
+		defer C.free(unsafe.Pointer(cMessageName))
+
+		ptr = C.zerobus_sdk_create_stream_with_headers_provider_from_descriptor_set(
+			(*C.CZerobusSdk)(sdkPtr),
+			cTableName,
+			(*C.uint8_t)(unsafe.Pointer(&tableProps.DescriptorSet[0])),
+			C.size_t(len(tableProps.DescriptorSet)),
+			cMessageName,
+			C.getHeadersCallback(),
+			handlePtr,
+			&cOpts,
+			&cres,
+		)
+	} else {
+		cDescriptor, descriptorLen := descriptorToC(tableProps.DescriptorProto)
+		ptr = C.zerobus_sdk_create_stream_with_headers_provider(
+			(*C.CZerobusSdk)(sdkPtr),
+			cTableName,
+			cDescriptor,
+			descriptorLen,
+			C.getHeadersCallback(),
+			handlePtr,
+			&cOpts,
+			&cres,
+		)
+	}
 
 	if ptr == nil {
 		// Clean up handle on error
 		handle.Delete()
+		if callbacks != 0 {
+			callbacks.Delete()
+		}
 		return nil, ffiResult(cres)
 	}
+	registerStreamCallbacks(unsafe.Pointer(ptr), callbacks)
 
 	// Store the handle so we can clean it up when the stream is freed
 	streamHandleRegistryMu.Lock()
-	streamHandleRegistry[unsafe.Pointer(ptr)] = handle
+	streamHandleRegistry[unsafe.Pointer(ptr)] = &sharedHandle{handle: handle, refs: 1}
 	streamHandleRegistryMu.Unlock()
 
 	return unsafe.Pointer(ptr), nil
 }
 
+// sdkRecreateStream opens a replacement for a failed stream via FFI
+// Returns the new stream and the new ack ID of every re-ingested batch, keyed by its old ack ID
+func sdkRecreateStream(sdkPtr unsafe.Pointer, oldStreamPtr unsafe.Pointer, settleTimeout time.Duration) (unsafe.Pointer, map[uint64]uint64, error) {
+	var cMappings C.CAckIdMappings
+	var cres C.CResult
+	ptr := C.zerobus_sdk_recreate_stream(
+		(*C.CZerobusSdk)(sdkPtr),
+		(*C.CZerobusStream)(oldStreamPtr),
+		C.uint64_t(timeoutMillis(settleTimeout)),
+		&cMappings,
+		&cres,
+	)
+
+	if ptr == nil {
+		return nil, nil, ffiResult(cres)
+	}
+	defer C.zerobus_free_ack_id_mappings(cMappings)
+
+	mappings := make(map[uint64]uint64, int(cMappings.count))
+	if cMappings.count > 0 {
+		for _, m := range unsafe.Slice(cMappings.mappings, int(cMappings.count)) {
+			mappings[uint64(m.old_ack_id)] = uint64(m.new_ack_id)
+		}
+	}
+
+	// Both streams may invoke the headers provider and the callbacks until they are freed
+	shareHandle(streamHandleRegistry, &streamHandleRegistryMu, oldStreamPtr, unsafe.Pointer(ptr))
+	shareStreamCallbacks(oldStreamPtr, unsafe.Pointer(ptr))
+
+	return unsafe.Pointer(ptr), mappings, nil
+}
+
 // streamFree frees a stream instance
 func streamFree(ptr unsafe.Pointer) {
 	if ptr != nil {
-		// Clean up the associated handle BEFORE freeing the stream
-		streamHandleRegistryMu.Lock()
-		if handle, exists := streamHandleRegistry[ptr]; exists {
-			handle.Delete() // This releases the Go object reference
-			delete(streamHandleRegistry, ptr)
-		}
-		streamHandleRegistryMu.Unlock()
-
 		C.zerobus_stream_free((*C.CZerobusStream)(ptr))
+		// Freeing stops the stream's calls into the headers provider and the callbacks; a stream
+		// recreated from this one keeps their handles alive
+		releaseHandle(streamHandleRegistry, &streamHandleRegistryMu, ptr)
+		releaseStreamCallbacks(ptr)
 	}
 }
 
@@ -359,7 +903,7 @@ func streamFree(ptr unsafe.Pointer) {
 // Returns an acknowledgment ID
 func streamIngestProtoRecord(streamPtr unsafe.Pointer, data []byte) (uint64, error) {
 	if len(data) == 0 {
-		return 0, &ZerobusError{Message: "empty data", IsRetryable: false}
+		return 0, &ZerobusError{Message: "empty data", Code: ErrorCodeInvalidArgument}
 	}
 
 	cData := (*C.uint8_t)(unsafe.Pointer(&data[0]))
@@ -373,7 +917,39 @@ func streamIngestProtoRecord(streamPtr unsafe.Pointer, data []byte) (uint64, err
 		&cres,
 	)
 
-	if ackID == 0 {
+	// Fire-and-forget streams return ack ID 0 on success, so rely on the result flag
+	if !cres.success {
+		return 0, ffiResult(cres)
+	}
+
+	return uint64(ackID), nil
+}
+
+// recordBufferAlloc allocates native memory for a record, nil if capacity is 0
+func recordBufferAlloc(capacity int) unsafe.Pointer {
+	return unsafe.Pointer(C.zerobus_record_buffer_alloc(C.size_t(capacity)))
+}
+
+// recordBufferFree releases a record buffer that was not ingested
+func recordBufferFree(buffer unsafe.Pointer, capacity int) {
+	C.zerobus_record_buffer_free((*C.uint8_t)(buffer), C.size_t(capacity))
+}
+
+// streamIngestProtoRecordBuffer hands a record buffer over to the stream without copying it
+// The buffer is owned by the native library afterwards, also when an error is returned
+// Returns an acknowledgment ID
+func streamIngestProtoRecordBuffer(streamPtr unsafe.Pointer, buffer unsafe.Pointer, length, capacity int) (uint64, error) {
+	var cres C.CResult
+	ackID := C.zerobus_stream_ingest_proto_record_buffer(
+		(*C.CZerobusStream)(streamPtr),
+		(*C.uint8_t)(buffer),
+		C.size_t(length),
+		C.size_t(capacity),
+		&cres,
+	)
+
+	// Fire-and-forget streams return ack ID 0 on success, so rely on the result flag
+	if !cres.success {
 		return 0, ffiResult(cres)
 	}
 
@@ -381,29 +957,194 @@ func streamIngestProtoRecord(streamPtr unsafe.Pointer, data []byte) (uint64, err
 }
 
 // streamIngestJSONRecord ingests a JSON record
+// The string's bytes are passed to the FFI layer in place, without a C copy
 // Returns an acknowledgment ID
 func streamIngestJSONRecord(streamPtr unsafe.Pointer, jsonData string) (uint64, error) {
-	cJSON := C.CString(jsonData)
-	defer C.free(unsafe.Pointer(cJSON))
+	var cJSON *C.uint8_t
+	if len(jsonData) > 0 {
+		cJSON = (*C.uint8_t)(unsafe.Pointer(unsafe.StringData(jsonData)))
+	}
 
 	var cres C.CResult
-	ackID := C.zerobus_stream_ingest_json_record(
+	ackID := C.zerobus_stream_ingest_json_record_bytes(
 		(*C.CZerobusStream)(streamPtr),
 		cJSON,
+		C.size_t(len(jsonData)),
 		&cres,
 	)
 
-	if ackID == 0 {
+	// Fire-and-forget streams return ack ID 0 on success, so rely on the result flag
+	if !cres.success {
+		return 0, ffiResult(cres)
+	}
+
+	return uint64(ackID), nil
+}
+
+// streamIngestProtoRecordTimeout ingests a protobuf record, waiting at most timeout for a saturated stream
+// Returns an acknowledgment ID
+func streamIngestProtoRecordTimeout(streamPtr unsafe.Pointer, data []byte, timeout time.Duration) (uint64, error) {
+	if len(data) == 0 {
+		return 0, &ZerobusError{Message: "empty data", Code: ErrorCodeInvalidArgument}
+	}
+
+	var cres C.CResult
+	ackID := C.zerobus_stream_ingest_proto_record_timeout(
+		(*C.CZerobusStream)(streamPtr),
+		(*C.uint8_t)(unsafe.Pointer(&data[0])),
+		C.size_t(len(data)),
+		C.uint64_t(timeoutMillis(timeout)),
+		&cres,
+	)
+
+	if !cres.success {
+		return 0, ffiResult(cres)
+	}
+
+	return uint64(ackID), nil
+}
+
+// streamIngestProtoRecordBufferTimeout ingests a record buffer, waiting at most timeout for a
+// saturated stream. The buffer is handed over unless ErrorCodeWouldBlock is returned
+// Returns an acknowledgment ID
+func streamIngestProtoRecordBufferTimeout(streamPtr unsafe.Pointer, buffer unsafe.Pointer, length, capacity int, timeout time.Duration) (uint64, error) {
+	var cres C.CResult
+	ackID := C.zerobus_stream_ingest_proto_record_buffer_timeout(
+		(*C.CZerobusStream)(streamPtr),
+		(*C.uint8_t)(buffer),
+		C.size_t(length),
+		C.size_t(capacity),
+		C.uint64_t(timeoutMillis(timeout)),
+		&cres,
+	)
+
+	if !cres.success {
+		return 0, ffiResult(cres)
+	}
+
+	return uint64(ackID), nil
+}
+
+// streamIngestJSONRecordTimeout ingests a JSON record, waiting at most timeout for a saturated stream
+// Returns an acknowledgment ID
+func streamIngestJSONRecordTimeout(streamPtr unsafe.Pointer, jsonData string, timeout time.Duration) (uint64, error) {
+	var cJSON *C.uint8_t
+	if len(jsonData) > 0 {
+		cJSON = (*C.uint8_t)(unsafe.Pointer(unsafe.StringData(jsonData)))
+	}
+
+	var cres C.CResult
+	ackID := C.zerobus_stream_ingest_json_record_bytes_timeout(
+		(*C.CZerobusStream)(streamPtr),
+		cJSON,
+		C.size_t(len(jsonData)),
+		C.uint64_t(timeoutMillis(timeout)),
+		&cres,
+	)
+
+	if !cres.success {
+		return 0, ffiResult(cres)
+	}
+
+	return uint64(ackID), nil
+}
+
+// streamIngestBatch queues records as a single request
+// data holds the records back to back, record i spanning data[offsets[i]:offsets[i+1]]
+// Returns one acknowledgment ID for the whole batch
+func streamIngestBatch(streamPtr unsafe.Pointer, data []byte, offsets []uintptr, isJSON bool) (uint64, error) {
+	var cData *C.uint8_t
+	if len(data) > 0 {
+		cData = (*C.uint8_t)(unsafe.Pointer(&data[0]))
+	}
+	cOffsets := (*C.uintptr_t)(unsafe.Pointer(&offsets[0]))
+	numRecords := C.uintptr_t(len(offsets) - 1)
+
+	var cres C.CResult
+	var ackID C.uint64_t
+	if isJSON {
+		ackID = C.zerobus_stream_ingest_json_batch(
+			(*C.CZerobusStream)(streamPtr),
+			cData,
+			C.uintptr_t(len(data)),
+			cOffsets,
+			numRecords,
+			&cres,
+		)
+	} else {
+		ackID = C.zerobus_stream_ingest_proto_batch(
+			(*C.CZerobusStream)(streamPtr),
+			cData,
+			C.uintptr_t(len(data)),
+			cOffsets,
+			numRecords,
+			&cres,
+		)
+	}
+
+	// Fire-and-forget streams return ack ID 0 on success, so rely on the result flag
+	if !cres.success {
+		return 0, ffiResult(cres)
+	}
+
+	return uint64(ackID), nil
+}
+
+// streamIngestBatchTimeout queues records as a single request like streamIngestBatch, waiting
+// at most timeout for a saturated stream
+// Returns one acknowledgment ID for the whole batch
+func streamIngestBatchTimeout(streamPtr unsafe.Pointer, data []byte, offsets []uintptr, isJSON bool, timeout time.Duration) (uint64, error) {
+	var cData *C.uint8_t
+	if len(data) > 0 {
+		cData = (*C.uint8_t)(unsafe.Pointer(&data[0]))
+	}
+	cOffsets := (*C.uintptr_t)(unsafe.Pointer(&offsets[0]))
+	numRecords := C.uintptr_t(len(offsets) - 1)
+
+	var cres C.CResult
+	var ackID C.uint64_t
+	if isJSON {
+		ackID = C.zerobus_stream_ingest_json_batch_timeout(
+			(*C.CZerobusStream)(streamPtr),
+			cData,
+			C.uintptr_t(len(data)),
+			cOffsets,
+			numRecords,
+			C.uint64_t(timeoutMillis(timeout)),
+			&cres,
+		)
+	} else {
+		ackID = C.zerobus_stream_ingest_proto_batch_timeout(
+			(*C.CZerobusStream)(streamPtr),
+			cData,
+			C.uintptr_t(len(data)),
+			cOffsets,
+			numRecords,
+			C.uint64_t(timeoutMillis(timeout)),
+			&cres,
+		)
+	}
+
+	if !cres.success {
 		return 0, ffiResult(cres)
 	}
 
 	return uint64(ackID), nil
 }
 
+// timeoutMillis converts a timeout to whole milliseconds, rounding up so short timeouts still wait
+func timeoutMillis(timeout time.Duration) uint64 {
+	if timeout <= 0 {
+		return 0
+	}
+	return uint64((timeout + time.Millisecond - 1) / time.Millisecond)
+}
+
 // streamAwaitAck waits for an acknowledgment and returns the offset
-func streamAwaitAck(ackID uint64) (int64, error) {
+func streamAwaitAck(streamPtr unsafe.Pointer, ackID uint64) (int64, error) {
 	var cres C.CResult
 	offset := C.zerobus_stream_await_ack(
+		(*C.CZerobusStream)(streamPtr),
 		C.uint64_t(ackID),
 		&cres,
 	)
@@ -415,12 +1156,77 @@ func streamAwaitAck(ackID uint64) (int64, error) {
 	return int64(offset), nil
 }
 
+// streamAwaitAckTimeout waits at most timeout for an acknowledgment and returns the offset
+// The ack ID stays valid if the wait times out (ErrorCodeWaitTimedOut)
+func streamAwaitAckTimeout(streamPtr unsafe.Pointer, ackID uint64, timeout time.Duration) (int64, error) {
+	var cres C.CResult
+	offset := C.zerobus_stream_await_ack_timeout(
+		(*C.CZerobusStream)(streamPtr),
+		C.uint64_t(ackID),
+		C.uint64_t(timeoutMillis(timeout)),
+		&cres,
+	)
+
+	if offset < 0 {
+		return -1, ffiResult(cres)
+	}
+
+	return int64(offset), nil
+}
+
+// streamAwaitAcks waits for several acknowledgments in one call
+// Returns the offset (-1 on failure) and error of each ack ID
+func streamAwaitAcks(streamPtr unsafe.Pointer, ackIDs []uint64) ([]int64, []error, error) {
+	offsets := make([]int64, len(ackIDs))
+	results := make([]C.CResult, len(ackIDs))
+
+	var cres C.CResult
+	C.zerobus_stream_await_acks(
+		(*C.CZerobusStream)(streamPtr),
+		(*C.uint64_t)(unsafe.Pointer(&ackIDs[0])),
+		C.size_t(len(ackIDs)),
+		(*C.int64_t)(unsafe.Pointer(&offsets[0])),
+		&results[0],
+		&cres,
+	)
+
+	if !cres.success {
+		return nil, nil, ffiResult(cres)
+	}
+
+	errs := make([]error, len(ackIDs))
+	for i := range results {
+		errs[i] = ffiResult(results[i])
+	}
+	return offsets, errs, nil
+}
+
+// streamAwaitAnyAck waits for the first of several acknowledgments to complete
+// Returns the completed ack ID with its offset and error, or a call error if nothing was awaited
+func streamAwaitAnyAck(streamPtr unsafe.Pointer, ackIDs []uint64) (uint64, int64, error, error) {
+	var offset C.int64_t
+	var cres C.CResult
+	ackID := C.zerobus_stream_await_any_ack(
+		(*C.CZerobusStream)(streamPtr),
+		(*C.uint64_t)(unsafe.Pointer(&ackIDs[0])),
+		C.size_t(len(ackIDs)),
+		&offset,
+		&cres,
+	)
+
+	if ackID == 0 {
+		return 0, -1, nil, ffiResult(cres)
+	}
+	return uint64(ackID), int64(offset), ffiResult(cres), nil
+}
+
 // streamTryGetAck tries to get an acknowledgment without blocking
-func streamTryGetAck(ackID uint64) (int64, error, bool) {
+func streamTryGetAck(streamPtr unsafe.Pointer, ackID uint64) (int64, error, bool) {
 	var cres C.CResult
 	var isReady C.bool
 
 	offset := C.zerobus_stream_try_get_ack(
+		(*C.CZerobusStream)(streamPtr),
 		C.uint64_t(ackID),
 		&isReady,
 		&cres,
@@ -440,6 +1246,78 @@ func streamTryGetAck(ackID uint64) (int64, error, bool) {
 	return int64(offset), nil, true
 }
 
+// streamOutstandingAcks returns the number of acknowledgments not yet collected
+func streamOutstandingAcks(streamPtr unsafe.Pointer) int {
+	return int(C.zerobus_stream_get_outstanding_acks((*C.CZerobusStream)(streamPtr)))
+}
+
+// streamGetMetrics returns a snapshot of the stream's counters
+func streamGetMetrics(streamPtr unsafe.Pointer) (StreamMetrics, error) {
+	var cMetrics C.CStreamMetrics
+	var cres C.CResult
+
+	if !C.zerobus_stream_get_metrics((*C.CZerobusStream)(streamPtr), &cMetrics, &cres) {
+		return StreamMetrics{}, ffiResult(cres)
+	}
+
+	return StreamMetrics{
+		RecordsIngested: uint64(cMetrics.records_ingested),
+		BytesIngested:   uint64(cMetrics.bytes_ingested),
+		RecordsAcked:    uint64(cMetrics.records_acked),
+		RecordsFailed:   uint64(cMetrics.records_failed),
+		RecordsInflight: uint64(cMetrics.records_inflight),
+		AckLatencyAvg:   time.Duration(cMetrics.ack_latency_avg_us) * time.Microsecond,
+		AckLatencyMax:   time.Duration(cMetrics.ack_latency_max_us) * time.Microsecond,
+		Recoveries:      uint64(cMetrics.recoveries),
+	}, nil
+}
+
+// descriptorProtoFromTableSchema generates the encoded DescriptorProto of a table's records
+func descriptorProtoFromTableSchema(tableSchemaJSON, messageName string) ([]byte, error) {
+	cSchema := C.CString(tableSchemaJSON)
+	defer C.free(unsafe.Pointer(cSchema))
+
+	cMessageName := C.CString(messageName)
+	defer C.free(unsafe.Pointer(cMessageName))
+
+	var cres C.CResult
+	cBytes := C.zerobus_descriptor_proto_from_table_schema(cSchema, cMessageName, &cres)
+
+	if !cres.success {
+		return nil, ffiResult(cres)
+	}
+	defer C.zerobus_free_bytes(cBytes)
+
+	return C.GoBytes(unsafe.Pointer(cBytes.data), C.int(cBytes.len)), nil
+}
+
+// streamGetUnackedRecords copies the unacknowledged records of a failed or closed stream
+func streamGetUnackedRecords(streamPtr unsafe.Pointer) ([]UnackedRecord, error) {
+	var cres C.CResult
+	cRecords := C.zerobus_stream_get_unacked_records((*C.CZerobusStream)(streamPtr), &cres)
+
+	if !cres.success {
+		return nil, ffiResult(cres)
+	}
+	defer C.zerobus_free_unacked_records(cRecords)
+
+	if cRecords.count == 0 {
+		return nil, nil
+	}
+
+	cSlice := unsafe.Slice(cRecords.records, int(cRecords.count))
+	records := make([]UnackedRecord, len(cSlice))
+	for i, rec := range cSlice {
+		records[i] = UnackedRecord{
+			AckID:      uint64(rec.ack_id),
+			RecordType: RecordType(rec.record_type),
+			Payload:    C.GoBytes(unsafe.Pointer(rec.data), C.int(rec.data_len)),
+		}
+	}
+
+	return records, nil
+}
+
 // streamFlush flushes pending records
 func streamFlush(streamPtr unsafe.Pointer) error {
 	var cres C.CResult
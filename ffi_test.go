@@ -1,8 +1,10 @@
 package zerobus
 
 import (
+	"errors"
 	"runtime/cgo"
 	"testing"
+	"time"
 	"unsafe"
 )
 
@@ -18,7 +20,7 @@ func TestStreamHandleRegistry(t *testing.T) {
 
 	// Store in registry
 	streamHandleRegistryMu.Lock()
-	streamHandleRegistry[dummyStreamPtr] = handle
+	streamHandleRegistry[dummyStreamPtr] = &sharedHandle{handle: handle, refs: 1}
 	streamHandleRegistryMu.Unlock()
 
 	// Verify it's stored
@@ -30,7 +32,7 @@ func TestStreamHandleRegistry(t *testing.T) {
 		t.Fatal("Handle not found in registry")
 	}
 
-	if storedHandle != handle {
+	if storedHandle.handle != handle {
 		t.Fatal("Retrieved handle doesn't match stored handle")
 	}
 
@@ -51,13 +53,13 @@ func TestStreamHandleCleanup(t *testing.T) {
 
 	// Store in registry
 	streamHandleRegistryMu.Lock()
-	streamHandleRegistry[dummyStreamPtr] = handle
+	streamHandleRegistry[dummyStreamPtr] = &sharedHandle{handle: handle, refs: 1}
 	streamHandleRegistryMu.Unlock()
 
 	// Simulate streamFree cleanup logic
 	streamHandleRegistryMu.Lock()
 	if h, exists := streamHandleRegistry[dummyStreamPtr]; exists {
-		h.Delete()
+		h.handle.Delete()
 		delete(streamHandleRegistry, dummyStreamPtr)
 	}
 	streamHandleRegistryMu.Unlock()
@@ -87,7 +89,7 @@ func TestHandleConcurrency(t *testing.T) {
 
 			// Store
 			streamHandleRegistryMu.Lock()
-			streamHandleRegistry[ptr] = handle
+			streamHandleRegistry[ptr] = &sharedHandle{handle: handle, refs: 1}
 			streamHandleRegistryMu.Unlock()
 
 			// Retrieve
@@ -115,6 +117,73 @@ func TestHandleConcurrency(t *testing.T) {
 	}
 }
 
+// TestStreamCallbacksSharedByRecreatedStream tests that a recreated stream keeps the callback
+// handle alive until both streams are freed
+func TestStreamCallbacksSharedByRecreatedStream(t *testing.T) {
+	var acked []uint64
+	handle := cgo.NewHandle(&streamCallbacks{
+		onAck: func(ackID uint64, offset int64, err error) { acked = append(acked, ackID) },
+	})
+
+	oldStream := struct{ id int }{1}
+	newStream := struct{ id int }{2}
+	oldPtr, newPtr := unsafe.Pointer(&oldStream), unsafe.Pointer(&newStream)
+
+	registerStreamCallbacks(oldPtr, handle)
+	shareStreamCallbacks(oldPtr, newPtr)
+	releaseStreamCallbacks(oldPtr)
+
+	// Still reachable through the new stream
+	callbacks, ok := handle.Value().(*streamCallbacks)
+	if !ok {
+		t.Fatal("Handle should stay valid while the recreated stream is alive")
+	}
+	callbacks.onAck(7, 0, nil)
+	if len(acked) != 1 || acked[0] != 7 {
+		t.Fatalf("Expected ack 7 to be handled, got %v", acked)
+	}
+
+	releaseStreamCallbacks(newPtr)
+	streamCallbackRegistryMu.Lock()
+	remaining := len(streamCallbackRegistry)
+	streamCallbackRegistryMu.Unlock()
+	if remaining != 0 {
+		t.Fatalf("Expected no registered callbacks, got %d", remaining)
+	}
+
+	// Releasing a stream without callbacks is a no-op
+	releaseStreamCallbacks(newPtr)
+}
+
+// TestHeadersProviderSharedByRecreatedStream tests that freeing a recreated stream first keeps
+// the headers provider handle alive for the old stream
+func TestHeadersProviderSharedByRecreatedStream(t *testing.T) {
+	handle := cgo.NewHandle(&mockHeadersProvider{})
+
+	oldStream := struct{ id int }{1}
+	newStream := struct{ id int }{2}
+	oldPtr, newPtr := unsafe.Pointer(&oldStream), unsafe.Pointer(&newStream)
+
+	streamHandleRegistryMu.Lock()
+	streamHandleRegistry[oldPtr] = &sharedHandle{handle: handle, refs: 1}
+	streamHandleRegistryMu.Unlock()
+	shareHandle(streamHandleRegistry, &streamHandleRegistryMu, oldPtr, newPtr)
+	releaseHandle(streamHandleRegistry, &streamHandleRegistryMu, newPtr)
+
+	// Still reachable through the old stream
+	if _, ok := handle.Value().(HeadersProvider); !ok {
+		t.Fatal("Handle should stay valid while the old stream is alive")
+	}
+
+	releaseHandle(streamHandleRegistry, &streamHandleRegistryMu, oldPtr)
+	streamHandleRegistryMu.Lock()
+	_, exists := streamHandleRegistry[oldPtr]
+	streamHandleRegistryMu.Unlock()
+	if exists {
+		t.Fatal("Handle should have been removed from registry")
+	}
+}
+
 // Mock HeadersProvider for testing
 type mockHeadersProvider struct {
 	headers map[string]string
@@ -194,3 +263,246 @@ func TestZerobusError(t *testing.T) {
 		t.Errorf("Expected 'ZerobusError: permanent error', got '%s'", errStr2)
 	}
 }
+
+// TestAwaitTimeoutWhileAckIsAwaited tests AwaitTimeout on an ack another goroutine is awaiting
+func TestAwaitTimeoutWhileAckIsAwaited(t *testing.T) {
+	ack := newRecordAck(&ZerobusStream{}, 1)
+
+	// Simulate another goroutine blocked in Await
+	ack.lock()
+	start := time.Now()
+	_, err := ack.AwaitTimeout(20 * time.Millisecond)
+	var zerr *ZerobusError
+	if !errors.As(err, &zerr) || zerr.Code != ErrorCodeWaitTimedOut {
+		t.Fatalf("Expected ErrorCodeWaitTimedOut, got %v", err)
+	}
+	if elapsed := time.Since(start); elapsed < 20*time.Millisecond {
+		t.Errorf("AwaitTimeout returned after %v, before its timeout", elapsed)
+	}
+	if _, _, ready := ack.TryGet(); ready {
+		t.Error("Expected the ack to still be pending")
+	}
+
+	// The result of the other goroutine is returned as soon as it finishes
+	go func() {
+		time.Sleep(10 * time.Millisecond)
+		ack.offset, ack.done = 42, true
+		ack.unlock()
+	}()
+	start = time.Now()
+	offset, err := ack.AwaitTimeout(time.Minute)
+	if err != nil || offset != 42 {
+		t.Fatalf("Expected offset 42, got %d, %v", offset, err)
+	}
+	if elapsed := time.Since(start); elapsed > 10*time.Second {
+		t.Errorf("AwaitTimeout waited %v for a finished ack", elapsed)
+	}
+}
+
+// closedStream returns a stream that Close has already marked as closed
+func closedStream() *ZerobusStream {
+	stream := &ZerobusStream{drained: make(chan struct{})}
+	stream.closed.Store(true)
+	return stream
+}
+
+// TestStreamUseDrainsAfterClose tests that calls fail right away on a closed stream and that
+// Close is woken once the calls using the stream have returned
+func TestStreamUseDrainsAfterClose(t *testing.T) {
+	stream := &ZerobusStream{}
+	if !stream.acquire() {
+		t.Fatal("Expected an open stream to be acquired")
+	}
+
+	// What Close does before waiting
+	stream.drained = make(chan struct{})
+	stream.closed.Store(true)
+	if stream.acquire() {
+		t.Fatal("Expected a closed stream not to be acquired")
+	}
+	select {
+	case <-stream.drained:
+		t.Fatal("Close woken while the stream is still in use")
+	default:
+	}
+
+	stream.release()
+	select {
+	case <-stream.drained:
+	case <-time.After(10 * time.Second):
+		t.Fatal("Close not woken after the last call returned")
+	}
+	if n := stream.inUse.Load(); n != 0 {
+		t.Errorf("Expected no calls using the stream, got %d", n)
+	}
+
+	// Calls on a closed stream fail without blocking, including bounded waits
+	ack := newRecordAck(stream, 1)
+	start := time.Now()
+	var zerr *ZerobusError
+	if _, err := ack.AwaitTimeout(time.Minute); !errors.As(err, &zerr) || zerr.Code != ErrorCodeStreamClosed {
+		t.Errorf("Expected ErrorCodeStreamClosed, got %v", err)
+	}
+	if _, err := stream.IngestRecord([]byte{1}); err != errStreamClosed {
+		t.Errorf("Expected errStreamClosed, got %v", err)
+	}
+	if elapsed := time.Since(start); elapsed > 10*time.Second {
+		t.Errorf("Calls on a closed stream took %v", elapsed)
+	}
+}
+
+// TestAwaitTimeoutWhileStreamCloses tests that AwaitTimeout does not wait for a Close that is
+// waiting for other calls to return
+func TestAwaitTimeoutWhileStreamCloses(t *testing.T) {
+	stream := &ZerobusStream{}
+	// Another goroutine is blocked in Await, and Close waits for it
+	if !stream.acquire() {
+		t.Fatal("Expected an open stream to be acquired")
+	}
+	stream.drained = make(chan struct{})
+	stream.closed.Store(true)
+
+	ack := newRecordAck(stream, 1)
+	start := time.Now()
+	_, err := ack.AwaitTimeout(time.Minute)
+	var zerr *ZerobusError
+	if !errors.As(err, &zerr) || zerr.Code != ErrorCodeStreamClosed {
+		t.Fatalf("Expected ErrorCodeStreamClosed, got %v", err)
+	}
+	if elapsed := time.Since(start); elapsed > 10*time.Second {
+		t.Errorf("AwaitTimeout waited %v for the closing stream", elapsed)
+	}
+	stream.release()
+}
+
+// settledAck returns an ack of stream whose result is already cached
+func settledAck(stream *ZerobusStream, ackID uint64, offset int64, err error) *RecordAck {
+	ack := newRecordAck(stream, ackID)
+	ack.offset, ack.err, ack.done = offset, err, true
+	return ack
+}
+
+// TestAwaitAcksCachedResults tests AwaitAcks on acks that need no call into the native library
+func TestAwaitAcksCachedResults(t *testing.T) {
+	stream := closedStream()
+	failure := &ZerobusError{Message: "rejected", Code: ErrorCodeInvalidArgument}
+	first := settledAck(stream, 3, 30, nil)
+	failed := settledAck(stream, 1, -1, failure)
+	last := settledAck(stream, 2, 20, nil)
+
+	// Resolved by Await: the stream is closed, so the ack fails without blocking
+	awaited := newRecordAck(stream, 4)
+	if _, err := awaited.Await(); err == nil {
+		t.Fatal("Expected Await on a closed stream to fail")
+	}
+
+	// Results follow the order of the acks given, duplicates included
+	offsets, err := AwaitAcks([]*RecordAck{first, failed, first, last, awaited})
+	want := []int64{30, -1, 30, 20, -1}
+	if len(offsets) != len(want) {
+		t.Fatalf("Expected %d offsets, got %v", len(want), offsets)
+	}
+	for i := range want {
+		if offsets[i] != want[i] {
+			t.Errorf("offsets[%d] = %d, expected %d", i, offsets[i], want[i])
+		}
+	}
+	if err != failure {
+		t.Errorf("Expected the first failure in ack order, got %v", err)
+	}
+
+	// Each ack keeps its own result
+	if offset, err := last.Await(); offset != 20 || err != nil {
+		t.Errorf("Expected cached offset 20, got %d, %v", offset, err)
+	}
+	var zerr *ZerobusError
+	if _, err := awaited.Await(); !errors.As(err, &zerr) || zerr.Code != ErrorCodeStreamClosed {
+		t.Errorf("Expected the cached StreamClosed error, got %v", err)
+	}
+}
+
+// TestAwaitAnyCachedResults tests that AwaitAny returns the first completed ack in ack order
+func TestAwaitAnyCachedResults(t *testing.T) {
+	stream := &ZerobusStream{}
+	later := settledAck(stream, 1, 10, nil)
+	earlier := settledAck(stream, 2, 20, nil)
+
+	done, err := AwaitAny([]*RecordAck{earlier, later, earlier})
+	if err != nil || done != earlier {
+		t.Fatalf("Expected the first ack given, got %v, %v", done, err)
+	}
+
+	// Acks already awaited are returned with their cached result
+	untracked := newRecordAck(stream, 0)
+	if _, err := untracked.Await(); err != errAckNotTracked {
+		t.Fatalf("Expected errAckNotTracked, got %v", err)
+	}
+	done, err = AwaitAny([]*RecordAck{untracked, untracked})
+	if err != nil || done != untracked {
+		t.Fatalf("Expected the awaited ack, got %v, %v", done, err)
+	}
+	if _, err := done.Await(); err != errAckNotTracked {
+		t.Errorf("Expected the cached error, got %v", err)
+	}
+}
+
+// TestAwaitAcksInvalidArguments tests that acks must be given and belong to one stream
+func TestAwaitAcksInvalidArguments(t *testing.T) {
+	one := settledAck(&ZerobusStream{}, 1, 10, nil)
+	other := settledAck(&ZerobusStream{}, 1, 10, nil)
+
+	for _, acks := range [][]*RecordAck{nil, {one, other}, {one, nil}} {
+		var zerr *ZerobusError
+		if _, err := AwaitAcks(acks); !errors.As(err, &zerr) || zerr.Code != ErrorCodeInvalidArgument {
+			t.Errorf("AwaitAcks: expected ErrorCodeInvalidArgument, got %v", err)
+		}
+		if _, err := AwaitAny(acks); !errors.As(err, &zerr) || zerr.Code != ErrorCodeInvalidArgument {
+			t.Errorf("AwaitAny: expected ErrorCodeInvalidArgument, got %v", err)
+		}
+	}
+}
+
+// TestLockAcksOrdering tests that lockAcks locks every distinct ack once, in ack ID order
+func TestLockAcksOrdering(t *testing.T) {
+	stream := &ZerobusStream{}
+	a1 := newRecordAck(stream, 1)
+	a2 := newRecordAck(stream, 2)
+	a3 := newRecordAck(stream, 3)
+
+	unique, unlock := lockAcks([]*RecordAck{a3, a1, a3, a2, a1})
+	if len(unique) != 3 || unique[0] != a1 || unique[1] != a2 || unique[2] != a3 {
+		t.Fatalf("Expected the acks once each in ID order, got %v", unique)
+	}
+	for _, a := range unique {
+		if a.tryLock() {
+			t.Errorf("Ack %d is not locked", a.ackID)
+		}
+	}
+	unlock()
+	for _, a := range unique {
+		if !a.tryLock() {
+			t.Errorf("Ack %d is still locked", a.ackID)
+		}
+		a.unlock()
+	}
+
+	// Callers locking the same acks in opposite orders do not deadlock
+	finished := make(chan struct{})
+	for _, acks := range [][]*RecordAck{{a1, a2, a3}, {a3, a2, a1}} {
+		acks := acks
+		go func() {
+			for i := 0; i < 1000; i++ {
+				_, unlock := lockAcks(acks)
+				unlock()
+			}
+			finished <- struct{}{}
+		}()
+	}
+	for i := 0; i < 2; i++ {
+		select {
+		case <-finished:
+		case <-time.After(10 * time.Second):
+			t.Fatal("lockAcks deadlocked")
+		}
+	}
+}
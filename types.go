@@ -1,5 +1,7 @@
 package zerobus
 
+import "time"
+
 // RecordType represents the type of records to ingest
 type RecordType int32
 
@@ -45,6 +47,64 @@ type StreamConfigurationOptions struct {
 	// Type of record to ingest (Proto, Json, or Unspecified)
 	// Default: RecordTypeProto
 	RecordType RecordType
+
+	// Skip per-record acknowledgment tracking. Ingested records return acks that cannot
+	// be awaited; failures are reported by the next Flush or Close instead.
+	// Default: false
+	FireAndForget bool
+
+	// Receives the outcome of every acknowledgment as soon as it arrives, for example to
+	// drain acks into a channel. Acks of a stream with a handler cannot be awaited through
+	// RecordAck. Cannot be combined with FireAndForget.
+	// Default: nil (await acks through RecordAck)
+	AckHandler AckHandler
+
+	// Check every record before sending it: proto records are decoded against the stream's
+	// descriptor, JSON records must be JSON objects (matching TableSchemaJSON if set).
+	// Malformed records are rejected by the ingest call with an InvalidArgument error
+	// naming the offending field instead of failing the stream server-side.
+	// Default: false
+	ValidateRecords bool
+
+	// Accept JSON records (string payloads) on a proto stream. They are converted to
+	// protobuf with the proto3 JSON mapping of the stream's descriptor before sending;
+	// conversion errors are returned by IngestRecord. Requires RecordTypeProto.
+	// Default: false
+	TranscodeJSON bool
+
+	// Columns JSON records are validated against, as a JSON array of
+	// {"name": ..., "type": ..., "nullable": ...} objects with Delta types such as
+	// "BIGINT", "ARRAY<STRING>" or "STRUCT<city: STRING, zip: INT NOT NULL>".
+	// Requires ValidateRecords and RecordTypeJson.
+	// Default: "" (only require a JSON object)
+	TableSchemaJSON string
+
+	// Directory records are written to before they are sent, so they survive a crash or a
+	// failed stream. Records left there by an earlier stream are sent again when a stream
+	// is created with it, and records of a failed stream by RecreateStream. Delivery is
+	// at-least-once; the directory is synced to disk by Flush and Close. A directory can be
+	// used by one stream at a time.
+	// Default: "" (no spill buffer)
+	SpillDir string
+
+	// Size cap of SpillDir in bytes. Ingest calls fail with ErrorCodeSpillFull (retryable)
+	// once it is reached.
+	// Default: 0 (no cap)
+	SpillMaxBytes uint64
+
+	// JSON Lines file every record of a request the server rejected as invalid
+	// (ErrorCodeInvalidArgument) is appended to, so rejected records are kept even if nobody
+	// awaits their ack. Requests of a stream that failed as a whole are not written; RecreateStream
+	// sends them again. Each line
+	// holds ack_id, record_type ("proto" or "json"), payload (base64 for proto records, the
+	// JSON text for JSON records), error_code and error_message.
+	// Default: "" (no dead-letter file)
+	DeadLetterFile string
+
+	// Called with every record of a request the server rejected as invalid, whether or not its
+	// ack is awaited. Can be combined with DeadLetterFile.
+	// Default: nil (no dead-letter handler)
+	DeadLetterHandler DeadLetterHandler
 }
 
 // DefaultStreamConfigurationOptions returns the default configuration options
@@ -61,6 +121,89 @@ func DefaultStreamConfigurationOptions() *StreamConfigurationOptions {
 	}
 }
 
+// LogLevel is the severity of a log event emitted by the SDK
+type LogLevel int32
+
+const (
+	// LogLevelOff disables log forwarding
+	LogLevelOff LogLevel = 0
+	// LogLevelError is used for failures
+	LogLevelError LogLevel = 1
+	// LogLevelWarn is used for recoverable problems such as stream recovery attempts
+	LogLevelWarn LogLevel = 2
+	// LogLevelInfo is used for lifecycle events such as stream creation
+	LogLevelInfo LogLevel = 3
+	// LogLevelDebug is used for detailed diagnostics
+	LogLevelDebug LogLevel = 4
+	// LogLevelTrace is used for very verbose diagnostics
+	LogLevelTrace LogLevel = 5
+)
+
+// LogEntry is a log event emitted by the SDK
+type LogEntry struct {
+	Level LogLevel
+
+	// Module that emitted the event
+	Target string
+
+	Message string
+
+	// Structured key-value fields of the event and of the spans it was emitted in, such as the
+	// table_name of the SDK task reporting it; the event's own value wins on conflicts
+	Fields map[string]string
+}
+
+// AckHandler receives the acknowledgment of an ingested request: its offset, or -1 and the
+// error if it failed. It is called from SDK threads and must be safe for concurrent use. It
+// should return quickly and must not close the stream, which waits for running handlers.
+type AckHandler func(ackID uint64, offset int64, err error)
+
+// DeadLetter is a record of a request the server rejected as invalid
+type DeadLetter struct {
+	// ID of the acknowledgment the record was ingested under, shared by all records of a batch
+	AckID uint64
+
+	// RecordTypeProto for Protocol Buffer bytes, RecordTypeJson for a JSON document
+	RecordType RecordType
+
+	// The encoded record as it was sent
+	Payload []byte
+
+	// Why the request failed
+	Err error
+}
+
+// DeadLetterHandler receives the records of requests that failed for good. It is called from
+// SDK threads and must be safe for concurrent use. It must not close the stream, which waits
+// for running handlers.
+type DeadLetterHandler func(letter DeadLetter)
+
+// LogHandler receives log events. It is called from SDK threads and must be safe for concurrent use.
+type LogHandler func(entry LogEntry)
+
+// RuntimeConfig configures the runtime that drives all SDK I/O. Zero values keep the defaults.
+type RuntimeConfig struct {
+	// Number of worker threads
+	// Default: one per CPU core
+	WorkerThreads int
+
+	// Runtime threads are named "<prefix>-<n>"
+	// Default: "tokio-runtime-worker"
+	ThreadNamePrefix string
+
+	// Stack size of runtime threads in bytes
+	// Default: 2 MiB
+	ThreadStackSize int
+
+	// Maximum number of threads used for blocking operations
+	// Default: 512
+	MaxBlockingThreads int
+
+	// Run all SDK tasks on a single background thread instead of a worker pool
+	// Default: false
+	CurrentThread bool
+}
+
 // TableProperties contains information about the target table
 type TableProperties struct {
 	// Fully qualified table name (catalog.schema.table)
@@ -69,4 +212,56 @@ type TableProperties struct {
 	// Protocol buffer descriptor (required for Proto record type, nil for JSON)
 	// This should be a serialized prost_types::DescriptorProto
 	DescriptorProto []byte
+
+	// Serialized FileDescriptorSet, used instead of DescriptorProto when set.
+	// Must include all imported files (protoc --include_imports), so records may use
+	// imported messages, well-known types and enums defined in other files.
+	DescriptorSet []byte
+
+	// Fully qualified name of the record message in DescriptorSet (e.g. "my.package.Event")
+	MessageName string
+}
+
+// UnackedRecord is a record that was ingested but never acknowledged by the server
+type UnackedRecord struct {
+	// ID of the acknowledgment returned when the record was ingested.
+	// Records ingested together in one batch share the same ID.
+	AckID uint64
+
+	// RecordTypeProto for Protocol Buffer bytes, RecordTypeJson for a JSON document
+	RecordType RecordType
+
+	// The encoded record as it was ingested
+	Payload []byte
+}
+
+// StreamMetrics is a point-in-time snapshot of a stream's counters.
+// Counters are cumulative since the stream was created.
+type StreamMetrics struct {
+	// Records accepted by the stream
+	RecordsIngested uint64
+
+	// Payload bytes of the accepted records, as encoded for the server (JSON records after
+	// transcoding). Each record counts once: gRPC framing, compression and records resent
+	// after a recovery are not included, so this is not the number of bytes on the wire.
+	BytesIngested uint64
+
+	// Records acknowledged by the server
+	RecordsAcked uint64
+
+	// Records whose acknowledgment failed
+	RecordsFailed uint64
+
+	// Records accepted but neither acknowledged nor failed yet
+	RecordsInflight uint64
+
+	// Mean time from ingestion to server acknowledgment
+	AckLatencyAvg time.Duration
+
+	// Longest time from ingestion to server acknowledgment
+	AckLatencyMax time.Duration
+
+	// Times the SDK tried to reopen the stream after a retryable failure (see the Recovery
+	// option), each retry of a recovery included
+	Recoveries uint64
 }
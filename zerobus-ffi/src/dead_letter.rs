@@ -0,0 +1,228 @@
+// Sink for records the server rejected for good, see dead_letter_callback and dead_letter_path
+//
+// A request fails as a whole, so every record of a failed batch is reported under the batch's
+// ack ID. Only rejections of the records themselves (invalid arguments) are reported: records
+// of a stream that failed as a whole, retryably or not (authentication, closed stream, recovery
+// exhausted), are recovered with zerobus_sdk_recreate_stream instead. A rejection fails the
+// stream, and the SDK fails every pending request with the same error, so only the request the
+// server rejected is reported (see RejectionTracker) and the others are recovered as well.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use databricks_zerobus_ingest_sdk::{EncodedRecord, ZerobusError};
+use std::collections::BTreeSet;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::sync::Mutex;
+use tokio::sync::Notify;
+
+use crate::{zerobus_free_error_message, CErrorCode, CResult, CallbackTracker};
+
+/// Function pointer type for the dead-letter callback (nullable)
+/// Invoked from a runtime worker thread once per record of a request the server rejected as
+/// invalid; record_type is 1 for proto and 2 for JSON records
+/// data and result (and its error_message) are only valid during the call
+/// Freeing the stream waits for callbacks in progress and drops later ones, so the callback
+/// must not free the stream itself
+pub type DeadLetterCallback = Option<
+    extern "C" fn(
+        user_data: *mut std::ffi::c_void,
+        ack_id: u64,
+        record_type: i32,
+        data: *const u8,
+        data_len: usize,
+        result: *const CResult,
+    ),
+>;
+
+/// The dead-letter callback together with the user data it was registered with
+#[derive(Clone, Copy)]
+struct DeadLetterCallbackHandle {
+    callback: extern "C" fn(*mut std::ffi::c_void, u64, i32, *const u8, usize, *const CResult),
+    user_data: *mut std::ffi::c_void,
+}
+
+// Safety: The host is responsible for making the callback and its user data thread-safe
+unsafe impl Send for DeadLetterCallbackHandle {}
+unsafe impl Sync for DeadLetterCallbackHandle {}
+
+/// Where the records of permanently failed requests are reported
+pub(crate) struct DeadLetterSink {
+    callback: Option<DeadLetterCallbackHandle>,
+    /// JSON Lines file every dead letter is appended to
+    file: Option<Mutex<File>>,
+}
+
+impl DeadLetterSink {
+    /// Build the sink for the configured callback and file, None if neither is set
+    pub(crate) fn new(
+        callback: DeadLetterCallback,
+        user_data: *mut std::ffi::c_void,
+        path: Option<&str>,
+    ) -> io::Result<Option<Self>> {
+        let file = match path {
+            Some(path) => Some(Mutex::new(
+                OpenOptions::new().create(true).append(true).open(path)?,
+            )),
+            None => None,
+        };
+        let callback = callback.map(|callback| DeadLetterCallbackHandle {
+            callback,
+            user_data,
+        });
+        if callback.is_none() && file.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Self { callback, file }))
+    }
+
+    /// Report the records of a failed request, unless the failure is not a record rejection
+    /// The callback is skipped once the stream it was registered with has been freed, and
+    /// freeing the stream waits for a delivery in progress
+    /// Returns whether the records were reported
+    pub(crate) fn deliver(
+        &self,
+        ack_id: u64,
+        records: &[EncodedRecord],
+        err: &ZerobusError,
+        tracker: &CallbackTracker,
+    ) -> bool {
+        if !is_record_rejection(err) {
+            return false;
+        }
+
+        if let Some(file) = &self.file {
+            let mut lines = String::new();
+            for record in records {
+                lines.push_str(&dead_letter_line(ack_id, record, err));
+                lines.push('\n');
+            }
+            // One write per request keeps the lines of concurrent failures apart
+            if let Err(write_err) = file.lock().unwrap().write_all(lines.as_bytes()) {
+                tracing::warn!(ack_id, "Failed to write dead letters: {}", write_err);
+            }
+        }
+
+        let Some(callback) = self.callback else {
+            return true;
+        };
+        let result = CResult::error(err.clone());
+        tracker.run_if_open(|| {
+            for record in records {
+                let (record_type, data) = match record {
+                    EncodedRecord::Proto(bytes) => (1, bytes.as_slice()),
+                    EncodedRecord::Json(json) => (2, json.as_bytes()),
+                };
+                (callback.callback)(
+                    callback.user_data,
+                    ack_id,
+                    record_type,
+                    data.as_ptr(),
+                    data.len(),
+                    &result,
+                );
+            }
+        });
+        // The callback only borrows the result, so the message is released here
+        zerobus_free_error_message(result.error_message);
+        true
+    }
+}
+
+/// Tells the request the server rejected apart from the ones failed along with it
+/// The server handles requests in order and acknowledges every request before the one it
+/// rejects, so of the requests failed with a rejection, the one with the lowest ack ID is the
+/// rejected one once every request before it has settled
+#[derive(Default)]
+pub(crate) struct RejectionTracker {
+    state: Mutex<RejectionState>,
+    decided: Notify,
+}
+
+#[derive(Default)]
+struct RejectionState {
+    /// Ack IDs of requests queued with the SDK that have not settled yet
+    pending: BTreeSet<u64>,
+    /// Ack IDs of requests that failed with a rejection
+    rejected: BTreeSet<u64>,
+    /// Ack ID of the request the server rejected, once known
+    culprit: Option<u64>,
+}
+
+impl RejectionState {
+    fn decide(&mut self) {
+        if self.culprit.is_some() {
+            return;
+        }
+        let Some(&first) = self.rejected.first() else {
+            return;
+        };
+        // An earlier request that has not settled yet may still turn out to be the rejected one
+        if self.pending.first().is_some_and(|&pending| pending < first) {
+            return;
+        }
+        self.culprit = Some(first);
+    }
+}
+
+impl RejectionTracker {
+    /// Track a request the SDK accepted, in ack ID order
+    pub(crate) fn queued(&self, ack_id: u64) {
+        self.state.lock().unwrap().pending.insert(ack_id);
+    }
+
+    /// Record how a request settled and, for a rejection, wait until it is known whether it is
+    /// the request the server rejected
+    pub(crate) async fn settle(&self, ack_id: u64, rejected: bool) -> bool {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.pending.remove(&ack_id);
+            if rejected {
+                state.rejected.insert(ack_id);
+            }
+            state.decide();
+        }
+        self.decided.notify_waiters();
+        if !rejected {
+            return false;
+        }
+
+        loop {
+            let notified = self.decided.notified();
+            tokio::pin!(notified);
+            // Registered before checking, so a decision made in between is not missed
+            notified.as_mut().enable();
+            if let Some(culprit) = self.state.lock().unwrap().culprit {
+                return culprit == ack_id;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Whether a request failed because the server rejected its records, rather than because the
+/// stream failed: only such requests are dead letters
+pub(crate) fn is_record_rejection(err: &ZerobusError) -> bool {
+    match err {
+        ZerobusError::InvalidArgument(_) => true,
+        ZerobusError::StreamClosedError(status) => status.code() == tonic::Code::InvalidArgument,
+        _ => false,
+    }
+}
+
+/// JSON object describing one dead letter: proto payloads are base64 encoded, JSON payloads
+/// are kept as the original JSON text
+pub(crate) fn dead_letter_line(ack_id: u64, record: &EncodedRecord, err: &ZerobusError) -> String {
+    let (record_type, payload) = match record {
+        EncodedRecord::Proto(bytes) => ("proto", STANDARD.encode(bytes)),
+        EncodedRecord::Json(json) => ("json", json.clone()),
+    };
+    serde_json::json!({
+        "ack_id": ack_id,
+        "record_type": record_type,
+        "payload": payload,
+        "error_code": CErrorCode::from(err) as i32,
+        "error_message": err.to_string(),
+    })
+    .to_string()
+}
@@ -0,0 +1,167 @@
+// Flattening of FileDescriptorSets into the self-contained DescriptorProto a stream needs
+
+use prost_types::{DescriptorProto, EnumDescriptorProto, FileDescriptorSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// A top-level message or enum declared in one of the files of a descriptor set
+enum TopLevelType<'a> {
+    Message(&'a DescriptorProto),
+    Enum(&'a EnumDescriptorProto),
+}
+
+/// Prefix a name with its package, producing the fully qualified ".pkg.Name" form protoc uses
+fn qualify(package: &str, name: &str) -> String {
+    if package.is_empty() {
+        format!(".{}", name)
+    } else {
+        format!(".{}.{}", package, name)
+    }
+}
+
+/// Resolve `message_name` in the descriptor set and flatten it into a single DescriptorProto
+///
+/// Types the message references from outside its own declaration (imported messages,
+/// well-known types, enums declared elsewhere) are copied into it as nested types named after
+/// their fully qualified name with dots replaced by underscores, e.g. `google_protobuf_Timestamp`,
+/// and every field's `type_name` is rewritten to point at the copy.
+/// The set must contain all dependencies (protoc `--include_imports`).
+pub(crate) fn flatten_descriptor_set(
+    descriptor_set: &FileDescriptorSet,
+    message_name: &str,
+) -> Result<DescriptorProto, String> {
+    let mut top_level = HashMap::new();
+    for file in &descriptor_set.file {
+        let package = file.package();
+        for message in &file.message_type {
+            top_level.insert(
+                qualify(package, message.name()),
+                TopLevelType::Message(message),
+            );
+        }
+        for enum_type in &file.enum_type {
+            top_level.insert(
+                qualify(package, enum_type.name()),
+                TopLevelType::Enum(enum_type),
+            );
+        }
+    }
+
+    let root_name = format!(".{}", message_name.trim_start_matches('.'));
+    let (root_unit, root_path) = find_unit(&top_level, &root_name)
+        .ok_or_else(|| format!("Message {} not found in descriptor set", message_name))?;
+    let mut root = match top_level[root_unit] {
+        TopLevelType::Message(message) => find_nested(message, &root_path)
+            .ok_or_else(|| format!("Message {} not found in descriptor set", message_name))?,
+        TopLevelType::Enum(_) => return Err(format!("{} is an enum, not a message", message_name)),
+    }
+    .clone();
+
+    let mut flattener = Flattener {
+        root_name: &root_name,
+        copied: HashSet::new(),
+        queue: VecDeque::new(),
+        top_level: &top_level,
+    };
+    flattener.rewrite_message(&mut root)?;
+
+    while let Some(unit) = flattener.queue.pop_front() {
+        match flattener.top_level[unit.as_str()] {
+            TopLevelType::Message(message) => {
+                let mut copy = message.clone();
+                copy.name = Some(mangle(&unit));
+                flattener.rewrite_message(&mut copy)?;
+                root.nested_type.push(copy);
+            }
+            TopLevelType::Enum(enum_type) => {
+                let mut copy = enum_type.clone();
+                copy.name = Some(mangle(&unit));
+                root.enum_type.push(copy);
+            }
+        }
+    }
+
+    Ok(root)
+}
+
+/// Name of a type copied into the root message
+fn mangle(unit: &str) -> String {
+    unit.trim_start_matches('.').replace('.', "_")
+}
+
+/// Split a fully qualified type name into the top-level type declaring it and the nested path below
+fn find_unit<'a, 'b>(
+    top_level: &'a HashMap<String, TopLevelType<'b>>,
+    type_name: &str,
+) -> Option<(&'a str, Vec<String>)> {
+    let mut prefix = type_name;
+    let mut nested = Vec::new();
+    loop {
+        if let Some((unit, _)) = top_level.get_key_value(prefix) {
+            nested.reverse();
+            return Some((unit.as_str(), nested));
+        }
+        let (parent, last) = prefix.rsplit_once('.')?;
+        nested.push(last.to_string());
+        prefix = parent;
+    }
+}
+
+fn find_nested<'a>(message: &'a DescriptorProto, path: &[String]) -> Option<&'a DescriptorProto> {
+    path.iter().try_fold(message, |current, name| {
+        current
+            .nested_type
+            .iter()
+            .find(|nested| nested.name() == name)
+    })
+}
+
+struct Flattener<'a, 'b> {
+    root_name: &'a str,
+    /// Top-level types already scheduled to be copied into the root
+    copied: HashSet<String>,
+    queue: VecDeque<String>,
+    top_level: &'a HashMap<String, TopLevelType<'b>>,
+}
+
+impl Flattener<'_, '_> {
+    /// Rewrite the type references of a message and its nested messages, scheduling copies
+    fn rewrite_message(&mut self, message: &mut DescriptorProto) -> Result<(), String> {
+        for field in &mut message.field {
+            if let Some(type_name) = field.type_name.as_mut() {
+                *type_name = self.rewrite_type_name(type_name)?;
+            }
+        }
+        for nested in &mut message.nested_type {
+            self.rewrite_message(nested)?;
+        }
+        Ok(())
+    }
+
+    fn rewrite_type_name(&mut self, type_name: &str) -> Result<String, String> {
+        if !type_name.starts_with('.') {
+            return Err(format!("Type name {} is not fully qualified", type_name));
+        }
+
+        // Types declared inside the root message travel with it
+        if type_name == self.root_name || type_name.starts_with(&format!("{}.", self.root_name)) {
+            return Ok(type_name.to_string());
+        }
+
+        let (unit, path) = find_unit(self.top_level, type_name).ok_or_else(|| {
+            format!(
+                "Type {} not found in descriptor set (was it built with --include_imports?)",
+                type_name
+            )
+        })?;
+        if self.copied.insert(unit.to_string()) {
+            self.queue.push_back(unit.to_string());
+        }
+
+        let mut rewritten = format!("{}.{}", self.root_name, mangle(unit));
+        for name in path {
+            rewritten.push('.');
+            rewritten.push_str(&name);
+        }
+        Ok(rewritten)
+    }
+}
@@ -0,0 +1,505 @@
+// Transcoding of JSON records into protobuf records following the proto3 JSON mapping
+
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD};
+use base64::Engine;
+use prost::encoding::{encode_key, encode_varint, WireType};
+use prost_types::field_descriptor_proto::Type;
+use serde_json::Value;
+use std::collections::HashSet;
+
+use crate::proto_schema::{display_path, type_name, FieldSchema, ProtoSchema};
+
+// Names of the well-known types with a special JSON representation once copied into a
+// descriptor by flatten_descriptor_set
+const TIMESTAMP: &str = "google_protobuf_Timestamp";
+const DURATION: &str = "google_protobuf_Duration";
+
+impl ProtoSchema {
+    /// Parse a JSON object and encode it as a protobuf record of the root message
+    /// Errors name the offending field by its dotted path, e.g. "field 'tags[2]' (3): ..."
+    pub(crate) fn transcode_json(&self, json: &str) -> Result<Vec<u8>, String> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|e| format!("invalid JSON: {}", e))?;
+        let mut buf = Vec::new();
+        self.encode_message(&self.root, &value, "", &mut buf)?;
+        Ok(buf)
+    }
+
+    fn encode_message(
+        &self,
+        message: &str,
+        value: &Value,
+        path: &str,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), String> {
+        match message.rsplit('.').next() {
+            Some(TIMESTAMP) => return encode_well_known(value, path, parse_timestamp, buf),
+            Some(DURATION) => return encode_well_known(value, path, parse_duration, buf),
+            _ => {}
+        }
+
+        let Value::Object(object) = value else {
+            return Err(format!(
+                "{}: expected a JSON object, got {}",
+                display_path(path),
+                json_kind(value)
+            ));
+        };
+        let schema = &self.messages[message];
+        let mut oneofs = HashSet::new();
+
+        for (key, value) in object {
+            let number = *schema
+                .by_name
+                .get(key)
+                .ok_or_else(|| format!("unknown field '{}' in {}", key, display_path(path)))?;
+            let field = &schema.fields[&number];
+            let field_path = if path.is_empty() {
+                field.name.clone()
+            } else {
+                format!("{}.{}", path, field.name)
+            };
+
+            // null is the default value of every field
+            if value.is_null() {
+                continue;
+            }
+            if let Some(oneof) = field.oneof {
+                if !oneofs.insert(oneof) {
+                    return Err(format!(
+                        "field '{}' ({}): another field of the same oneof is already set",
+                        field_path, number
+                    ));
+                }
+            }
+
+            let map_entry = field
+                .message
+                .as_deref()
+                .filter(|entry| self.messages[*entry].map_entry);
+            if let Some(entry) = map_entry {
+                self.encode_map(number, entry, value, &field_path, buf)?;
+            } else if field.repeated {
+                self.encode_repeated(field, number, value, &field_path, buf)?;
+            } else {
+                self.encode_field(field, number, value, &field_path, buf)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn encode_repeated(
+        &self,
+        field: &FieldSchema,
+        number: u32,
+        value: &Value,
+        path: &str,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), String> {
+        let Value::Array(items) = value else {
+            return Err(field_error(
+                path,
+                number,
+                format!("expected an array, got {}", json_kind(value)),
+            ));
+        };
+
+        let packed = !matches!(field.kind, Type::String | Type::Bytes | Type::Message);
+        let mut packed_buf = Vec::new();
+        for (i, item) in items.iter().enumerate() {
+            let item_path = format!("{}[{}]", path, i);
+            if item.is_null() {
+                return Err(field_error(
+                    &item_path,
+                    number,
+                    "null is not allowed in arrays",
+                ));
+            }
+            if packed {
+                self.encode_scalar(field, number, item, &item_path, &mut packed_buf)?;
+            } else {
+                self.encode_field(field, number, item, &item_path, buf)?;
+            }
+        }
+
+        if packed && !packed_buf.is_empty() {
+            encode_key(number, WireType::LengthDelimited, buf);
+            encode_varint(packed_buf.len() as u64, buf);
+            buf.extend_from_slice(&packed_buf);
+        }
+        Ok(())
+    }
+
+    fn encode_map(
+        &self,
+        number: u32,
+        entry: &str,
+        value: &Value,
+        path: &str,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), String> {
+        let Value::Object(object) = value else {
+            return Err(field_error(
+                path,
+                number,
+                format!("expected a JSON object, got {}", json_kind(value)),
+            ));
+        };
+        let entry_schema = &self.messages[entry];
+        let (Some(key_field), Some(value_field)) =
+            (entry_schema.fields.get(&1), entry_schema.fields.get(&2))
+        else {
+            return Err(field_error(
+                path,
+                number,
+                "map entry has no key or value field",
+            ));
+        };
+
+        for (key, value) in object {
+            let entry_path = format!("{}[{}]", path, key);
+            // Map keys are always JSON strings, bool keys are spelled "true" / "false"
+            let key = match (key_field.kind, key.as_str()) {
+                (Type::Bool, "true") => Value::Bool(true),
+                (Type::Bool, "false") => Value::Bool(false),
+                _ => Value::String(key.clone()),
+            };
+
+            let mut entry_buf = Vec::new();
+            self.encode_field(key_field, 1, &key, &entry_path, &mut entry_buf)?;
+            if !value.is_null() {
+                self.encode_field(value_field, 2, value, &entry_path, &mut entry_buf)?;
+            }
+            encode_key(number, WireType::LengthDelimited, buf);
+            encode_varint(entry_buf.len() as u64, buf);
+            buf.extend_from_slice(&entry_buf);
+        }
+        Ok(())
+    }
+
+    /// Encode a single (non-repeated) value of a field, including its key
+    fn encode_field(
+        &self,
+        field: &FieldSchema,
+        number: u32,
+        value: &Value,
+        path: &str,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), String> {
+        let bytes = match field.kind {
+            Type::Message => {
+                let mut nested = Vec::new();
+                let message = field.message.as_deref().unwrap_or_default();
+                self.encode_message(message, value, path, &mut nested)?;
+                nested
+            }
+            Type::String => match value {
+                Value::String(s) => s.as_bytes().to_vec(),
+                _ => return Err(expected(path, number, "a string", value)),
+            },
+            Type::Bytes => match value {
+                Value::String(s) => decode_base64(s)
+                    .ok_or_else(|| field_error(path, number, "invalid base64 value"))?,
+                _ => return Err(expected(path, number, "a base64 string", value)),
+            },
+            Type::Group => {
+                return Err(field_error(path, number, "groups are not supported"));
+            }
+            _ => {
+                let wire_type = match field.kind {
+                    Type::Double | Type::Fixed64 | Type::Sfixed64 => WireType::SixtyFourBit,
+                    Type::Float | Type::Fixed32 | Type::Sfixed32 => WireType::ThirtyTwoBit,
+                    _ => WireType::Varint,
+                };
+                encode_key(number, wire_type, buf);
+                return self.encode_scalar(field, number, value, path, buf);
+            }
+        };
+
+        encode_key(number, WireType::LengthDelimited, buf);
+        encode_varint(bytes.len() as u64, buf);
+        buf.extend_from_slice(&bytes);
+        Ok(())
+    }
+
+    /// Encode the value of a numeric, bool or enum field without its key
+    fn encode_scalar(
+        &self,
+        field: &FieldSchema,
+        number: u32,
+        value: &Value,
+        path: &str,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), String> {
+        let int = |min: i128, max: i128| {
+            parse_int(value)
+                .filter(|n| (min..=max).contains(n))
+                .ok_or_else(|| expected(path, number, type_name(field.kind), value))
+        };
+        let float = || parse_float(value).ok_or_else(|| expected(path, number, "a number", value));
+
+        match field.kind {
+            Type::Int32 => encode_varint(int(i32::MIN.into(), i32::MAX.into())? as i64 as u64, buf),
+            Type::Int64 => encode_varint(int(i64::MIN.into(), i64::MAX.into())? as i64 as u64, buf),
+            Type::Uint32 => encode_varint(int(0, u32::MAX.into())? as u64, buf),
+            Type::Uint64 => encode_varint(int(0, u64::MAX.into())? as u64, buf),
+            Type::Sint32 => {
+                let n = int(i32::MIN.into(), i32::MAX.into())? as i32;
+                encode_varint(((n << 1) ^ (n >> 31)) as u32 as u64, buf);
+            }
+            Type::Sint64 => {
+                let n = int(i64::MIN.into(), i64::MAX.into())? as i64;
+                encode_varint(((n << 1) ^ (n >> 63)) as u64, buf);
+            }
+            Type::Fixed32 => {
+                buf.extend_from_slice(&(int(0, u32::MAX.into())? as u32).to_le_bytes())
+            }
+            Type::Fixed64 => {
+                buf.extend_from_slice(&(int(0, u64::MAX.into())? as u64).to_le_bytes())
+            }
+            Type::Sfixed32 => buf
+                .extend_from_slice(&(int(i32::MIN.into(), i32::MAX.into())? as i32).to_le_bytes()),
+            Type::Sfixed64 => buf
+                .extend_from_slice(&(int(i64::MIN.into(), i64::MAX.into())? as i64).to_le_bytes()),
+            Type::Bool => match value {
+                Value::Bool(b) => encode_varint(*b as u64, buf),
+                _ => return Err(expected(path, number, "true or false", value)),
+            },
+            Type::Double => buf.extend_from_slice(&float()?.to_le_bytes()),
+            Type::Float => {
+                let f = float()?;
+                if f.is_finite() && f.abs() > f32::MAX as f64 {
+                    return Err(field_error(path, number, "value out of range for float"));
+                }
+                buf.extend_from_slice(&(f as f32).to_le_bytes());
+            }
+            Type::Enum => {
+                let n = match value {
+                    Value::String(name) => *field
+                        .enum_type
+                        .as_ref()
+                        .and_then(|enum_type| self.enums[enum_type].get(name))
+                        .ok_or_else(|| {
+                            field_error(path, number, format!("unknown enum value \"{}\"", name))
+                        })?,
+                    _ => int(i32::MIN.into(), i32::MAX.into())
+                        .map_err(|_| expected(path, number, "an enum name or number", value))?
+                        as i32,
+                };
+                encode_varint(n as i64 as u64, buf);
+            }
+            Type::String | Type::Bytes | Type::Message | Type::Group => {
+                return Err(field_error(
+                    path,
+                    number,
+                    format!("{} values cannot be packed", type_name(field.kind)),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn field_error(path: &str, number: u32, problem: impl std::fmt::Display) -> String {
+    format!("field '{}' ({}): {}", path, number, problem)
+}
+
+fn expected(path: &str, number: u32, what: &str, value: &Value) -> String {
+    field_error(
+        path,
+        number,
+        format!("expected {}, got {}", what, json_kind(value)),
+    )
+}
+
+pub(crate) fn json_kind(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => format!("number {}", n),
+        Value::String(s) => format!("string \"{}\"", s),
+        Value::Array(_) => "an array".to_string(),
+        Value::Object(_) => "an object".to_string(),
+    }
+}
+
+/// Integers may be JSON numbers without a fractional part or strings holding one
+fn parse_int(value: &Value) -> Option<i128> {
+    let from_float = |f: f64| (f.is_finite() && f.fract() == 0.0).then_some(f as i128);
+    match value {
+        Value::Number(n) => n
+            .as_i64()
+            .map(i128::from)
+            .or_else(|| n.as_u64().map(i128::from))
+            .or_else(|| n.as_f64().and_then(from_float)),
+        Value::String(s) => s
+            .parse::<i128>()
+            .ok()
+            .or_else(|| s.parse::<f64>().ok().and_then(from_float)),
+        _ => None,
+    }
+}
+
+/// Floating point values may be JSON numbers, numeric strings, "NaN", "Infinity" or "-Infinity"
+fn parse_float(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => match s.as_str() {
+            "NaN" => Some(f64::NAN),
+            "Infinity" => Some(f64::INFINITY),
+            "-Infinity" => Some(f64::NEG_INFINITY),
+            s => s.parse::<f64>().ok().filter(|f| f.is_finite()),
+        },
+        _ => None,
+    }
+}
+
+/// Bytes are base64 encoded, with the standard or URL-safe alphabet, padded or not
+fn decode_base64(s: &str) -> Option<Vec<u8>> {
+    [STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD]
+        .iter()
+        .find_map(|engine| engine.decode(s).ok())
+}
+
+/// Encode a Timestamp or Duration given as a string into its seconds and nanos fields
+fn encode_well_known(
+    value: &Value,
+    path: &str,
+    parse: fn(&str) -> Option<(i64, i32)>,
+    buf: &mut Vec<u8>,
+) -> Result<(), String> {
+    let Value::String(s) = value else {
+        return Err(format!(
+            "{}: expected a string, got {}",
+            display_path(path),
+            json_kind(value)
+        ));
+    };
+    let (seconds, nanos) =
+        parse(s).ok_or_else(|| format!("{}: invalid value \"{}\"", display_path(path), s))?;
+    if seconds != 0 {
+        encode_key(1, WireType::Varint, buf);
+        encode_varint(seconds as u64, buf);
+    }
+    if nanos != 0 {
+        encode_key(2, WireType::Varint, buf);
+        encode_varint(nanos as i64 as u64, buf);
+    }
+    Ok(())
+}
+
+/// Parse the digits of a fractional second into nanoseconds
+fn parse_nanos(digits: &str) -> Option<i32> {
+    if digits.is_empty() || digits.len() > 9 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    format!("{:0<9}", digits).parse().ok()
+}
+
+/// RFC 3339 timestamp, e.g. "1972-01-01T10:00:20.021Z" or "2024-05-01T12:00:00+02:00"
+fn parse_timestamp(s: &str) -> Option<(i64, i32)> {
+    let number = |range: std::ops::Range<usize>| -> Option<i64> {
+        let digits = s.get(range)?;
+        digits
+            .bytes()
+            .all(|b| b.is_ascii_digit())
+            .then(|| digits.parse().ok())?
+    };
+    let bytes = s.as_bytes();
+    if bytes.len() < 20
+        || bytes[4] != b'-'
+        || bytes[7] != b'-'
+        || !matches!(bytes[10], b'T' | b't')
+        || bytes[13] != b':'
+        || bytes[16] != b':'
+    {
+        return None;
+    }
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    if !(1..=12).contains(&month)
+        || day < 1
+        || day > days_in_month(year, month)
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return None;
+    }
+
+    let mut rest = &s[19..];
+    let mut nanos = 0;
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let end = fraction
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(fraction.len());
+        nanos = parse_nanos(&fraction[..end])?;
+        rest = &fraction[end..];
+    }
+
+    let offset = match rest.as_bytes() {
+        [b'Z' | b'z'] => 0,
+        [sign @ (b'+' | b'-'), h1, h2, b':', m1, m2] => {
+            let digits = [*h1, *h2, *m1, *m2];
+            if !digits.iter().all(u8::is_ascii_digit) {
+                return None;
+            }
+            let hours = ((h1 - b'0') * 10 + (h2 - b'0')) as i64;
+            let minutes = ((m1 - b'0') * 10 + (m2 - b'0')) as i64;
+            let offset = hours * 3600 + minutes * 60;
+            if *sign == b'-' {
+                -offset
+            } else {
+                offset
+            }
+        }
+        _ => return None,
+    };
+
+    let days = days_from_civil(year, month, day);
+    Some((
+        days * 86_400 + hour * 3600 + minute * 60 + second - offset,
+        nanos,
+    ))
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days between 1970-01-01 and a date of the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Duration in seconds with an "s" suffix, e.g. "1.5s" or "-0.000001s"
+fn parse_duration(s: &str) -> Option<(i64, i32)> {
+    let s = s.strip_suffix('s')?;
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(s) => (true, s),
+        None => (false, s),
+    };
+    let (seconds, nanos) = match s.split_once('.') {
+        Some((seconds, fraction)) => (seconds, parse_nanos(fraction)?),
+        None => (s, 0),
+    };
+    if seconds.is_empty() || !seconds.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let seconds: i64 = seconds.parse().ok()?;
+    Some(if negative {
+        (-seconds, -nanos)
+    } else {
+        (seconds, nanos)
+    })
+}
@@ -1,38 +1,57 @@
 // Allow clippy warnings for FFI code where unsafe operations are unavoidable
 #![allow(clippy::not_unsafe_ptr_arg_deref)]
 #![allow(clippy::type_complexity)]
+// ZerobusError comes from the SDK and is returned by value throughout the FFI layer
+#![allow(clippy::result_large_err)]
 
-use once_cell::sync::Lazy;
+use once_cell::sync::{Lazy, OnceCell};
 use std::collections::{HashMap, HashSet};
 use std::ffi::{CStr, CString};
+use std::future::Future;
 use std::os::raw::c_char;
+use std::pin::Pin;
 use std::ptr;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::Mutex;
-use tokio::runtime::Runtime;
-use tokio::task::JoinHandle;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex, MutexGuard, PoisonError, RwLock};
+use std::time::{Duration, Instant};
+use tokio::runtime::{Builder, Runtime};
+use tokio::task::{JoinError, JoinHandle};
 
 use async_trait::async_trait;
 use databricks_zerobus_ingest_sdk::databricks::zerobus::RecordType;
 use databricks_zerobus_ingest_sdk::{
-    EncodedRecord, HeadersProvider, StreamConfigurationOptions, TableProperties, ZerobusError,
-    ZerobusResult, ZerobusSdk, ZerobusStream,
+    DefaultTokenFactory, EncodedRecord, HeadersProvider, StreamConfigurationOptions,
+    TableProperties, ZerobusError, ZerobusResult, ZerobusSdk, ZerobusStream,
 };
+use dead_letter::{is_record_rejection, DeadLetterCallback, DeadLetterSink, RejectionTracker};
 use prost::Message;
+use proto_schema::ProtoSchema;
+use spill::{SpillBuffer, SpillError, SpillTicket, SpilledRecords};
 use std::sync::Arc;
+use table_schema::TableSchema;
+
+mod dead_letter;
+mod descriptor;
+mod json_transcode;
+mod logging;
+mod proto_schema;
+mod spill;
+mod table_schema;
 
 // Test module
 #[cfg(test)]
 mod tests;
 
 // Global Tokio runtime for handling async Rust calls
-static RUNTIME: Lazy<Runtime> =
-    Lazy::new(|| Runtime::new().expect("Failed to create Tokio runtime"));
-
-// Global acknowledgment registry
-static ACK_COUNTER: AtomicU64 = AtomicU64::new(1);
-static ACK_REGISTRY: Lazy<Mutex<HashMap<u64, JoinHandle<Result<i64, ZerobusError>>>>> =
-    Lazy::new(|| Mutex::new(HashMap::new()));
+// Built on first use with Tokio's defaults unless zerobus_runtime_init configured it earlier
+static RUNTIME: OnceCell<Runtime> = OnceCell::new();
+
+/// Get the global runtime, starting it with the default configuration if needed
+pub(crate) fn runtime() -> &'static Runtime {
+    RUNTIME.get_or_init(|| {
+        build_runtime(&CRuntimeConfig::default()).expect("Failed to create Tokio runtime")
+    })
+}
 
 // Global cache for header keys to prevent memory leaks
 // Header keys are typically a small set of constant strings (e.g., "Authorization", "Content-Type")
@@ -40,6 +59,17 @@ static ACK_REGISTRY: Lazy<Mutex<HashMap<u64, JoinHandle<Result<i64, ZerobusError
 static HEADER_KEY_CACHE: Lazy<Mutex<HashSet<&'static str>>> =
     Lazy::new(|| Mutex::new(HashSet::new()));
 
+/// Addresses of the streams handed out and not freed yet, so that only the first of several
+/// zerobus_stream_free calls for a stream releases it
+static LIVE_STREAMS: Lazy<Mutex<HashSet<usize>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// Hand a stream over to the caller, to be released with zerobus_stream_free
+fn into_stream_ptr(wrapper: StreamWrapper) -> *mut CZerobusStream {
+    let stream = Box::into_raw(Box::new(wrapper)) as *mut CZerobusStream;
+    LIVE_STREAMS.lock().unwrap().insert(stream as usize);
+    stream
+}
+
 /// Intern a header key string to prevent memory leaks
 /// Only leaks memory for unique keys, not on every call
 pub(crate) fn intern_header_key(key: String) -> &'static str {
@@ -67,12 +97,889 @@ pub struct CZerobusStream {
     _private: [u8; 0],
 }
 
+/// FFI-side state behind a CZerobusStream pointer
+/// Owns the acknowledgment bookkeeping so it is released together with the stream
+pub(crate) struct StreamWrapper {
+    pub(crate) stream: ZerobusStream,
+    /// Headers provider the stream was created with, reused when it is recreated (the SDK's
+    /// copy counts connections into this stream's metrics, see CountingHeadersProvider)
+    headers_provider: Arc<dyn HeadersProvider>,
+    /// Options the stream was created with, reused when it is recreated
+    c_options: Option<CStreamConfigurationOptions>,
+    acks: AckRegistry,
+    ack_callback: Option<AckCallbackHandle>,
+    callback_tracker: Arc<CallbackTracker>,
+    fire_and_forget: bool,
+    /// First failure among fire-and-forget records, reported by the next flush or close
+    deferred_error: Arc<Mutex<Option<ZerobusError>>>,
+    /// Serializes ingestion so ack IDs follow the order of the SDK's offsets
+    ingest_lock: Mutex<()>,
+    metrics: Arc<StreamMetrics>,
+    inflight: Arc<InflightRequests>,
+    schemas: RecordSchemas,
+    validate_records: bool,
+    transcode_json: bool,
+    /// Write-ahead buffer records are persisted in before being queued, shared with streams
+    /// recreated from this one
+    spill: Option<Arc<SpillBuffer>>,
+    /// Persisted records of requests the SDK accepted, by ack ID, until they are acknowledged
+    spill_tickets: Arc<Mutex<HashMap<u64, PersistedRequest>>>,
+    /// Persisted records the SDK refused because the stream had failed, queued on recreation
+    spilled: Mutex<Vec<SpilledRecords>>,
+    /// Receives the records of requests the server rejected as invalid
+    dead_letter: Option<Arc<DeadLetterSink>>,
+    /// Picks the rejected request out of those failed with it, when dead letters are reported
+    rejections: Arc<RejectionTracker>,
+    /// Ack IDs of requests reported to the dead-letter sink, which recreation does not resend
+    dead_lettered: Arc<Mutex<HashSet<u64>>>,
+}
+
+/// Persisted records of a request the SDK accepted
+struct PersistedRequest {
+    ticket: SpillTicket,
+    /// Replayed by the FFI layer itself, so the caller knows no ack ID for it
+    detached: bool,
+}
+
+/// Records queued with the SDK as one request by an ingest call
+pub(crate) enum Submission {
+    Record(EncodedRecord),
+    Batch(Vec<EncodedRecord>),
+}
+
+impl Submission {
+    fn records(&self) -> &[EncodedRecord] {
+        match self {
+            Submission::Record(record) => std::slice::from_ref(record),
+            Submission::Batch(records) => records,
+        }
+    }
+}
+
+/// Acknowledgment of a queued request, resolving to its offset
+type AckFuture = Pin<Box<dyn Future<Output = ZerobusResult<i64>> + Send>>;
+
+/// Message of IngestError::WouldBlock
+const WOULD_BLOCK: &str =
+    "Stream could not accept the request in time (max_inflight_requests reached or another ingest in progress)";
+
+/// Why an ingest call did not queue its records
+#[derive(Debug)]
+pub(crate) enum IngestError {
+    Sdk(ZerobusError),
+    /// The stream could not take the request before the caller's deadline (try_ingest only)
+    WouldBlock,
+    /// The stream had failed, but the records are kept in the spill buffer for its recreation
+    Spilled(ZerobusError),
+    Spill(SpillError),
+}
+
+impl From<ZerobusError> for IngestError {
+    fn from(err: ZerobusError) -> Self {
+        IngestError::Sdk(err)
+    }
+}
+
+/// Schemas a stream checks or converts records with, shared with streams recreated from it
+#[derive(Clone, Default)]
+pub(crate) struct RecordSchemas {
+    /// Built from the descriptor for validate_records (proto streams) and transcode_json
+    pub(crate) proto: Option<Arc<ProtoSchema>>,
+    /// Parsed from table_schema_json
+    pub(crate) table: Option<Arc<TableSchema>>,
+}
+
+impl StreamWrapper {
+    fn new(
+        (stream, headers_provider, metrics): OpenedStream,
+        options: Option<&CStreamConfigurationOptions>,
+        schemas: RecordSchemas,
+        spill: Option<Arc<SpillBuffer>>,
+        dead_letter: Option<Arc<DeadLetterSink>>,
+    ) -> Self {
+        let ack_callback = options.and_then(|opts| {
+            opts.ack_callback
+                .map(|callback| AckCallbackHandle::new(callback, opts.ack_callback_user_data))
+        });
+
+        Self {
+            stream,
+            headers_provider,
+            c_options: options.copied(),
+            acks: AckRegistry::default(),
+            ack_callback,
+            callback_tracker: Arc::new(CallbackTracker::default()),
+            fire_and_forget: options.is_some_and(|opts| opts.fire_and_forget),
+            deferred_error: Arc::new(Mutex::new(None)),
+            ingest_lock: Mutex::new(()),
+            metrics,
+            inflight: Arc::new(InflightRequests::default()),
+            schemas,
+            validate_records: options.is_some_and(|opts| opts.validate_records),
+            transcode_json: options.is_some_and(|opts| opts.transcode_json),
+            spill,
+            spill_tickets: Arc::new(Mutex::new(HashMap::new())),
+            spilled: Mutex::new(Vec::new()),
+            dead_letter,
+            rejections: Arc::new(RejectionTracker::default()),
+            dead_lettered: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Turn a JSON payload into the record sent to the server
+    /// With transcode_json the JSON is encoded as a protobuf record of the stream's descriptor
+    fn json_record(&self, json: String) -> ZerobusResult<EncodedRecord> {
+        match &self.schemas.proto {
+            Some(schema) if self.transcode_json => schema
+                .transcode_json(&json)
+                .map(EncodedRecord::Proto)
+                .map_err(invalid_argument),
+            _ => Ok(EncodedRecord::Json(json)),
+        }
+    }
+
+    /// Check a record against the stream's schemas when validate_records is enabled
+    fn validate_record(&self, record: &EncodedRecord) -> ZerobusResult<()> {
+        if !self.validate_records {
+            return Ok(());
+        }
+        match record {
+            EncodedRecord::Proto(bytes) => self.validate_proto(bytes),
+            EncodedRecord::Json(json) => {
+                table_schema::validate_json_record(json, self.schemas.table.as_deref())
+                    .map_err(invalid_argument)
+            }
+        }
+    }
+
+    /// validate_record for proto bytes the stream does not own yet
+    fn validate_proto(&self, bytes: &[u8]) -> ZerobusResult<()> {
+        match &self.schemas.proto {
+            Some(schema) if self.validate_records => {
+                schema.validate(bytes).map_err(invalid_argument)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Queue records with the SDK and register the acknowledgment they produce
+    /// The ack ID is taken under the ingest lock, so the n-th ack ID always belongs to the
+    /// n-th batch the SDK accepted (this is what maps unacknowledged batches back to ack IDs)
+    fn ingest(&self, submission: Submission) -> Result<u64, IngestError> {
+        let guard = self.ingest_lock.lock().unwrap();
+        self.submit(guard, submission, None, false)
+    }
+
+    /// Like ingest, but waits at most `wait` for the stream to drop below max_inflight_requests
+    /// and for concurrent ingest calls to finish, instead of blocking until they do (None waits
+    /// as long as ingest would)
+    /// Fails with IngestError::WouldBlock if the request cannot be queued when the wait is over
+    fn try_ingest(
+        &self,
+        submission: Submission,
+        wait: Option<Duration>,
+    ) -> Result<u64, IngestError> {
+        match wait {
+            Some(wait) => self.ingest_when_ready(wait, || submission),
+            None => self.ingest(submission),
+        }
+    }
+
+    /// The waiting part of try_ingest: the submission is only built once the stream has room
+    /// for it, so nothing is consumed when IngestError::WouldBlock is returned
+    fn ingest_when_ready(
+        &self,
+        wait: Duration,
+        submission: impl FnOnce() -> Submission,
+    ) -> Result<u64, IngestError> {
+        let deadline = Instant::now().checked_add(wait);
+        let limit = self.stream.options.max_inflight_requests;
+
+        loop {
+            // Created before checking so a release in between still wakes the wait below
+            let changed = self.inflight.changed.notified();
+            // The lock may be held by an ingest call blocked in the SDK, so never block on it
+            if self.inflight.count() < limit {
+                if let Ok(guard) = self.ingest_lock.try_lock() {
+                    if self.inflight.count() < limit {
+                        return self.submit(guard, submission(), None, false);
+                    }
+                    // The last slot was taken in the meantime
+                    continue;
+                }
+            }
+
+            // Whether the stream is full or the lock is busy, never wait past the caller's deadline
+            let remaining = deadline.map_or(Duration::MAX, |deadline| {
+                deadline.saturating_duration_since(Instant::now())
+            });
+            let timed_out = remaining.is_zero()
+                || runtime()
+                    .block_on(async { tokio::time::timeout(remaining, changed).await })
+                    .is_err();
+            if timed_out {
+                return Err(IngestError::WouldBlock);
+            }
+        }
+    }
+
+    /// Queue records that are already persisted in the spill buffer, without checking them again
+    /// Detached requests are tracked by the FFI layer only and get no caller-visible ack
+    fn resubmit(
+        &self,
+        records: Vec<EncodedRecord>,
+        ticket: Option<SpillTicket>,
+        detached: bool,
+    ) -> Result<u64, IngestError> {
+        let guard = self.ingest_lock.lock().unwrap();
+        self.submit(guard, Submission::Batch(records), ticket, detached)
+    }
+
+    /// Queue the records left in the spill directory by an earlier stream
+    fn replay_spilled(&self, leftovers: Vec<SpilledRecords>) -> Result<(), IngestError> {
+        for spilled in leftovers {
+            self.resubmit(spilled.records, Some(spilled.ticket), true)?;
+        }
+        Ok(())
+    }
+
+    /// Hand records to the SDK under the ingest lock and track the request until it is acknowledged
+    /// With a spill buffer the records are persisted first, unless `persisted` says they already are
+    fn submit(
+        &self,
+        guard: MutexGuard<'_, ()>,
+        submission: Submission,
+        persisted: Option<SpillTicket>,
+        detached: bool,
+    ) -> Result<u64, IngestError> {
+        let res = self.submit_locked(submission, persisted, detached);
+        drop(guard);
+        // Wake try_ingest calls that found the lock taken
+        self.inflight.changed.notify_waiters();
+        res
+    }
+
+    fn submit_locked(
+        &self,
+        submission: Submission,
+        persisted: Option<SpillTicket>,
+        detached: bool,
+    ) -> Result<u64, IngestError> {
+        let records = submission.records();
+        // The SDK performs the same check, but persisted records must not be refused afterwards
+        let record_type = self.stream.options.record_type;
+        if !records.iter().all(|record| {
+            matches!(
+                (record, record_type),
+                (EncodedRecord::Proto(_), RecordType::Proto)
+                    | (EncodedRecord::Json(_), RecordType::Json)
+            )
+        }) {
+            return Err(IngestError::Sdk(invalid_argument(
+                "Record type does not match stream configuration",
+            )));
+        }
+        let record_count = records.len() as u64;
+        let byte_count = records.iter().map(encoded_len).sum::<usize>() as u64;
+
+        let ticket = match &self.spill {
+            Some(spill) => Some(match persisted {
+                Some(ticket) => ticket,
+                None => spill.append(records).map_err(IngestError::Spill)?,
+            }),
+            None => None,
+        };
+        // The SDK takes the records, so requests that may end up in the dead-letter sink keep a
+        // copy, unless they can be read back from the spill buffer
+        let retained = (ticket.is_none() && self.dead_letter.is_some()).then(|| records.to_vec());
+
+        let ack_future = match runtime().block_on(self.send(submission)) {
+            Ok(ack_future) => ack_future,
+            Err(err) => {
+                let (Some(spill), Some(ticket)) = (&self.spill, ticket) else {
+                    return Err(IngestError::Sdk(err));
+                };
+                // Kept for recreation, which queues the records the failed stream refused
+                return Err(match spill.read(&ticket) {
+                    Ok(records) => {
+                        self.spilled
+                            .lock()
+                            .unwrap()
+                            .push(SpilledRecords { ticket, records });
+                        IngestError::Spilled(err)
+                    }
+                    Err(read_err) => {
+                        tracing::warn!("Failed to read back spilled records: {}", read_err);
+                        IngestError::Sdk(err)
+                    }
+                });
+            }
+        };
+        self.metrics.record_ingested(record_count, byte_count);
+        self.inflight.acquire();
+
+        let ack_id = self.acks.next_id();
+        if let Some(ticket) = ticket {
+            self.spill_tickets
+                .lock()
+                .unwrap()
+                .insert(ack_id, PersistedRequest { ticket, detached });
+        }
+        if self.dead_letter.is_some() {
+            self.rejections.queued(ack_id);
+        }
+
+        let metrics = Arc::clone(&self.metrics);
+        let inflight = Arc::clone(&self.inflight);
+        let spill = self.spill.clone();
+        let spill_tickets = Arc::clone(&self.spill_tickets);
+        let dead_letter = self.dead_letter.clone();
+        let rejections = Arc::clone(&self.rejections);
+        let dead_lettered = Arc::clone(&self.dead_lettered);
+        let callback_tracker = Arc::clone(&self.callback_tracker);
+        let ingested_at = Instant::now();
+        let bookkeeping = async move {
+            let res = ack_future.await;
+            metrics.record_ack(res.is_ok(), record_count, ingested_at.elapsed());
+            let rejected = match &dead_letter {
+                Some(_) => {
+                    let rejection = res.as_ref().is_err_and(is_record_rejection);
+                    rejections.settle(ack_id, rejection).await
+                }
+                None => false,
+            };
+            // Other requests failed with the rejection are sent again on recreation
+            let reported = match (&res, &dead_letter) {
+                (Err(err), Some(sink)) if rejected => {
+                    let records = match (retained, &spill) {
+                        (Some(records), _) => Ok(records),
+                        (None, Some(spill)) => spill_tickets
+                            .lock()
+                            .unwrap()
+                            .get(&ack_id)
+                            .map_or(Ok(Vec::new()), |request| spill.read(&request.ticket)),
+                        (None, None) => Ok(Vec::new()),
+                    };
+                    match records {
+                        Ok(records) => sink.deliver(ack_id, &records, err, &callback_tracker),
+                        Err(read_err) => {
+                            tracing::warn!(
+                                ack_id,
+                                "Failed to read back dead letters: {}",
+                                read_err
+                            );
+                            false
+                        }
+                    }
+                }
+                _ => false,
+            };
+            if reported {
+                dead_lettered.lock().unwrap().insert(ack_id);
+            }
+            // Records of other failed requests stay persisted until the stream is recreated
+            if let (true, Some(spill)) = (res.is_ok() || reported, spill) {
+                if let Some(request) = spill_tickets.lock().unwrap().remove(&ack_id) {
+                    spill.release(request.ticket);
+                }
+            }
+            // Last, so a settled request is fully accounted for once its slot is free
+            inflight.release();
+            res
+        };
+        // Runs in its own task, so freeing the stream aborts only the caller-facing handle below
+        // and never skips the metrics, inflight slot, spill or dead-letter bookkeeping
+        let bookkeeping = runtime().spawn(bookkeeping);
+        let ack_future = async move {
+            bookkeeping.await.unwrap_or_else(|err| {
+                Err(ZerobusError::InvalidStateError(format!(
+                    "Acknowledgment task failed: {}",
+                    err
+                )))
+            })
+        };
+
+        if detached {
+            runtime().spawn(async move {
+                if let Err(err) = ack_future.await {
+                    tracing::warn!("Replayed spill records were not acknowledged: {}", err);
+                }
+            });
+            return Ok(ack_id);
+        }
+        Ok(self.register_ack(ack_id, ack_future))
+    }
+
+    /// Queue a submission with the SDK, returning the future of its acknowledgment
+    async fn send(&self, submission: Submission) -> ZerobusResult<AckFuture> {
+        match submission {
+            Submission::Record(record) => Ok(Box::pin(self.stream.ingest_record(record).await?)),
+            Submission::Batch(records) => {
+                let ack_future = self.stream.ingest_records(records).await?;
+                Ok(Box::pin(async move {
+                    // Empty batches are rejected before reaching the SDK, so an offset is always present
+                    ack_future.await?.ok_or_else(|| {
+                        ZerobusError::InvalidStateError(
+                            "Batch acknowledged without an offset".to_string(),
+                        )
+                    })
+                }))
+            }
+        }
+    }
+
+    /// Queue a batch as a single request and register one acknowledgment for it
+    /// `wait` bounds the wait for a saturated stream like try_ingest
+    fn ingest_batch(
+        &self,
+        records: Vec<EncodedRecord>,
+        wait: Option<Duration>,
+    ) -> Result<u64, IngestError> {
+        for (i, record) in records.iter().enumerate() {
+            self.validate_record(record)
+                .map_err(|err| in_batch_record(i, err))?;
+        }
+        self.try_ingest(Submission::Batch(records), wait)
+    }
+
+    /// Flush the spill buffer's current segment to disk
+    fn sync_spill(&self) -> ZerobusResult<()> {
+        match &self.spill {
+            Some(spill) => spill.sync().map_err(|err| {
+                ZerobusError::InvalidStateError(format!("Failed to sync the spill buffer: {}", err))
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Report a failed fire-and-forget record once the stream operation itself succeeded
+    fn with_deferred_error(&self, res: ZerobusResult<()>) -> ZerobusResult<()> {
+        res?;
+        match self.deferred_error.lock().unwrap().take() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Number of acknowledgments that have been handed out but not yet collected or delivered
+    fn outstanding_acks(&self) -> usize {
+        self.acks.len() + self.callback_tracker.pending.load(Ordering::SeqCst)
+    }
+
+    /// Track an acknowledgment future under the given ack ID and return the ID handed to the caller
+    /// With an ack callback configured the result is delivered to the callback instead of the registry
+    /// In fire-and-forget mode only failures are kept (for flush/close) and 0 is returned
+    fn register_ack<F>(&self, ack_id: u64, ack_future: F) -> u64
+    where
+        F: Future<Output = ZerobusResult<i64>> + Send + 'static,
+    {
+        if self.fire_and_forget {
+            let deferred_error = Arc::clone(&self.deferred_error);
+            runtime().spawn(async move {
+                if let Err(err) = ack_future.await {
+                    deferred_error.lock().unwrap().get_or_insert(err);
+                }
+            });
+            return 0;
+        }
+
+        match self.ack_callback {
+            Some(callback) => {
+                self.callback_tracker.pending.fetch_add(1, Ordering::SeqCst);
+                runtime().spawn(deliver_ack(
+                    callback,
+                    Arc::clone(&self.callback_tracker),
+                    ack_id,
+                    ack_future,
+                ));
+            }
+            None => self.acks.spawn(ack_id, ack_future),
+        }
+        ack_id
+    }
+}
+
+impl Drop for StreamWrapper {
+    fn drop(&mut self) {
+        // Acks nobody collected would otherwise keep their tasks alive after the stream is gone
+        // (their bookkeeping runs in separate tasks and still completes)
+        self.acks.abort_all();
+        // The host may release its callback state together with the stream
+        self.callback_tracker.close();
+    }
+}
+
+/// Prefix a rejected record's error with its position in the batch
+fn in_batch_record(index: usize, err: ZerobusError) -> ZerobusError {
+    match err {
+        ZerobusError::InvalidArgument(msg) => {
+            invalid_argument(format!("record {}: {}", index, msg))
+        }
+        err => err,
+    }
+}
+
+/// Size of a record's encoded payload in bytes
+fn encoded_len(record: &EncodedRecord) -> usize {
+    match record {
+        EncodedRecord::Proto(bytes) => bytes.len(),
+        EncodedRecord::Json(json) => json.len(),
+    }
+}
+
+/// Requests the SDK accepted that are neither acknowledged nor failed yet
+/// Mirrors the SDK's own max_inflight_requests accounting (one request per ingest call). A slot
+/// is released here only after the SDK released it, so a free slot here is free in the SDK too
+#[derive(Default)]
+pub(crate) struct InflightRequests {
+    count: AtomicUsize,
+    /// Notified when a slot is released or the ingest lock becomes free
+    changed: tokio::sync::Notify,
+}
+
+impl InflightRequests {
+    pub(crate) fn count(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+
+    fn acquire(&self) {
+        self.count.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn release(&self) {
+        self.count.fetch_sub(1, Ordering::SeqCst);
+        self.changed.notify_waiters();
+    }
+
+    /// Wait at most `wait` for every request to be settled, returning whether they are
+    fn wait_settled(&self, wait: Duration) -> bool {
+        runtime().block_on(async {
+            tokio::time::timeout(wait, async {
+                loop {
+                    let changed = self.changed.notified();
+                    if self.count() == 0 {
+                        return;
+                    }
+                    changed.await;
+                }
+            })
+            .await
+            .is_ok()
+        })
+    }
+}
+
+/// Counters maintained along the ingest and ack paths of a stream
+#[derive(Default)]
+pub(crate) struct StreamMetrics {
+    records_ingested: AtomicU64,
+    bytes_ingested: AtomicU64,
+    records_acked: AtomicU64,
+    records_failed: AtomicU64,
+    /// Number of acknowledgments (one per ingest call) that contributed to the latency figures
+    acks_completed: AtomicU64,
+    ack_latency_total_us: AtomicU64,
+    ack_latency_max_us: AtomicU64,
+    /// Connections the SDK attempted to open for the stream, see CountingHeadersProvider
+    connection_attempts: AtomicU64,
+    /// Connection attempts made until the stream was created
+    creation_attempts: AtomicU64,
+}
+
+impl StreamMetrics {
+    pub(crate) fn record_ingested(&self, record_count: u64, byte_count: u64) {
+        self.records_ingested
+            .fetch_add(record_count, Ordering::Relaxed);
+        self.bytes_ingested.fetch_add(byte_count, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_ack(&self, success: bool, record_count: u64, latency: Duration) {
+        if success {
+            self.records_acked
+                .fetch_add(record_count, Ordering::Relaxed);
+            let latency_us = latency.as_micros().min(u64::MAX as u128) as u64;
+            self.acks_completed.fetch_add(1, Ordering::Relaxed);
+            self.ack_latency_total_us
+                .fetch_add(latency_us, Ordering::Relaxed);
+            self.ack_latency_max_us
+                .fetch_max(latency_us, Ordering::Relaxed);
+        } else {
+            self.records_failed
+                .fetch_add(record_count, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn record_connection_attempt(&self) {
+        self.connection_attempts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Mark the stream as created: later connection attempts are recoveries
+    pub(crate) fn record_stream_created(&self) {
+        self.creation_attempts.store(
+            self.connection_attempts.load(Ordering::Relaxed),
+            Ordering::Relaxed,
+        );
+    }
+
+    pub(crate) fn snapshot(&self) -> CStreamMetrics {
+        let records_ingested = self.records_ingested.load(Ordering::Relaxed);
+        let records_acked = self.records_acked.load(Ordering::Relaxed);
+        let records_failed = self.records_failed.load(Ordering::Relaxed);
+        let acks_completed = self.acks_completed.load(Ordering::Relaxed);
+        let ack_latency_total_us = self.ack_latency_total_us.load(Ordering::Relaxed);
+
+        CStreamMetrics {
+            records_ingested,
+            bytes_ingested: self.bytes_ingested.load(Ordering::Relaxed),
+            records_acked,
+            records_failed,
+            records_inflight: records_ingested.saturating_sub(records_acked + records_failed),
+            ack_latency_avg_us: ack_latency_total_us
+                .checked_div(acks_completed)
+                .unwrap_or(0),
+            ack_latency_max_us: self.ack_latency_max_us.load(Ordering::Relaxed),
+            recoveries: self
+                .connection_attempts
+                .load(Ordering::Relaxed)
+                .saturating_sub(self.creation_attempts.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Point-in-time snapshot of a stream's counters, see zerobus_stream_get_metrics
+/// Counters are cumulative since the stream was created
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CStreamMetrics {
+    /// Records accepted by the stream (a batch counts each of its records)
+    pub records_ingested: u64,
+    /// Payload bytes of the accepted records, as encoded for the server (JSON records after
+    /// transcoding); counted once per record, so gRPC framing, compression and records resent
+    /// after a recovery are not included
+    pub bytes_ingested: u64,
+    /// Records acknowledged by the server
+    pub records_acked: u64,
+    /// Records whose acknowledgment failed
+    pub records_failed: u64,
+    /// Records accepted but neither acknowledged nor failed yet
+    pub records_inflight: u64,
+    /// Mean time from ingestion to server acknowledgment, in microseconds
+    pub ack_latency_avg_us: u64,
+    /// Longest time from ingestion to server acknowledgment, in microseconds
+    pub ack_latency_max_us: u64,
+    /// Times the SDK tried to reopen the stream after a retryable failure (see the recovery
+    /// option), each retry of a recovery included; counted from the headers the SDK requests
+    /// for every connection attempt
+    pub recoveries: u64,
+}
+
+/// Per-stream acknowledgment registry, keyed by ack IDs unique within the stream
+#[derive(Default)]
+pub(crate) struct AckRegistry {
+    last_id: AtomicU64,
+    entries: Mutex<HashMap<u64, AckEntry>>,
+    /// Notified whenever a registered acknowledgment gets its outcome
+    settled: Arc<tokio::sync::Notify>,
+}
+
+/// A registered acknowledgment task
+struct AckEntry {
+    handle: JoinHandle<ZerobusResult<i64>>,
+    /// Set by the task once it has its outcome, so waiters can race entries without taking them
+    settled: Arc<AtomicBool>,
+}
+
+impl AckRegistry {
+    pub(crate) fn next_id(&self) -> u64 {
+        self.last_id.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// The most recently assigned ack ID (0 if none)
+    pub(crate) fn last_id(&self) -> u64 {
+        self.last_id.load(Ordering::SeqCst)
+    }
+
+    /// Run the acknowledgment future on the runtime and register it under `ack_id`
+    pub(crate) fn spawn<F>(&self, ack_id: u64, ack_future: F)
+    where
+        F: Future<Output = ZerobusResult<i64>> + Send + 'static,
+    {
+        let settled = Arc::new(AtomicBool::new(false));
+        let task_settled = Arc::clone(&settled);
+        let notify = Arc::clone(&self.settled);
+        let handle = runtime().spawn(async move {
+            let res = ack_future.await;
+            task_settled.store(true, Ordering::SeqCst);
+            notify.notify_waiters();
+            res
+        });
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(ack_id, AckEntry { handle, settled });
+    }
+
+    /// Remove the handle only if its task has completed
+    /// Returns Err(()) for unknown IDs and Ok(None) while the ack is still pending
+    pub(crate) fn remove_if_finished(
+        &self,
+        ack_id: u64,
+    ) -> Result<Option<JoinHandle<ZerobusResult<i64>>>, ()> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(&ack_id) {
+            Some(entry) if entry.handle.is_finished() => {
+                Ok(entries.remove(&ack_id).map(|entry| entry.handle))
+            }
+            Some(_) => Ok(None),
+            None => Err(()),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Remove the handles of several ack IDs under one lock, None for unknown or repeated IDs
+    pub(crate) fn remove_many(
+        &self,
+        ack_ids: &[u64],
+    ) -> Vec<Option<JoinHandle<ZerobusResult<i64>>>> {
+        let mut entries = self.entries.lock().unwrap();
+        ack_ids
+            .iter()
+            .map(|id| entries.remove(id).map(|entry| entry.handle))
+            .collect()
+    }
+
+    /// Check that every given ack ID is registered and listed once
+    /// Returns the first unknown or repeated ID as the error
+    pub(crate) fn check_all_registered(&self, ack_ids: &[u64]) -> Result<(), u64> {
+        let entries = self.entries.lock().unwrap();
+        let mut seen = HashSet::with_capacity(ack_ids.len());
+        match ack_ids
+            .iter()
+            .find(|&&id| !entries.contains_key(&id) || !seen.insert(id))
+        {
+            Some(&ack_id) => Err(ack_id),
+            None => Ok(()),
+        }
+    }
+
+    /// Wait until one of the given acknowledgments has its outcome and remove only that one,
+    /// returning its index in `ack_ids` and its handle, which completes right away
+    /// The other entries stay registered throughout, so they can be awaited concurrently; IDs
+    /// taken by such calls are skipped, and None is returned once no listed ID is left
+    pub(crate) async fn take_first_settled(
+        &self,
+        ack_ids: &[u64],
+    ) -> Option<(usize, JoinHandle<ZerobusResult<i64>>)> {
+        loop {
+            // Register for the notification before checking, so an outcome in between is not missed
+            let notified = self.settled.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            {
+                let mut entries = self.entries.lock().unwrap();
+                let mut any_left = false;
+                for (i, id) in ack_ids.iter().enumerate() {
+                    let Some(entry) = entries.get(id) else {
+                        continue;
+                    };
+                    if entry.settled.load(Ordering::SeqCst) || entry.handle.is_finished() {
+                        return entries.remove(id).map(|entry| (i, entry.handle));
+                    }
+                    any_left = true;
+                }
+                if !any_left {
+                    return None;
+                }
+            }
+            notified.await;
+        }
+    }
+
+    pub(crate) fn abort_all(&self) {
+        for (_, entry) in self.entries.lock().unwrap().drain() {
+            entry.handle.abort();
+        }
+    }
+}
+
+/// Stable numeric error codes reported in CResult
+/// Values are part of the C ABI: never renumber, only append
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CErrorCode {
+    Ok = 0,
+    /// Failure inside the FFI layer itself (e.g. a panicked task or a runtime that cannot start)
+    Internal = 1,
+    InvalidArgument = 2,
+    /// Authentication or authorization failed (bad credentials, token or permissions)
+    AuthFailure = 3,
+    /// The gRPC channel could not be opened
+    ChannelError = 4,
+    TlsError = 5,
+    InvalidEndpoint = 6,
+    InvalidTableName = 7,
+    /// The server refused to create the stream
+    CreateStreamFailed = 8,
+    StreamClosed = 9,
+    /// A deadline was exceeded (flush timeout, server ack timeout)
+    Timeout = 10,
+    UnexpectedResponse = 11,
+    /// The call is not allowed in the current state (e.g. the runtime has already started)
+    InvalidState = 12,
+    /// The stream could not take the request without blocking or before the timeout, see
+    /// zerobus_stream_try_ingest_proto_record
+    WouldBlock = 13,
+    /// zerobus_stream_await_ack_timeout gave up; the acknowledgment is still pending
+    /// Also returned by zerobus_sdk_recreate_stream when the old stream's requests did not settle
+    /// within settle_timeout_ms
+    WaitTimedOut = 14,
+    /// The stream had failed, but the records were persisted in its spill directory and are
+    /// queued again when it is recreated: they must not be ingested again
+    Spilled = 15,
+    /// The spill directory reached spill_max_bytes
+    SpillFull = 16,
+}
+
+impl From<&ZerobusError> for CErrorCode {
+    fn from(err: &ZerobusError) -> Self {
+        // Status-carrying variants are refined by their gRPC code
+        let status_code = |status: &tonic::Status, fallback: CErrorCode| match status.code() {
+            tonic::Code::Unauthenticated | tonic::Code::PermissionDenied => CErrorCode::AuthFailure,
+            tonic::Code::DeadlineExceeded => CErrorCode::Timeout,
+            tonic::Code::InvalidArgument => CErrorCode::InvalidArgument,
+            _ => fallback,
+        };
+
+        match err {
+            ZerobusError::ChannelCreationError(_) => CErrorCode::ChannelError,
+            ZerobusError::CreateStreamError(status) => {
+                status_code(status, CErrorCode::CreateStreamFailed)
+            }
+            ZerobusError::FailedToEstablishTlsConnectionError => CErrorCode::TlsError,
+            ZerobusError::InvalidZerobusEndpointError(_) => CErrorCode::InvalidEndpoint,
+            ZerobusError::InvalidTableName(_) => CErrorCode::InvalidTableName,
+            ZerobusError::InvalidUCEndpointError(_) => CErrorCode::InvalidEndpoint,
+            ZerobusError::InvalidUCTokenError(_) => CErrorCode::AuthFailure,
+            ZerobusError::StreamClosedError(status) => {
+                status_code(status, CErrorCode::StreamClosed)
+            }
+            ZerobusError::InvalidArgument(_) => CErrorCode::InvalidArgument,
+            ZerobusError::UnexpectedStreamResponseError(_) => CErrorCode::UnexpectedResponse,
+            ZerobusError::InvalidStateError(_) => CErrorCode::InvalidState,
+        }
+    }
+}
+
 // Result type for FFI calls
 #[repr(C)]
 pub struct CResult {
     pub success: bool,
     pub error_message: *mut c_char,
     pub is_retryable: bool,
+    pub error_code: CErrorCode,
 }
 
 impl CResult {
@@ -81,11 +988,13 @@ impl CResult {
             success: true,
             error_message: ptr::null_mut(),
             is_retryable: false,
+            error_code: CErrorCode::Ok,
         }
     }
 
     fn error(err: ZerobusError) -> Self {
         let is_retryable = err.is_retryable();
+        let error_code = CErrorCode::from(&err);
         let message = CString::new(err.to_string())
             .unwrap_or_else(|_| CString::new("Unknown error").unwrap());
 
@@ -93,6 +1002,7 @@ impl CResult {
             success: false,
             error_message: message.into_raw(),
             is_retryable,
+            error_code,
         }
     }
 }
@@ -109,6 +1019,157 @@ pub struct CStreamConfigurationOptions {
     pub server_lack_of_ack_timeout_ms: u64,
     pub flush_timeout_ms: u64,
     pub record_type: i32,
+    /// Optional callback receiving every acknowledgment (NULL to await acks by ID instead)
+    pub ack_callback: AckCallback,
+    /// Opaque pointer passed back to ack_callback
+    pub ack_callback_user_data: *mut std::ffi::c_void,
+    /// Do not track per-record acknowledgments; ingest calls return ack ID 0 and
+    /// record failures are reported by the next flush or close
+    pub fire_and_forget: bool,
+    /// Check every record before queueing it and reject malformed records with an
+    /// InvalidArgument error naming the offending field: proto records are decoded against the
+    /// stream's descriptor, JSON records must be JSON objects matching table_schema_json if set
+    pub validate_records: bool,
+    /// Accept JSON records on a proto stream: they are parsed with the proto3 JSON mapping of
+    /// the stream's descriptor and sent as protobuf records; conversion errors fail the ingest call
+    pub transcode_json: bool,
+    /// Columns JSON records are validated against (NULL = only require a JSON object), as a JSON
+    /// array of {"name", "type", "nullable"} objects with Delta types such as "BIGINT" or
+    /// "ARRAY<STRING>"; requires validate_records on a JSON stream. Only read during stream creation
+    pub table_schema_json: *const c_char,
+    /// Directory records are persisted in before being queued (NULL = no spill buffer); records
+    /// left there by an earlier stream are queued again when the stream is created, and records
+    /// of a failed stream when it is recreated. One stream at a time may use a directory. Only
+    /// read during stream creation
+    pub spill_dir: *const c_char,
+    /// Size cap of the spill directory in bytes (0 = no cap); ingest calls fail with SpillFull
+    /// once it is reached
+    pub spill_max_bytes: u64,
+    /// Optional callback receiving every record of a request the server rejected as invalid
+    /// (NULL to not report them). Requests of a stream that failed as a whole are not reported,
+    /// but re-ingested by zerobus_sdk_recreate_stream. Unless spill_dir is set, reporting
+    /// requires a copy of every record ingested
+    pub dead_letter_callback: DeadLetterCallback,
+    /// Opaque pointer passed back to dead_letter_callback
+    pub dead_letter_user_data: *mut std::ffi::c_void,
+    /// JSON Lines file the same records are appended to (NULL to not write them), one object
+    /// per record with ack_id, record_type ("proto" or "json"), payload (base64 for proto
+    /// records, the JSON text for JSON records), error_code and error_message
+    /// Only read during stream creation
+    pub dead_letter_path: *const c_char,
+}
+
+/// Reject option combinations the FFI layer cannot honor
+pub(crate) fn validate_stream_options(opts: &CStreamConfigurationOptions) -> Result<(), String> {
+    if opts.fire_and_forget && opts.ack_callback.is_some() {
+        return Err("ack_callback cannot be used together with fire_and_forget".to_string());
+    }
+    if opts.transcode_json && opts.record_type != 1 {
+        return Err("transcode_json requires the proto record type".to_string());
+    }
+    let json_validation = opts.validate_records && opts.record_type == 2;
+    if !opts.table_schema_json.is_null() && !json_validation {
+        return Err(
+            "table_schema_json requires validate_records and the JSON record type".to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// Build the schemas records are validated against or transcoded with, as the options ask for
+pub(crate) fn record_schemas(
+    opts: Option<&CStreamConfigurationOptions>,
+    descriptor: Option<&prost_types::DescriptorProto>,
+) -> ZerobusResult<RecordSchemas> {
+    let Some(opts) = opts else {
+        return Ok(RecordSchemas::default());
+    };
+
+    let proto = match descriptor {
+        Some(descriptor) if opts.validate_records || opts.transcode_json => Some(Arc::new(
+            ProtoSchema::new(descriptor).map_err(invalid_argument)?,
+        )),
+        None if opts.transcode_json => {
+            return Err(invalid_argument(
+                "transcode_json requires a descriptor to transcode JSON records with",
+            ))
+        }
+        // JSON streams have no descriptor to validate against
+        _ => None,
+    };
+
+    let table = if opts.table_schema_json.is_null() {
+        None
+    } else {
+        let json = unsafe { c_str_to_string(opts.table_schema_json).map_err(invalid_argument)? };
+        Some(Arc::new(
+            TableSchema::parse(&json).map_err(invalid_argument)?,
+        ))
+    };
+
+    Ok(RecordSchemas { proto, table })
+}
+
+/// Records left in a spill directory by an earlier stream, to be queued on a new one
+type SpillLeftovers = Vec<SpilledRecords>;
+
+/// Open the spill buffer the options ask for, with the records left in its directory
+pub(crate) fn open_spill(
+    opts: Option<&CStreamConfigurationOptions>,
+) -> ZerobusResult<(Option<Arc<SpillBuffer>>, SpillLeftovers)> {
+    let Some(opts) = opts.filter(|opts| !opts.spill_dir.is_null()) else {
+        return Ok((None, Vec::new()));
+    };
+    let dir = unsafe { c_str_to_string(opts.spill_dir).map_err(invalid_argument)? };
+    let (spill, leftovers) = SpillBuffer::open(&dir, opts.spill_max_bytes).map_err(|err| {
+        invalid_argument(format!("Failed to open spill directory {}: {}", dir, err))
+    })?;
+    Ok((Some(Arc::new(spill)), leftovers))
+}
+
+/// Build the dead-letter sink the options ask for
+pub(crate) fn open_dead_letter(
+    opts: Option<&CStreamConfigurationOptions>,
+) -> ZerobusResult<Option<Arc<DeadLetterSink>>> {
+    let Some(opts) = opts else {
+        return Ok(None);
+    };
+    let path = if opts.dead_letter_path.is_null() {
+        None
+    } else {
+        Some(unsafe { c_str_to_string(opts.dead_letter_path).map_err(invalid_argument)? })
+    };
+    let sink = DeadLetterSink::new(
+        opts.dead_letter_callback,
+        opts.dead_letter_user_data,
+        path.as_deref(),
+    )
+    .map_err(|err| invalid_argument(format!("Failed to open dead-letter file: {}", err)))?;
+    Ok(sink.map(Arc::new))
+}
+
+/// Queue the records a new stream found in its spill directory and hand the stream to the caller
+/// Runs outside of the creation's block_on, since ingestion blocks on the runtime itself
+fn finish_create_stream(
+    res: ZerobusResult<(StreamWrapper, SpillLeftovers)>,
+    result: *mut CResult,
+) -> *mut CZerobusStream {
+    let res = res
+        .map_err(IngestError::Sdk)
+        .and_then(|(wrapper, leftovers)| {
+            wrapper.replay_spilled(leftovers)?;
+            Ok(wrapper)
+        });
+    match res {
+        Ok(wrapper) => {
+            write_success_result(result);
+            into_stream_ptr(wrapper)
+        }
+        Err(err) => {
+            write_ingest_error(result, err);
+            ptr::null_mut()
+        }
+    }
 }
 
 impl From<CStreamConfigurationOptions> for StreamConfigurationOptions {
@@ -130,28 +1191,211 @@ impl From<CStreamConfigurationOptions> for StreamConfigurationOptions {
     }
 }
 
-// Helper to convert C string to Rust String
-unsafe fn c_str_to_string(c_str: *const c_char) -> Result<String, &'static str> {
-    if c_str.is_null() {
-        return Err("Null pointer passed");
-    }
-    CStr::from_ptr(c_str)
-        .to_str()
-        .map(|s| s.to_string())
-        .map_err(|_| "Invalid UTF-8 string")
+/// Configuration of the global Tokio runtime, see zerobus_runtime_init
+/// Zero / NULL fields keep Tokio's defaults
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CRuntimeConfig {
+    /// Number of worker threads (0 = one per CPU core); ignored for a current-thread runtime
+    pub worker_threads: usize,
+    /// Runtime threads are named "<prefix>-<n>" (NULL = Tokio's default names)
+    pub thread_name_prefix: *const c_char,
+    /// Stack size of runtime threads in bytes (0 = Tokio default)
+    pub thread_stack_size: usize,
+    /// Upper bound of the blocking thread pool (0 = Tokio default)
+    pub max_blocking_threads: usize,
+    /// Run all tasks on a single background thread instead of a worker pool
+    pub current_thread: bool,
 }
 
-/// A single header key-value pair for C FFI
-#[repr(C)]
-pub struct CHeader {
-    pub key: *mut c_char,
-    pub value: *mut c_char,
+impl Default for CRuntimeConfig {
+    fn default() -> Self {
+        CRuntimeConfig {
+            worker_threads: 0,
+            thread_name_prefix: ptr::null(),
+            thread_stack_size: 0,
+            max_blocking_threads: 0,
+            current_thread: false,
+        }
+    }
 }
 
-/// A collection of headers returned from Go callback
-#[repr(C)]
-pub struct CHeaders {
-    pub headers: *mut CHeader,
+/// Build a runtime from its C configuration
+/// Fails with InvalidArgument for an unusable config and Internal if the runtime cannot start
+pub(crate) fn build_runtime(config: &CRuntimeConfig) -> Result<Runtime, (CErrorCode, String)> {
+    let mut builder = if config.current_thread {
+        Builder::new_current_thread()
+    } else {
+        Builder::new_multi_thread()
+    };
+    builder.enable_all();
+
+    if config.worker_threads > 0 && !config.current_thread {
+        builder.worker_threads(config.worker_threads);
+    }
+    if !config.thread_name_prefix.is_null() {
+        let prefix = unsafe { c_str_to_string(config.thread_name_prefix) }
+            .map_err(|msg| (CErrorCode::InvalidArgument, msg.to_string()))?;
+        let thread_index = AtomicUsize::new(0);
+        builder.thread_name_fn(move || {
+            format!("{}-{}", prefix, thread_index.fetch_add(1, Ordering::SeqCst))
+        });
+    }
+    if config.thread_stack_size > 0 {
+        builder.thread_stack_size(config.thread_stack_size);
+    }
+    if config.max_blocking_threads > 0 {
+        builder.max_blocking_threads(config.max_blocking_threads);
+    }
+
+    builder
+        .build()
+        .map_err(|e| (CErrorCode::Internal, e.to_string()))
+}
+
+/// Start the background thread that parks inside a current-thread runtime for the process lifetime
+/// Spawned tasks (stream supervisors, ack delivery) only make progress while a thread drives it.
+/// The thread drives the runtime sent to it once that runtime has been installed, and exits if
+/// None is sent instead.
+fn spawn_runtime_driver(
+    config: &CRuntimeConfig,
+) -> Result<mpsc::Sender<Option<&'static Runtime>>, String> {
+    let mut thread = std::thread::Builder::new();
+    if !config.thread_name_prefix.is_null() {
+        let prefix = unsafe { c_str_to_string(config.thread_name_prefix)? };
+        thread = thread.name(format!("{}-driver", prefix));
+    }
+    let (sender, receiver) = mpsc::channel::<Option<&'static Runtime>>();
+    thread
+        .spawn(move || {
+            if let Ok(Some(rt)) = receiver.recv() {
+                rt.block_on(std::future::pending::<()>());
+            }
+        })
+        .map_err(|e| e.to_string())?;
+    Ok(sender)
+}
+
+/// Configure the global Tokio runtime used by every SDK and stream
+/// Must be called before the first SDK is created; fails with an invalid state error once the
+/// runtime has started. A NULL config starts the runtime with Tokio's defaults.
+#[no_mangle]
+pub extern "C" fn zerobus_runtime_init(
+    config: *const CRuntimeConfig,
+    result: *mut CResult,
+) -> bool {
+    let already_started = |result| {
+        write_coded_error_result(
+            result,
+            "Runtime has already been started",
+            CErrorCode::InvalidState,
+            false,
+        );
+        false
+    };
+
+    if RUNTIME.get().is_some() {
+        return already_started(result);
+    }
+
+    let config = unsafe { config.as_ref() }.copied().unwrap_or_default();
+    let rt = match build_runtime(&config) {
+        Ok(rt) => rt,
+        Err((error_code, msg)) => {
+            write_coded_error_result(result, &msg, error_code, false);
+            return false;
+        }
+    };
+
+    // A current-thread runtime is only installed once a thread is there to drive it
+    let driver = if config.current_thread {
+        match spawn_runtime_driver(&config) {
+            Ok(driver) => Some(driver),
+            Err(msg) => {
+                write_coded_error_result(result, &msg, CErrorCode::Internal, false);
+                return false;
+            }
+        }
+    } else {
+        None
+    };
+
+    // Another thread may have started the runtime while this one was being built
+    let installed = RUNTIME.set(rt).is_ok();
+    if let Some(driver) = driver {
+        let _ = driver.send(installed.then(runtime));
+    }
+    if !installed {
+        return already_started(result);
+    }
+
+    write_success_result(result);
+    true
+}
+
+/// Wrap an argument validation failure into a ZerobusError
+pub(crate) fn invalid_argument(err: impl ToString) -> ZerobusError {
+    ZerobusError::InvalidArgument(err.to_string())
+}
+
+// Helper to convert C string to Rust String
+unsafe fn c_str_to_string(c_str: *const c_char) -> Result<String, &'static str> {
+    if c_str.is_null() {
+        return Err("Null pointer passed");
+    }
+    CStr::from_ptr(c_str)
+        .to_str()
+        .map(|s| s.to_string())
+        .map_err(|_| "Invalid UTF-8 string")
+}
+
+/// Split a contiguous batch buffer into per-record slices using an offsets array
+/// `offsets` must hold `num_records + 1` non-decreasing entries, the last one not exceeding `data_len`
+pub(crate) unsafe fn split_batch_buffer<'a>(
+    data: *const u8,
+    data_len: usize,
+    offsets: *const usize,
+    num_records: usize,
+) -> Result<Vec<&'a [u8]>, &'static str> {
+    if num_records == 0 {
+        return Err("Batch must contain at least one record");
+    }
+    if offsets.is_null() {
+        return Err("Invalid offsets pointer");
+    }
+    if data.is_null() && data_len > 0 {
+        return Err("Invalid data pointer");
+    }
+
+    let offsets = std::slice::from_raw_parts(offsets, num_records + 1);
+    let data = if data_len > 0 {
+        std::slice::from_raw_parts(data, data_len)
+    } else {
+        &[]
+    };
+
+    let mut records = Vec::with_capacity(num_records);
+    for window in offsets.windows(2) {
+        let (start, end) = (window[0], window[1]);
+        if start > end || end > data_len {
+            return Err("Batch offsets are out of bounds or not increasing");
+        }
+        records.push(&data[start..end]);
+    }
+    Ok(records)
+}
+
+/// A single header key-value pair for C FFI
+#[repr(C)]
+pub struct CHeader {
+    pub key: *mut c_char,
+    pub value: *mut c_char,
+}
+
+/// A collection of headers returned from Go callback
+#[repr(C)]
+pub struct CHeaders {
+    pub headers: *mut CHeader,
     pub count: usize,
     pub error_message: *mut c_char,
 }
@@ -261,6 +1505,185 @@ impl HeadersProvider for CallbackHeadersProvider {
     }
 }
 
+/// Headers provider the SDK uses for streams created with OAuth credentials
+/// Same as the SDK's own, which is not exported, so the connections can be counted
+struct OAuthHeadersProvider {
+    client_id: String,
+    client_secret: String,
+    table_name: String,
+    workspace_id: String,
+    unity_catalog_url: String,
+}
+
+impl OAuthHeadersProvider {
+    fn new(
+        sdk: &ZerobusSdk,
+        table_name: String,
+        client_id: String,
+        client_secret: String,
+    ) -> ZerobusResult<Self> {
+        // Extracted from the endpoint like ZerobusSdk::new does
+        let workspace_id = sdk
+            .zerobus_endpoint
+            .strip_prefix("https://")
+            .or_else(|| sdk.zerobus_endpoint.strip_prefix("http://"))
+            .and_then(|host| host.split('.').next())
+            .ok_or_else(|| {
+                ZerobusError::ChannelCreationError(
+                    "Failed to extract workspace_id from zerobus_endpoint".to_string(),
+                )
+            })?;
+        Ok(Self {
+            client_id,
+            client_secret,
+            table_name,
+            workspace_id: workspace_id.to_string(),
+            unity_catalog_url: sdk.unity_catalog_url.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl HeadersProvider for OAuthHeadersProvider {
+    async fn get_headers(&self) -> ZerobusResult<HashMap<&'static str, String>> {
+        let token = DefaultTokenFactory::get_token(
+            &self.unity_catalog_url,
+            &self.table_name,
+            &self.client_id,
+            &self.client_secret,
+            &self.workspace_id,
+        )
+        .await?;
+        let mut headers = HashMap::new();
+        headers.insert("authorization", format!("Bearer {}", token));
+        headers.insert("x-databricks-zerobus-table-name", self.table_name.clone());
+        Ok(headers)
+    }
+}
+
+/// Counts the connections the SDK opens for a stream in its metrics
+/// The SDK requests headers once per connection attempt, when the stream is created and on
+/// every recovery attempt
+struct CountingHeadersProvider {
+    inner: Arc<dyn HeadersProvider>,
+    metrics: Arc<StreamMetrics>,
+}
+
+#[async_trait]
+impl HeadersProvider for CountingHeadersProvider {
+    async fn get_headers(&self) -> ZerobusResult<HashMap<&'static str, String>> {
+        self.metrics.record_connection_attempt();
+        self.inner.get_headers().await
+    }
+}
+
+/// A stream the SDK created, with the headers provider it was given and its metrics
+type OpenedStream = (ZerobusStream, Arc<dyn HeadersProvider>, Arc<StreamMetrics>);
+
+/// Create a stream whose connection attempts are counted in fresh metrics
+async fn open_stream(
+    sdk: &ZerobusSdk,
+    table_properties: TableProperties,
+    headers_provider: Arc<dyn HeadersProvider>,
+    options: Option<StreamConfigurationOptions>,
+) -> ZerobusResult<OpenedStream> {
+    let metrics = Arc::new(StreamMetrics::default());
+    let counting = Arc::new(CountingHeadersProvider {
+        inner: Arc::clone(&headers_provider),
+        metrics: Arc::clone(&metrics),
+    });
+    let stream = sdk
+        .create_stream_with_headers_provider(table_properties, counting, options)
+        .await?;
+    metrics.record_stream_created();
+    Ok((stream, headers_provider, metrics))
+}
+
+/// Function pointer type for the acknowledgment callback (nullable)
+/// Invoked from a runtime worker thread as soon as a record is acknowledged or fails
+/// offset is -1 on failure; result (and its error_message) is only valid during the call
+/// Freeing the stream waits for callbacks in progress and drops later ones, so the callback
+/// must not free the stream itself
+pub type AckCallback = Option<
+    extern "C" fn(
+        user_data: *mut std::ffi::c_void,
+        ack_id: u64,
+        offset: i64,
+        result: *const CResult,
+    ),
+>;
+
+/// An ack callback together with the user data it was registered with
+#[derive(Clone, Copy)]
+pub(crate) struct AckCallbackHandle {
+    callback: extern "C" fn(*mut std::ffi::c_void, u64, i64, *const CResult),
+    user_data: *mut std::ffi::c_void,
+}
+
+impl AckCallbackHandle {
+    pub(crate) fn new(
+        callback: extern "C" fn(*mut std::ffi::c_void, u64, i64, *const CResult),
+        user_data: *mut std::ffi::c_void,
+    ) -> Self {
+        Self {
+            callback,
+            user_data,
+        }
+    }
+}
+
+// Safety: The host is responsible for making the callback and its user data thread-safe
+unsafe impl Send for AckCallbackHandle {}
+unsafe impl Sync for AckCallbackHandle {}
+
+/// State shared between a stream and its in-flight callback deliveries
+#[derive(Default)]
+pub(crate) struct CallbackTracker {
+    pending: AtomicUsize,
+    /// Set once the stream is freed; callbacks run under the read lock, so setting it waits
+    /// for the callbacks in progress
+    closed: RwLock<bool>,
+}
+
+impl CallbackTracker {
+    /// Run a host callback unless the stream has been freed, returning whether it ran
+    pub(crate) fn run_if_open(&self, callback: impl FnOnce()) -> bool {
+        let closed = self.closed.read().unwrap_or_else(PoisonError::into_inner);
+        if *closed {
+            return false;
+        }
+        callback();
+        true
+    }
+
+    /// Stop delivering callbacks, waiting for the ones in progress to return
+    pub(crate) fn close(&self) {
+        *self.closed.write().unwrap_or_else(PoisonError::into_inner) = true;
+    }
+}
+
+/// Await an acknowledgment and hand its outcome to the ack callback
+/// Deliveries resolving after the stream was freed are dropped
+pub(crate) async fn deliver_ack<F>(
+    callback: AckCallbackHandle,
+    tracker: Arc<CallbackTracker>,
+    ack_id: u64,
+    ack_future: F,
+) where
+    F: Future<Output = ZerobusResult<i64>>,
+{
+    let (offset, result) = match ack_future.await {
+        Ok(offset) => (offset, CResult::success()),
+        Err(err) => (-1, CResult::error(err)),
+    };
+
+    tracker.run_if_open(|| (callback.callback)(callback.user_data, ack_id, offset, &result));
+    tracker.pending.fetch_sub(1, Ordering::SeqCst);
+
+    // The callback only borrows the result, so the message is released here
+    zerobus_free_error_message(result.error_message);
+}
+
 // ============================================================================
 // SDK Functions
 // ============================================================================
@@ -287,25 +1710,30 @@ pub(crate) fn validate_sdk_ptr_mut<'a>(
 /// Safe wrapper to validate stream pointer
 pub(crate) fn validate_stream_ptr<'a>(
     stream: *mut CZerobusStream,
-) -> Result<&'a ZerobusStream, &'static str> {
+) -> Result<&'a StreamWrapper, &'static str> {
     if stream.is_null() {
         return Err("Stream pointer is null");
     }
-    unsafe { Ok(&*(stream as *const ZerobusStream)) }
+    unsafe { Ok(&*(stream as *const StreamWrapper)) }
 }
 
 /// Safe wrapper to validate mutable stream pointer
 pub(crate) fn validate_stream_ptr_mut<'a>(
     stream: *mut CZerobusStream,
-) -> Result<&'a mut ZerobusStream, &'static str> {
+) -> Result<&'a mut StreamWrapper, &'static str> {
     if stream.is_null() {
         return Err("Stream pointer is null");
     }
-    unsafe { Ok(&mut *(stream as *mut ZerobusStream)) }
+    unsafe { Ok(&mut *(stream as *mut StreamWrapper)) }
 }
 
-/// Helper to write error result
-pub(crate) fn write_error_result(result: *mut CResult, message: &str, is_retryable: bool) {
+/// Helper to write error result with an explicit error code
+pub(crate) fn write_coded_error_result(
+    result: *mut CResult,
+    message: &str,
+    error_code: CErrorCode,
+    is_retryable: bool,
+) {
     if !result.is_null() {
         unsafe {
             *result = CResult {
@@ -314,11 +1742,21 @@ pub(crate) fn write_error_result(result: *mut CResult, message: &str, is_retryab
                     .unwrap_or_else(|_| CString::new("Error message contains null byte").unwrap())
                     .into_raw(),
                 is_retryable,
+                error_code,
             };
         }
     }
 }
 
+/// Helper to write the result of a failed SDK call
+pub(crate) fn write_zerobus_error(result: *mut CResult, err: ZerobusError) {
+    if !result.is_null() {
+        unsafe {
+            *result = CResult::error(err);
+        }
+    }
+}
+
 /// Helper to write success result
 pub(crate) fn write_success_result(result: *mut CResult) {
     if !result.is_null() {
@@ -336,36 +1774,22 @@ pub extern "C" fn zerobus_sdk_new(
     unity_catalog_url: *const c_char,
     result: *mut CResult,
 ) -> *mut CZerobusSdk {
-    let res = (|| -> Result<*mut CZerobusSdk, String> {
-        let endpoint = unsafe { c_str_to_string(zerobus_endpoint).map_err(|e| e.to_string())? };
-        let catalog_url = unsafe { c_str_to_string(unity_catalog_url).map_err(|e| e.to_string())? };
+    let res = (|| -> ZerobusResult<*mut CZerobusSdk> {
+        let endpoint = unsafe { c_str_to_string(zerobus_endpoint).map_err(invalid_argument)? };
+        let catalog_url = unsafe { c_str_to_string(unity_catalog_url).map_err(invalid_argument)? };
 
-        let sdk = ZerobusSdk::new(endpoint, catalog_url).map_err(|e| e.to_string())?;
+        let sdk = ZerobusSdk::new(endpoint, catalog_url)?;
         let boxed = Box::new(sdk);
         Ok(Box::into_raw(boxed) as *mut CZerobusSdk)
     })();
 
     match res {
         Ok(sdk_ptr) => {
-            if !result.is_null() {
-                unsafe {
-                    *result = CResult::success();
-                }
-            }
+            write_success_result(result);
             sdk_ptr
         }
         Err(err) => {
-            if !result.is_null() {
-                let err_msg =
-                    CString::new(err).unwrap_or_else(|_| CString::new("Unknown error").unwrap());
-                unsafe {
-                    *result = CResult {
-                        success: false,
-                        error_message: err_msg.into_raw(),
-                        is_retryable: false,
-                    };
-                }
-            }
+            write_zerobus_error(result, err);
             ptr::null_mut()
         }
     }
@@ -390,6 +1814,127 @@ pub extern "C" fn zerobus_sdk_set_use_tls(sdk: *mut CZerobusSdk, use_tls: bool)
     }
 }
 
+/// Where the schema of a new stream comes from
+#[derive(Clone, Copy)]
+pub(crate) enum DescriptorSource {
+    /// protobuf-encoded DescriptorProto (NULL / empty for JSON streams)
+    Proto { bytes: *const u8, len: usize },
+    /// protobuf-encoded FileDescriptorSet and the fully qualified name of the record message
+    DescriptorSet {
+        bytes: *const u8,
+        len: usize,
+        message_name: *const c_char,
+    },
+}
+
+impl DescriptorSource {
+    /// Decode the descriptor handed to the SDK in TableProperties
+    pub(crate) fn decode(self) -> ZerobusResult<Option<prost_types::DescriptorProto>> {
+        match self {
+            DescriptorSource::Proto { bytes, len } => {
+                if bytes.is_null() || len == 0 {
+                    return Ok(None);
+                }
+                let bytes = unsafe { std::slice::from_raw_parts(bytes, len) };
+                Ok(Some(
+                    prost_types::DescriptorProto::decode(bytes).map_err(invalid_argument)?,
+                ))
+            }
+            DescriptorSource::DescriptorSet {
+                bytes,
+                len,
+                message_name,
+            } => {
+                if bytes.is_null() || len == 0 {
+                    return Err(invalid_argument("Descriptor set must not be empty"));
+                }
+                let message_name =
+                    unsafe { c_str_to_string(message_name).map_err(invalid_argument)? };
+                let bytes = unsafe { std::slice::from_raw_parts(bytes, len) };
+                let descriptor_set =
+                    prost_types::FileDescriptorSet::decode(bytes).map_err(invalid_argument)?;
+                descriptor::flatten_descriptor_set(&descriptor_set, &message_name)
+                    .map(Some)
+                    .map_err(invalid_argument)
+            }
+        }
+    }
+}
+
+/// A byte buffer allocated by the FFI layer, released with zerobus_free_bytes
+#[repr(C)]
+pub struct CBytes {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl CBytes {
+    fn empty() -> Self {
+        CBytes {
+            data: ptr::null_mut(),
+            len: 0,
+        }
+    }
+
+    fn from_vec(bytes: Vec<u8>) -> Self {
+        if bytes.is_empty() {
+            return Self::empty();
+        }
+        let len = bytes.len();
+        CBytes {
+            data: Box::into_raw(bytes.into_boxed_slice()) as *mut u8,
+            len,
+        }
+    }
+}
+
+/// Generate the protobuf-encoded DescriptorProto of a table's records from its column schema,
+/// ready to pass to zerobus_sdk_create_stream
+/// table_schema_json: JSON array of {"name", "type", "nullable"} column objects (or a Unity
+/// Catalog table info object with a "columns" array) using Delta types such as "BIGINT",
+/// "ARRAY<STRING>", "STRUCT<city: STRING, zip: INT NOT NULL>" or "MAP<STRING, DOUBLE>"
+/// message_name: name of the generated proto2 message
+/// Nullable columns become optional fields and NOT NULL columns required fields, numbered from 1
+/// in column order. Types map to BOOLEAN -> bool, TINYINT/SMALLINT/INT -> int32, BIGINT -> int64,
+/// FLOAT -> float, DOUBLE -> double, DECIMAL/STRING/VARIANT -> string, BINARY -> bytes,
+/// DATE -> int32 (days since epoch), TIMESTAMP/TIMESTAMP_NTZ -> int64 (microseconds since epoch),
+/// STRUCT -> nested message, ARRAY<T> -> repeated T, MAP<K, V> -> map<K, V>
+/// The returned bytes must be released with zerobus_free_bytes
+#[no_mangle]
+pub extern "C" fn zerobus_descriptor_proto_from_table_schema(
+    table_schema_json: *const c_char,
+    message_name: *const c_char,
+    result: *mut CResult,
+) -> CBytes {
+    let res = (|| -> Result<Vec<u8>, String> {
+        let schema_json = unsafe { c_str_to_string(table_schema_json)? };
+        let message_name = unsafe { c_str_to_string(message_name)? };
+        let descriptor = TableSchema::parse(&schema_json)?.to_descriptor(&message_name)?;
+        Ok(descriptor.encode_to_vec())
+    })();
+
+    match res {
+        Ok(bytes) => {
+            write_success_result(result);
+            CBytes::from_vec(bytes)
+        }
+        Err(msg) => {
+            write_coded_error_result(result, &msg, CErrorCode::InvalidArgument, false);
+            CBytes::empty()
+        }
+    }
+}
+
+/// Free bytes returned by the FFI layer, e.g. by zerobus_descriptor_proto_from_table_schema
+#[no_mangle]
+pub extern "C" fn zerobus_free_bytes(bytes: CBytes) {
+    if !bytes.data.is_null() {
+        unsafe {
+            let _ = Box::from_raw(ptr::slice_from_raw_parts_mut(bytes.data, bytes.len));
+        }
+    }
+}
+
 /// Create a stream with OAuth authentication
 /// descriptor_proto_bytes: protobuf-encoded DescriptorProto (can be NULL for JSON streams)
 #[no_mangle]
@@ -402,313 +1947,1218 @@ pub extern "C" fn zerobus_sdk_create_stream(
     client_secret: *const c_char,
     options: *const CStreamConfigurationOptions,
     result: *mut CResult,
+) -> *mut CZerobusStream {
+    create_stream_oauth(
+        sdk,
+        table_name,
+        DescriptorSource::Proto {
+            bytes: descriptor_proto_bytes,
+            len: descriptor_proto_len,
+        },
+        client_id,
+        client_secret,
+        options,
+        result,
+    )
+}
+
+/// Create a stream with OAuth authentication from a FileDescriptorSet
+/// descriptor_set_bytes: protobuf-encoded FileDescriptorSet holding the record message and all of
+/// its dependencies (protoc --include_imports); message_name: fully qualified record message name
+/// Referenced types declared outside the message are copied into it as nested types
+#[no_mangle]
+pub extern "C" fn zerobus_sdk_create_stream_from_descriptor_set(
+    sdk: *mut CZerobusSdk,
+    table_name: *const c_char,
+    descriptor_set_bytes: *const u8,
+    descriptor_set_len: usize,
+    message_name: *const c_char,
+    client_id: *const c_char,
+    client_secret: *const c_char,
+    options: *const CStreamConfigurationOptions,
+    result: *mut CResult,
+) -> *mut CZerobusStream {
+    create_stream_oauth(
+        sdk,
+        table_name,
+        DescriptorSource::DescriptorSet {
+            bytes: descriptor_set_bytes,
+            len: descriptor_set_len,
+            message_name,
+        },
+        client_id,
+        client_secret,
+        options,
+        result,
+    )
+}
+
+fn create_stream_oauth(
+    sdk: *mut CZerobusSdk,
+    table_name: *const c_char,
+    descriptor: DescriptorSource,
+    client_id: *const c_char,
+    client_secret: *const c_char,
+    options: *const CStreamConfigurationOptions,
+    result: *mut CResult,
 ) -> *mut CZerobusStream {
     let sdk_ref = match validate_sdk_ptr(sdk) {
         Ok(s) => s,
         Err(msg) => {
-            write_error_result(result, msg, false);
+            write_coded_error_result(result, msg, CErrorCode::InvalidArgument, false);
             return ptr::null_mut();
         }
     };
 
-    let res = RUNTIME.block_on(async {
-        let table_name_str = unsafe { c_str_to_string(table_name).map_err(|e| e.to_string())? };
-        let client_id_str = unsafe { c_str_to_string(client_id).map_err(|e| e.to_string())? };
+    let res = runtime().block_on(async {
+        let table_name_str = unsafe { c_str_to_string(table_name).map_err(invalid_argument)? };
+        let client_id_str = unsafe { c_str_to_string(client_id).map_err(invalid_argument)? };
         let client_secret_str =
-            unsafe { c_str_to_string(client_secret).map_err(|e| e.to_string())? };
+            unsafe { c_str_to_string(client_secret).map_err(invalid_argument)? };
 
-        // Decode descriptor if provided
-        let descriptor_proto = if !descriptor_proto_bytes.is_null() && descriptor_proto_len > 0 {
-            let bytes =
-                unsafe { std::slice::from_raw_parts(descriptor_proto_bytes, descriptor_proto_len) };
-            Some(prost_types::DescriptorProto::decode(bytes).map_err(|e| e.to_string())?)
-        } else {
-            None
+        let table_props = TableProperties {
+            table_name: table_name_str,
+            descriptor_proto: descriptor.decode()?,
         };
 
+        let c_options = unsafe { options.as_ref() };
+        if let Some(opts) = c_options {
+            validate_stream_options(opts).map_err(invalid_argument)?;
+        }
+        let schemas = record_schemas(c_options, table_props.descriptor_proto.as_ref())?;
+        let (spill, leftovers) = open_spill(c_options)?;
+        let dead_letter = open_dead_letter(c_options)?;
+        let stream_options = c_options.map(|opts| (*opts).into());
+
+        let headers_provider = Arc::new(OAuthHeadersProvider::new(
+            sdk_ref,
+            table_props.table_name.clone(),
+            client_id_str,
+            client_secret_str,
+        )?);
+        let opened = open_stream(sdk_ref, table_props, headers_provider, stream_options).await?;
+
+        let wrapper = StreamWrapper::new(opened, c_options, schemas, spill, dead_letter);
+        Ok::<_, ZerobusError>((wrapper, leftovers))
+    });
+    finish_create_stream(res, result)
+}
+
+/// Create a stream with a custom headers provider callback
+/// This allows you to provide custom authentication headers via a Go callback function
+#[no_mangle]
+pub extern "C" fn zerobus_sdk_create_stream_with_headers_provider(
+    sdk: *mut CZerobusSdk,
+    table_name: *const c_char,
+    descriptor_proto_bytes: *const u8,
+    descriptor_proto_len: usize,
+    headers_callback: HeadersProviderCallback,
+    user_data: *mut std::ffi::c_void,
+    options: *const CStreamConfigurationOptions,
+    result: *mut CResult,
+) -> *mut CZerobusStream {
+    create_stream_with_headers(
+        sdk,
+        table_name,
+        DescriptorSource::Proto {
+            bytes: descriptor_proto_bytes,
+            len: descriptor_proto_len,
+        },
+        headers_callback,
+        user_data,
+        options,
+        result,
+    )
+}
+
+/// Create a stream with a custom headers provider callback from a FileDescriptorSet
+/// See zerobus_sdk_create_stream_from_descriptor_set for how the descriptor set is resolved
+#[no_mangle]
+pub extern "C" fn zerobus_sdk_create_stream_with_headers_provider_from_descriptor_set(
+    sdk: *mut CZerobusSdk,
+    table_name: *const c_char,
+    descriptor_set_bytes: *const u8,
+    descriptor_set_len: usize,
+    message_name: *const c_char,
+    headers_callback: HeadersProviderCallback,
+    user_data: *mut std::ffi::c_void,
+    options: *const CStreamConfigurationOptions,
+    result: *mut CResult,
+) -> *mut CZerobusStream {
+    create_stream_with_headers(
+        sdk,
+        table_name,
+        DescriptorSource::DescriptorSet {
+            bytes: descriptor_set_bytes,
+            len: descriptor_set_len,
+            message_name,
+        },
+        headers_callback,
+        user_data,
+        options,
+        result,
+    )
+}
+
+fn create_stream_with_headers(
+    sdk: *mut CZerobusSdk,
+    table_name: *const c_char,
+    descriptor: DescriptorSource,
+    headers_callback: HeadersProviderCallback,
+    user_data: *mut std::ffi::c_void,
+    options: *const CStreamConfigurationOptions,
+    result: *mut CResult,
+) -> *mut CZerobusStream {
+    let sdk_ref = match validate_sdk_ptr(sdk) {
+        Ok(s) => s,
+        Err(msg) => {
+            write_coded_error_result(result, msg, CErrorCode::InvalidArgument, false);
+            return ptr::null_mut();
+        }
+    };
+
+    let res = runtime().block_on(async {
+        let table_name_str = unsafe { c_str_to_string(table_name).map_err(invalid_argument)? };
+
         let table_props = TableProperties {
             table_name: table_name_str,
-            descriptor_proto,
+            descriptor_proto: descriptor.decode()?,
         };
 
-        let stream_options = if !options.is_null() {
-            Some(unsafe { (*options).into() })
-        } else {
-            None
-        };
+        let c_options = unsafe { options.as_ref() };
+        if let Some(opts) = c_options {
+            validate_stream_options(opts).map_err(invalid_argument)?;
+        }
+        let schemas = record_schemas(c_options, table_props.descriptor_proto.as_ref())?;
+        let (spill, leftovers) = open_spill(c_options)?;
+        let dead_letter = open_dead_letter(c_options)?;
+        let stream_options = c_options.map(|opts| (*opts).into());
 
-        let stream = sdk_ref
-            .create_stream(
-                table_props,
-                client_id_str,
-                client_secret_str,
-                stream_options,
-            )
-            .await
-            .map_err(|e| e.to_string())?;
+        // Create the headers provider from the callback with thread-safety validation
+        let headers_provider = Arc::new(CallbackHeadersProvider::new(headers_callback, user_data));
+
+        let opened = open_stream(sdk_ref, table_props, headers_provider, stream_options).await?;
 
-        let boxed = Box::new(stream);
-        Ok::<*mut CZerobusStream, String>(Box::into_raw(boxed) as *mut CZerobusStream)
+        let wrapper = StreamWrapper::new(opened, c_options, schemas, spill, dead_letter);
+        Ok::<_, ZerobusError>((wrapper, leftovers))
     });
+    finish_create_stream(res, result)
+}
 
-    match res {
-        Ok(stream_ptr) => {
-            write_success_result(result);
-            stream_ptr
-        }
-        Err(err) => {
-            write_error_result(result, &err, false);
-            ptr::null_mut()
-        }
-    }
+/// Maps the ack ID of a record on a failed stream to its ack ID on the recreated stream
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CAckIdMapping {
+    pub old_ack_id: u64,
+    pub new_ack_id: u64,
+}
+
+/// A list of ack ID mappings, in ingestion order
+#[repr(C)]
+pub struct CAckIdMappings {
+    pub mappings: *mut CAckIdMapping,
+    pub count: usize,
+}
+
+impl CAckIdMappings {
+    fn empty() -> Self {
+        CAckIdMappings {
+            mappings: ptr::null_mut(),
+            count: 0,
+        }
+    }
+
+    fn from_vec(mappings: Vec<CAckIdMapping>) -> Self {
+        if mappings.is_empty() {
+            return Self::empty();
+        }
+        let count = mappings.len();
+        CAckIdMappings {
+            mappings: Box::into_raw(mappings.into_boxed_slice()) as *mut CAckIdMapping,
+            count,
+        }
+    }
+}
+
+/// Open a new stream with the table, authentication and options of a failed stream
+/// and re-ingest everything the failed stream left unacknowledged, batch by batch
+/// old_stream must be failed or closed and stays owned by the caller (free it as usual);
+/// the new stream keeps using the old stream's headers provider, spill directory, dead-letter
+/// sink and callback user data
+/// Batches already reported to the dead-letter sink are not re-ingested: the failed requests of
+/// the old stream settle asynchronously, and recreation waits at most settle_timeout_ms for them
+/// (without blocking the old stream's ingest calls), failing with WaitTimedOut (retryable,
+/// nothing is recreated) if they do not settle in time
+/// If ack_id_mapping is not NULL it receives the old -> new ack ID of every re-ingested batch,
+/// to be released with zerobus_free_ack_id_mappings
+/// Returns NULL on error. Check the result parameter for error details.
+#[no_mangle]
+pub extern "C" fn zerobus_sdk_recreate_stream(
+    sdk: *mut CZerobusSdk,
+    old_stream: *mut CZerobusStream,
+    settle_timeout_ms: u64,
+    ack_id_mapping: *mut CAckIdMappings,
+    result: *mut CResult,
+) -> *mut CZerobusStream {
+    if !ack_id_mapping.is_null() {
+        unsafe {
+            *ack_id_mapping = CAckIdMappings::empty();
+        }
+    }
+
+    let (sdk_ref, old_ref) = match (validate_sdk_ptr(sdk), validate_stream_ptr(old_stream)) {
+        (Ok(sdk_ref), Ok(old_ref)) => (sdk_ref, old_ref),
+        (Err(msg), _) | (_, Err(msg)) => {
+            write_coded_error_result(result, msg, CErrorCode::InvalidArgument, false);
+            return ptr::null_mut();
+        }
+    };
+
+    // Holding the ingest lock keeps the old stream's last ack ID in step with its unacked batches;
+    // a failed stream takes no further requests, so both stay valid once the lock is released
+    let unacked = {
+        let _guard = old_ref.ingest_lock.lock().unwrap();
+        runtime()
+            .block_on(async { old_ref.stream.get_unacked_batches().await })
+            .map(|batches| (old_ref.acks.last_id(), batches))
+    };
+    let (last_id, batches) = match unacked {
+        Ok(unacked) => unacked,
+        Err(err) => {
+            write_zerobus_error(result, err);
+            return ptr::null_mut();
+        }
+    };
+    // Which of the failed requests were dead-lettered is only known once they have settled
+    if !old_ref
+        .inflight
+        .wait_settled(Duration::from_millis(settle_timeout_ms))
+    {
+        write_coded_error_result(
+            result,
+            "Failed requests of the old stream did not settle within settle_timeout_ms",
+            CErrorCode::WaitTimedOut,
+            true,
+        );
+        return ptr::null_mut();
+    }
+
+    // Re-ingestion fails like an ingest call, e.g. with SpillFull
+    let res = (|| -> Result<(StreamWrapper, Vec<CAckIdMapping>), IngestError> {
+        // Records spilled by concurrent ingest calls are taken over below
+        let _guard = old_ref.ingest_lock.lock().unwrap();
+        let first_old_id = (last_id + 1).saturating_sub(batches.len() as u64);
+        let dead_lettered = std::mem::take(&mut *old_ref.dead_lettered.lock().unwrap());
+
+        let old = &old_ref.stream;
+        let opened = runtime().block_on(open_stream(
+            sdk_ref,
+            old.table_properties.clone(),
+            Arc::clone(&old_ref.headers_provider),
+            Some(old.options.clone()),
+        ))?;
+        let wrapper = StreamWrapper::new(
+            opened,
+            old_ref.c_options.as_ref(),
+            old_ref.schemas.clone(),
+            old_ref.spill.clone(),
+            old_ref.dead_letter.clone(),
+        );
+
+        // Batches move over with the records they hold in the spill buffer
+        let mut mappings = Vec::with_capacity(batches.len());
+        let mut old_tickets = old_ref.spill_tickets.lock().unwrap();
+        for (old_ack_id, batch) in (first_old_id..).zip(batches) {
+            // Already reported as permanently failed, and released from the spill buffer
+            if dead_lettered.contains(&old_ack_id) {
+                continue;
+            }
+            let persisted = old_tickets.remove(&old_ack_id);
+            let detached = persisted.as_ref().is_some_and(|request| request.detached);
+            let ticket = persisted.map(|request| request.ticket);
+            let new_ack_id = wrapper.resubmit(batch.into_iter().collect(), ticket, detached)?;
+            if !detached {
+                mappings.push(CAckIdMapping {
+                    old_ack_id,
+                    new_ack_id,
+                });
+            }
+        }
+        drop(old_tickets);
+
+        // Records the old stream refused after failing were persisted but never got an ack ID
+        let spilled = std::mem::take(&mut *old_ref.spilled.lock().unwrap());
+        wrapper.replay_spilled(spilled)?;
+        Ok((wrapper, mappings))
+    })();
+
+    match res {
+        Ok((wrapper, mappings)) => {
+            if !ack_id_mapping.is_null() {
+                unsafe {
+                    *ack_id_mapping = CAckIdMappings::from_vec(mappings);
+                }
+            }
+            write_success_result(result);
+            into_stream_ptr(wrapper)
+        }
+        Err(err) => {
+            write_ingest_error(result, err);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Free ack ID mappings returned by zerobus_sdk_recreate_stream
+#[no_mangle]
+pub extern "C" fn zerobus_free_ack_id_mappings(mappings: CAckIdMappings) {
+    if !mappings.mappings.is_null() {
+        unsafe {
+            let _ = Box::from_raw(ptr::slice_from_raw_parts_mut(
+                mappings.mappings,
+                mappings.count,
+            ));
+        }
+    }
+}
+
+/// Free a stream instance
+/// Outstanding acknowledgments of the stream are dropped and can no longer be awaited
+/// Waits for ack and dead-letter callbacks in progress; none are invoked once this returns
+/// Concurrent or repeated calls for the same stream release it once; the others return at once
+#[no_mangle]
+pub extern "C" fn zerobus_stream_free(stream: *mut CZerobusStream) {
+    // Claimed under the lock, so a single caller drops the stream
+    let live = LIVE_STREAMS.lock().unwrap().remove(&(stream as usize));
+    if live {
+        unsafe {
+            let _ = Box::from_raw(stream as *mut StreamWrapper);
+        }
+    }
+}
+
+/// Ingest a record (protobuf encoded)
+/// Returns an acknowledgment ID that can be awaited later
+/// Returns 0 on error, and always in fire-and-forget mode (check result)
+#[no_mangle]
+pub extern "C" fn zerobus_stream_ingest_proto_record(
+    stream: *mut CZerobusStream,
+    data: *const u8,
+    data_len: usize,
+    result: *mut CResult,
+) -> u64 {
+    ingest_proto_slice(stream, data, data_len, None, result)
+}
+
+/// Ingest a record (protobuf encoded) without blocking when the stream is saturated
+/// If max_inflight_requests requests are awaiting acknowledgment, or another ingest call on the
+/// stream is in progress, returns 0 immediately with error code WouldBlock (retryable) and the
+/// record is not queued
+/// Behaves like zerobus_stream_ingest_proto_record otherwise
+#[no_mangle]
+pub extern "C" fn zerobus_stream_try_ingest_proto_record(
+    stream: *mut CZerobusStream,
+    data: *const u8,
+    data_len: usize,
+    result: *mut CResult,
+) -> u64 {
+    ingest_proto_slice(stream, data, data_len, Some(Duration::ZERO), result)
+}
+
+/// Ingest a record (protobuf encoded), waiting at most `timeout_ms` for a saturated stream
+/// Fails with error code WouldBlock if the record could not be queued within the timeout
+/// Behaves like zerobus_stream_try_ingest_proto_record otherwise
+#[no_mangle]
+pub extern "C" fn zerobus_stream_ingest_proto_record_timeout(
+    stream: *mut CZerobusStream,
+    data: *const u8,
+    data_len: usize,
+    timeout_ms: u64,
+    result: *mut CResult,
+) -> u64 {
+    let wait = Duration::from_millis(timeout_ms);
+    ingest_proto_slice(stream, data, data_len, Some(wait), result)
+}
+
+fn ingest_proto_slice(
+    stream: *mut CZerobusStream,
+    data: *const u8,
+    data_len: usize,
+    wait: Option<Duration>,
+    result: *mut CResult,
+) -> u64 {
+    if data.is_null() {
+        write_coded_error_result(
+            result,
+            "Invalid data pointer",
+            CErrorCode::InvalidArgument,
+            false,
+        );
+        return 0;
+    }
+
+    let stream_ref = match validate_stream_ptr(stream) {
+        Ok(s) => s,
+        Err(msg) => {
+            write_coded_error_result(result, msg, CErrorCode::InvalidArgument, false);
+            return 0;
+        }
+    };
+
+    let data_slice = unsafe { std::slice::from_raw_parts(data, data_len) };
+    ingest_proto_record(stream_ref, data_slice.to_vec(), wait, result)
+}
+
+/// Allocate a zeroed buffer of `capacity` bytes to encode a proto record into
+/// Ingesting the buffer with zerobus_stream_ingest_proto_record_buffer hands it over to the
+/// stream, so the record is sent without being copied; buffers that are not ingested must be
+/// released with zerobus_record_buffer_free
+/// A stream with a dead-letter sink but no spill_dir still copies each record, to be able to
+/// report it; with spill_dir, dead letters are read back from disk instead
+/// Returns NULL if capacity is 0
+#[no_mangle]
+pub extern "C" fn zerobus_record_buffer_alloc(capacity: usize) -> *mut u8 {
+    if capacity == 0 {
+        return ptr::null_mut();
+    }
+    Box::into_raw(vec![0u8; capacity].into_boxed_slice()) as *mut u8
+}
+
+/// Free a buffer from zerobus_record_buffer_alloc that was not ingested
+#[no_mangle]
+pub extern "C" fn zerobus_record_buffer_free(buffer: *mut u8, capacity: usize) {
+    if !buffer.is_null() {
+        unsafe {
+            let _ = Box::from_raw(ptr::slice_from_raw_parts_mut(buffer, capacity));
+        }
+    }
+}
+
+/// Ingest a proto record encoded into a buffer from zerobus_record_buffer_alloc, without copying it
+/// `len` is the size of the record at the start of the buffer, `capacity` the size it was
+/// allocated with. The buffer is owned by the stream afterwards, also when an error is returned,
+/// and is released once the record is acknowledged or dropped
+/// Returns an acknowledgment ID like zerobus_stream_ingest_proto_record
+#[no_mangle]
+pub extern "C" fn zerobus_stream_ingest_proto_record_buffer(
+    stream: *mut CZerobusStream,
+    buffer: *mut u8,
+    len: usize,
+    capacity: usize,
+    result: *mut CResult,
+) -> u64 {
+    ingest_record_buffer(stream, buffer, len, capacity, None, result)
+}
+
+/// Ingest a record buffer like zerobus_stream_ingest_proto_record_buffer without blocking when
+/// the stream is saturated, see zerobus_stream_try_ingest_proto_record
+/// On WouldBlock the buffer stays with the caller, who can ingest it again or free it
+#[no_mangle]
+pub extern "C" fn zerobus_stream_try_ingest_proto_record_buffer(
+    stream: *mut CZerobusStream,
+    buffer: *mut u8,
+    len: usize,
+    capacity: usize,
+    result: *mut CResult,
+) -> u64 {
+    ingest_record_buffer(stream, buffer, len, capacity, Some(Duration::ZERO), result)
+}
+
+/// Ingest a record buffer like zerobus_stream_ingest_proto_record_buffer, waiting at most
+/// `timeout_ms` for a saturated stream, see zerobus_stream_ingest_proto_record_timeout
+/// On WouldBlock the buffer stays with the caller, who can ingest it again or free it
+#[no_mangle]
+pub extern "C" fn zerobus_stream_ingest_proto_record_buffer_timeout(
+    stream: *mut CZerobusStream,
+    buffer: *mut u8,
+    len: usize,
+    capacity: usize,
+    timeout_ms: u64,
+    result: *mut CResult,
+) -> u64 {
+    let wait = Duration::from_millis(timeout_ms);
+    ingest_record_buffer(stream, buffer, len, capacity, Some(wait), result)
+}
+
+fn ingest_record_buffer(
+    stream: *mut CZerobusStream,
+    buffer: *mut u8,
+    len: usize,
+    capacity: usize,
+    wait: Option<Duration>,
+    result: *mut CResult,
+) -> u64 {
+    if buffer.is_null() || len > capacity {
+        write_coded_error_result(
+            result,
+            "Invalid record buffer",
+            CErrorCode::InvalidArgument,
+            false,
+        );
+        return 0;
+    }
+    let take_buffer = || {
+        let mut data =
+            unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(buffer, capacity)) }.into_vec();
+        data.truncate(len);
+        data
+    };
+
+    let stream_ref = match validate_stream_ptr(stream) {
+        Ok(s) => s,
+        Err(msg) => {
+            drop(take_buffer());
+            write_coded_error_result(result, msg, CErrorCode::InvalidArgument, false);
+            return 0;
+        }
+    };
+    let Some(wait) = wait else {
+        return ingest_proto_record(stream_ref, take_buffer(), None, result);
+    };
+
+    // The buffer is only taken over once the stream has room for the record
+    let record = unsafe { std::slice::from_raw_parts(buffer, len) };
+    let ack_id_res = match stream_ref.validate_proto(record) {
+        Ok(()) => stream_ref.ingest_when_ready(wait, || {
+            Submission::Record(EncodedRecord::Proto(take_buffer()))
+        }),
+        Err(err) => {
+            drop(take_buffer());
+            Err(err.into())
+        }
+    };
+    write_ingest_result(result, ack_id_res)
+}
+
+/// Check and queue a single proto record and register its acknowledgment
+fn ingest_proto_record(
+    stream_ref: &StreamWrapper,
+    data: Vec<u8>,
+    wait: Option<Duration>,
+    result: *mut CResult,
+) -> u64 {
+    let payload = EncodedRecord::Proto(data);
+
+    // Queue the record and register its acknowledgment
+    let ack_id_res = match stream_ref.validate_record(&payload) {
+        Ok(()) => stream_ref.try_ingest(Submission::Record(payload), wait),
+        Err(err) => Err(err.into()),
+    };
+    write_ingest_result(result, ack_id_res)
+}
+
+/// Write the outcome of an ingest call and return its ack ID (0 on error)
+fn write_ingest_result(result: *mut CResult, ack_id_res: Result<u64, IngestError>) -> u64 {
+    match ack_id_res {
+        Ok(ack_id) => {
+            write_success_result(result);
+            ack_id
+        }
+        Err(err) => {
+            write_ingest_error(result, err);
+            0
+        }
+    }
+}
+
+/// Write why records could not be queued, with the error code of the layer that refused them
+fn write_ingest_error(result: *mut CResult, err: IngestError) {
+    match err {
+        IngestError::Sdk(err) => write_zerobus_error(result, err),
+        IngestError::WouldBlock => write_coded_error_result(
+            result,
+            WOULD_BLOCK,
+            CErrorCode::WouldBlock,
+            true,
+        ),
+        IngestError::Spilled(err) => write_coded_error_result(
+            result,
+            &format!(
+                "{}; the records are kept in the spill directory and queued again when the stream is recreated",
+                err
+            ),
+            CErrorCode::Spilled,
+            false,
+        ),
+        IngestError::Spill(err) => {
+            let (error_code, is_retryable) = match err {
+                SpillError::Full { .. } => (CErrorCode::SpillFull, true),
+                SpillError::Io(_) => (CErrorCode::Internal, false),
+            };
+            write_coded_error_result(result, &err.to_string(), error_code, is_retryable)
+        }
+    }
+}
+
+/// Ingest a JSON record
+/// On streams created with transcode_json the record is converted to protobuf first
+/// Returns an acknowledgment ID that can be awaited later
+/// Returns 0 on error, and always in fire-and-forget mode (check result)
+#[no_mangle]
+pub extern "C" fn zerobus_stream_ingest_json_record(
+    stream: *mut CZerobusStream,
+    json_data: *const c_char,
+    result: *mut CResult,
+) -> u64 {
+    let stream_ref = match validate_stream_ptr(stream) {
+        Ok(s) => s,
+        Err(msg) => {
+            write_coded_error_result(result, msg, CErrorCode::InvalidArgument, false);
+            return 0;
+        }
+    };
+
+    let json_str = match unsafe { c_str_to_string(json_data) } {
+        Ok(s) => s,
+        Err(e) => {
+            write_coded_error_result(result, e, CErrorCode::InvalidArgument, false);
+            return 0;
+        }
+    };
+
+    ingest_json_record(stream_ref, json_str, None, result)
+}
+
+/// Ingest a JSON record given as a UTF-8 byte slice (no NUL terminator required)
+/// `json_data` may contain embedded NUL bytes and may be NULL when `json_len` is 0
+/// Behaves like zerobus_stream_ingest_json_record otherwise
+#[no_mangle]
+pub extern "C" fn zerobus_stream_ingest_json_record_bytes(
+    stream: *mut CZerobusStream,
+    json_data: *const u8,
+    json_len: usize,
+    result: *mut CResult,
+) -> u64 {
+    ingest_json_bytes(stream, json_data, json_len, None, result)
+}
+
+/// Ingest a JSON record given as a UTF-8 byte slice without blocking when the stream is saturated
+/// Fails immediately with error code WouldBlock like zerobus_stream_try_ingest_proto_record
+/// Behaves like zerobus_stream_ingest_json_record_bytes otherwise
+#[no_mangle]
+pub extern "C" fn zerobus_stream_try_ingest_json_record_bytes(
+    stream: *mut CZerobusStream,
+    json_data: *const u8,
+    json_len: usize,
+    result: *mut CResult,
+) -> u64 {
+    ingest_json_bytes(stream, json_data, json_len, Some(Duration::ZERO), result)
+}
+
+/// Ingest a JSON record given as a UTF-8 byte slice, waiting at most `timeout_ms` for a
+/// saturated stream before failing with error code WouldBlock
+/// Behaves like zerobus_stream_try_ingest_json_record_bytes otherwise
+#[no_mangle]
+pub extern "C" fn zerobus_stream_ingest_json_record_bytes_timeout(
+    stream: *mut CZerobusStream,
+    json_data: *const u8,
+    json_len: usize,
+    timeout_ms: u64,
+    result: *mut CResult,
+) -> u64 {
+    let wait = Duration::from_millis(timeout_ms);
+    ingest_json_bytes(stream, json_data, json_len, Some(wait), result)
+}
+
+fn ingest_json_bytes(
+    stream: *mut CZerobusStream,
+    json_data: *const u8,
+    json_len: usize,
+    wait: Option<Duration>,
+    result: *mut CResult,
+) -> u64 {
+    if json_data.is_null() && json_len > 0 {
+        write_coded_error_result(
+            result,
+            "Invalid data pointer",
+            CErrorCode::InvalidArgument,
+            false,
+        );
+        return 0;
+    }
+
+    let stream_ref = match validate_stream_ptr(stream) {
+        Ok(s) => s,
+        Err(msg) => {
+            write_coded_error_result(result, msg, CErrorCode::InvalidArgument, false);
+            return 0;
+        }
+    };
+
+    let bytes = if json_len == 0 {
+        &[][..]
+    } else {
+        unsafe { std::slice::from_raw_parts(json_data, json_len) }
+    };
+    let json_str = match std::str::from_utf8(bytes) {
+        Ok(s) => s.to_string(),
+        Err(_) => {
+            write_coded_error_result(
+                result,
+                "Invalid UTF-8 string",
+                CErrorCode::InvalidArgument,
+                false,
+            );
+            return 0;
+        }
+    };
+
+    ingest_json_record(stream_ref, json_str, wait, result)
+}
+
+/// Convert, check and queue a single JSON record and register its acknowledgment
+fn ingest_json_record(
+    stream_ref: &StreamWrapper,
+    json_str: String,
+    wait: Option<Duration>,
+    result: *mut CResult,
+) -> u64 {
+    let ack_id_res = stream_ref
+        .json_record(json_str)
+        .and_then(|payload| stream_ref.validate_record(&payload).map(|_| payload))
+        .map_err(IngestError::from)
+        .and_then(|payload| stream_ref.try_ingest(Submission::Record(payload), wait));
+    write_ingest_result(result, ack_id_res)
+}
+
+/// Ingest a batch of protobuf encoded records with a single call
+/// `data` holds all records back to back; `offsets` must contain `num_records + 1`
+/// entries, record `i` spanning `data[offsets[i]..offsets[i + 1]]`
+/// Returns one acknowledgment ID for the whole batch; awaiting it yields the batch offset
+/// Returns 0 on error, and always in fire-and-forget mode (check result)
+#[no_mangle]
+pub extern "C" fn zerobus_stream_ingest_proto_batch(
+    stream: *mut CZerobusStream,
+    data: *const u8,
+    data_len: usize,
+    offsets: *const usize,
+    num_records: usize,
+    result: *mut CResult,
+) -> u64 {
+    ingest_proto_batch(stream, data, data_len, offsets, num_records, None, result)
+}
+
+/// Ingest a batch of proto records without blocking when the stream is saturated
+/// Fails immediately with error code WouldBlock like zerobus_stream_try_ingest_proto_record,
+/// in which case no record of the batch is queued
+/// Behaves like zerobus_stream_ingest_proto_batch otherwise
+#[no_mangle]
+pub extern "C" fn zerobus_stream_try_ingest_proto_batch(
+    stream: *mut CZerobusStream,
+    data: *const u8,
+    data_len: usize,
+    offsets: *const usize,
+    num_records: usize,
+    result: *mut CResult,
+) -> u64 {
+    let wait = Some(Duration::ZERO);
+    ingest_proto_batch(stream, data, data_len, offsets, num_records, wait, result)
+}
+
+/// Ingest a batch of proto records, waiting at most `timeout_ms` for a saturated stream before
+/// failing with error code WouldBlock
+/// Behaves like zerobus_stream_try_ingest_proto_batch otherwise
+#[no_mangle]
+pub extern "C" fn zerobus_stream_ingest_proto_batch_timeout(
+    stream: *mut CZerobusStream,
+    data: *const u8,
+    data_len: usize,
+    offsets: *const usize,
+    num_records: usize,
+    timeout_ms: u64,
+    result: *mut CResult,
+) -> u64 {
+    let wait = Some(Duration::from_millis(timeout_ms));
+    ingest_proto_batch(stream, data, data_len, offsets, num_records, wait, result)
+}
+
+fn ingest_proto_batch(
+    stream: *mut CZerobusStream,
+    data: *const u8,
+    data_len: usize,
+    offsets: *const usize,
+    num_records: usize,
+    wait: Option<Duration>,
+    result: *mut CResult,
+) -> u64 {
+    let stream_ref = match validate_stream_ptr(stream) {
+        Ok(s) => s,
+        Err(msg) => {
+            write_coded_error_result(result, msg, CErrorCode::InvalidArgument, false);
+            return 0;
+        }
+    };
+
+    let records = match unsafe { split_batch_buffer(data, data_len, offsets, num_records) } {
+        Ok(slices) => slices
+            .into_iter()
+            .map(|s| EncodedRecord::Proto(s.to_vec()))
+            .collect::<Vec<_>>(),
+        Err(e) => {
+            write_coded_error_result(result, e, CErrorCode::InvalidArgument, false);
+            return 0;
+        }
+    };
+
+    ingest_batch(stream_ref, records, wait, result)
+}
+
+/// Ingest a batch of JSON records with a single call
+/// `data` holds all UTF-8 encoded records back to back (no NUL terminators);
+/// `offsets` must contain `num_records + 1` entries, record `i` spanning
+/// `data[offsets[i]..offsets[i + 1]]`
+/// Returns one acknowledgment ID for the whole batch; awaiting it yields the batch offset
+/// Returns 0 on error, and always in fire-and-forget mode (check result)
+#[no_mangle]
+pub extern "C" fn zerobus_stream_ingest_json_batch(
+    stream: *mut CZerobusStream,
+    data: *const u8,
+    data_len: usize,
+    offsets: *const usize,
+    num_records: usize,
+    result: *mut CResult,
+) -> u64 {
+    ingest_json_batch(stream, data, data_len, offsets, num_records, None, result)
+}
+
+/// Ingest a batch of JSON records without blocking when the stream is saturated
+/// Fails immediately with error code WouldBlock like zerobus_stream_try_ingest_proto_record,
+/// in which case no record of the batch is queued
+/// Behaves like zerobus_stream_ingest_json_batch otherwise
+#[no_mangle]
+pub extern "C" fn zerobus_stream_try_ingest_json_batch(
+    stream: *mut CZerobusStream,
+    data: *const u8,
+    data_len: usize,
+    offsets: *const usize,
+    num_records: usize,
+    result: *mut CResult,
+) -> u64 {
+    let wait = Some(Duration::ZERO);
+    ingest_json_batch(stream, data, data_len, offsets, num_records, wait, result)
+}
+
+/// Ingest a batch of JSON records, waiting at most `timeout_ms` for a saturated stream before
+/// failing with error code WouldBlock
+/// Behaves like zerobus_stream_try_ingest_json_batch otherwise
+#[no_mangle]
+pub extern "C" fn zerobus_stream_ingest_json_batch_timeout(
+    stream: *mut CZerobusStream,
+    data: *const u8,
+    data_len: usize,
+    offsets: *const usize,
+    num_records: usize,
+    timeout_ms: u64,
+    result: *mut CResult,
+) -> u64 {
+    let wait = Some(Duration::from_millis(timeout_ms));
+    ingest_json_batch(stream, data, data_len, offsets, num_records, wait, result)
+}
+
+fn ingest_json_batch(
+    stream: *mut CZerobusStream,
+    data: *const u8,
+    data_len: usize,
+    offsets: *const usize,
+    num_records: usize,
+    wait: Option<Duration>,
+    result: *mut CResult,
+) -> u64 {
+    let stream_ref = match validate_stream_ptr(stream) {
+        Ok(s) => s,
+        Err(msg) => {
+            write_coded_error_result(result, msg, CErrorCode::InvalidArgument, false);
+            return 0;
+        }
+    };
+
+    let records = match unsafe { split_batch_buffer(data, data_len, offsets, num_records) } {
+        Ok(slices) => match slices
+            .into_iter()
+            .map(|s| std::str::from_utf8(s).map(str::to_string))
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(records) => records,
+            Err(_) => {
+                write_coded_error_result(
+                    result,
+                    "Invalid UTF-8 string",
+                    CErrorCode::InvalidArgument,
+                    false,
+                );
+                return 0;
+            }
+        },
+        Err(e) => {
+            write_coded_error_result(result, e, CErrorCode::InvalidArgument, false);
+            return 0;
+        }
+    };
+
+    let records = match records
+        .into_iter()
+        .enumerate()
+        .map(|(i, json)| {
+            stream_ref
+                .json_record(json)
+                .map_err(|err| in_batch_record(i, err))
+        })
+        .collect::<ZerobusResult<Vec<_>>>()
+    {
+        Ok(records) => records,
+        Err(err) => {
+            write_zerobus_error(result, err);
+            return 0;
+        }
+    };
+
+    ingest_batch(stream_ref, records, wait, result)
 }
 
-/// Create a stream with a custom headers provider callback
-/// This allows you to provide custom authentication headers via a Go callback function
+/// Queue a batch under a single runtime entry and register one acknowledgment for it
+fn ingest_batch(
+    stream_ref: &StreamWrapper,
+    records: Vec<EncodedRecord>,
+    wait: Option<Duration>,
+    result: *mut CResult,
+) -> u64 {
+    write_ingest_result(result, stream_ref.ingest_batch(records, wait))
+}
+
+/// Await an acknowledgment (BLOCKING)
+/// Returns the offset on success, or -1 on error
+/// Streams created with an ack_callback deliver acknowledgments to it, so their ack IDs cannot be awaited
 #[no_mangle]
-pub extern "C" fn zerobus_sdk_create_stream_with_headers_provider(
-    sdk: *mut CZerobusSdk,
-    table_name: *const c_char,
-    descriptor_proto_bytes: *const u8,
-    descriptor_proto_len: usize,
-    headers_callback: HeadersProviderCallback,
-    user_data: *mut std::ffi::c_void,
-    options: *const CStreamConfigurationOptions,
+pub extern "C" fn zerobus_stream_await_ack(
+    stream: *mut CZerobusStream,
+    ack_id: u64,
     result: *mut CResult,
-) -> *mut CZerobusStream {
-    let sdk_ref = match validate_sdk_ptr(sdk) {
+) -> i64 {
+    let stream_ref = match validate_stream_ptr(stream) {
         Ok(s) => s,
         Err(msg) => {
-            write_error_result(result, msg, false);
-            return ptr::null_mut();
+            write_coded_error_result(result, msg, CErrorCode::InvalidArgument, false);
+            return -1;
         }
     };
 
-    let res = RUNTIME.block_on(async {
-        let table_name_str = unsafe { c_str_to_string(table_name).map_err(|e| e.to_string())? };
-
-        // Decode descriptor if provided
-        let descriptor_proto = if !descriptor_proto_bytes.is_null() && descriptor_proto_len > 0 {
-            let bytes =
-                unsafe { std::slice::from_raw_parts(descriptor_proto_bytes, descriptor_proto_len) };
-            Some(prost_types::DescriptorProto::decode(bytes).map_err(|e| e.to_string())?)
-        } else {
-            None
-        };
-
-        let table_props = TableProperties {
-            table_name: table_name_str,
-            descriptor_proto,
-        };
-
-        let stream_options = if !options.is_null() {
-            Some(unsafe { (*options).into() })
-        } else {
-            None
-        };
+    await_registered_ack(&stream_ref.acks, ack_id, None, result)
+}
 
-        // Create the headers provider from the callback with thread-safety validation
-        let headers_provider = Arc::new(CallbackHeadersProvider::new(headers_callback, user_data));
+/// Await an acknowledgment for at most `timeout_ms` milliseconds (BLOCKING)
+/// Returns the offset on success, or -1 on error
+/// If the acknowledgment is still pending when the timeout expires, fails with error code
+/// WaitTimedOut and the ack ID stays registered, so it can be awaited again later
+#[no_mangle]
+pub extern "C" fn zerobus_stream_await_ack_timeout(
+    stream: *mut CZerobusStream,
+    ack_id: u64,
+    timeout_ms: u64,
+    result: *mut CResult,
+) -> i64 {
+    let stream_ref = match validate_stream_ptr(stream) {
+        Ok(s) => s,
+        Err(msg) => {
+            write_coded_error_result(result, msg, CErrorCode::InvalidArgument, false);
+            return -1;
+        }
+    };
 
-        let stream = sdk_ref
-            .create_stream_with_headers_provider(table_props, headers_provider, stream_options)
-            .await
-            .map_err(|e| e.to_string())?;
+    let wait = Duration::from_millis(timeout_ms);
+    await_registered_ack(&stream_ref.acks, ack_id, Some(wait), result)
+}
 
-        let boxed = Box::new(stream);
-        Ok::<*mut CZerobusStream, String>(Box::into_raw(boxed) as *mut CZerobusStream)
-    });
+/// Wait for a registered acknowledgment, at most `wait` if given, and write its outcome
+/// The ack ID stays registered while waiting and is consumed once the outcome is written; if the
+/// wait times out, it can be awaited again
+pub(crate) fn await_registered_ack(
+    acks: &AckRegistry,
+    ack_id: u64,
+    wait: Option<Duration>,
+    result: *mut CResult,
+) -> i64 {
+    let ack_ids = [ack_id];
+    let taken = match wait {
+        None => runtime().block_on(acks.take_first_settled(&ack_ids)),
+        Some(wait) => {
+            match runtime().block_on(async {
+                tokio::time::timeout(wait, acks.take_first_settled(&ack_ids)).await
+            }) {
+                Ok(taken) => taken,
+                Err(_) => {
+                    write_coded_error_result(
+                        result,
+                        "Timed out waiting for the acknowledgment",
+                        CErrorCode::WaitTimedOut,
+                        true,
+                    );
+                    return -1;
+                }
+            }
+        }
+    };
+    let Some((_, handle)) = taken else {
+        write_coded_error_result(result, "Invalid ack ID", CErrorCode::InvalidArgument, false);
+        return -1;
+    };
+    write_ack_result(result, runtime().block_on(handle))
+}
 
+/// Write the outcome of an acknowledgment task and return its offset (-1 on error)
+fn write_ack_result(result: *mut CResult, res: Result<ZerobusResult<i64>, JoinError>) -> i64 {
     match res {
-        Ok(stream_ptr) => {
+        Ok(Ok(offset)) => {
             write_success_result(result);
-            stream_ptr
+            offset
         }
-        Err(err) => {
-            write_error_result(result, &err, false);
-            ptr::null_mut()
+        Ok(Err(err)) => {
+            write_zerobus_error(result, err);
+            -1
         }
-    }
-}
-
-/// Free a stream instance
-#[no_mangle]
-pub extern "C" fn zerobus_stream_free(stream: *mut CZerobusStream) {
-    if !stream.is_null() {
-        unsafe {
-            let _ = Box::from_raw(stream as *mut ZerobusStream);
+        Err(_) => {
+            write_coded_error_result(result, "Task panicked", CErrorCode::Internal, false);
+            -1
         }
     }
 }
 
-/// Ingest a record (protobuf encoded)
-/// Returns an acknowledgment ID that can be awaited later
-/// Returns 0 on error
+/// Await several acknowledgments at once (BLOCKING)
+/// Writes the offset of `ack_ids[i]` to `offsets_out[i]` (-1 on error) and, unless
+/// `results_out` is NULL, its outcome to `results_out[i]`; error messages there must be freed
+/// with zerobus_free_error_message. Every given ack ID is consumed, unknown IDs fail individually
+/// Returns true if all acknowledgments succeeded; `result` only reports invalid arguments
 #[no_mangle]
-pub extern "C" fn zerobus_stream_ingest_proto_record(
+pub extern "C" fn zerobus_stream_await_acks(
     stream: *mut CZerobusStream,
-    data: *const u8,
-    data_len: usize,
+    ack_ids: *const u64,
+    count: usize,
+    offsets_out: *mut i64,
+    results_out: *mut CResult,
     result: *mut CResult,
-) -> u64 {
-    if data.is_null() {
-        write_error_result(result, "Invalid data pointer", false);
-        return 0;
+) -> bool {
+    if count > 0 && (ack_ids.is_null() || offsets_out.is_null()) {
+        write_coded_error_result(
+            result,
+            "Invalid ack ID array",
+            CErrorCode::InvalidArgument,
+            false,
+        );
+        return false;
     }
-
     let stream_ref = match validate_stream_ptr(stream) {
         Ok(s) => s,
         Err(msg) => {
-            write_error_result(result, msg, false);
-            return 0;
+            write_coded_error_result(result, msg, CErrorCode::InvalidArgument, false);
+            return false;
         }
     };
+    if count == 0 {
+        write_success_result(result);
+        return true;
+    }
 
-    let data_slice = unsafe { std::slice::from_raw_parts(data, data_len) };
-    let data_vec = data_slice.to_vec();
-
-    // Queue the record and get the acknowledgment future
-    let ack_future_res = RUNTIME.block_on(async {
-        let payload = EncodedRecord::Proto(data_vec);
-        stream_ref.ingest_record(payload).await
+    let ack_ids = unsafe { std::slice::from_raw_parts(ack_ids, count) };
+    let offsets_out = unsafe { std::slice::from_raw_parts_mut(offsets_out, count) };
+    let handles = stream_ref.acks.remove_many(ack_ids);
+
+    // The acknowledgment tasks already run concurrently, so awaiting them in order is enough
+    let outcomes = runtime().block_on(async {
+        let mut outcomes = Vec::with_capacity(count);
+        for handle in handles {
+            outcomes.push(match handle {
+                Some(handle) => Some(handle.await),
+                None => None,
+            });
+        }
+        outcomes
     });
 
-    match ack_future_res {
-        Ok(ack_future) => {
-            // Spawn a task to await the acknowledgment
-            let ack_id = ACK_COUNTER.fetch_add(1, Ordering::SeqCst);
-            let handle = RUNTIME.spawn(ack_future);
-
-            // Store the handle
-            ACK_REGISTRY.lock().unwrap().insert(ack_id, handle);
-
-            write_success_result(result);
-            ack_id
-        }
-        Err(err) => {
-            if !result.is_null() {
-                unsafe {
-                    *result = CResult::error(err);
-                }
+    let mut all_ok = true;
+    for (i, outcome) in outcomes.into_iter().enumerate() {
+        let entry_result = if results_out.is_null() {
+            ptr::null_mut()
+        } else {
+            unsafe { results_out.add(i) }
+        };
+        offsets_out[i] = match outcome {
+            Some(res) => write_ack_result(entry_result, res),
+            None => {
+                write_coded_error_result(
+                    entry_result,
+                    "Invalid ack ID",
+                    CErrorCode::InvalidArgument,
+                    false,
+                );
+                -1
             }
-            0
-        }
+        };
+        all_ok &= offsets_out[i] >= 0;
     }
+    write_success_result(result);
+    all_ok
 }
 
-/// Ingest a JSON record
-/// Returns an acknowledgment ID that can be awaited later
-/// Returns 0 on error
+/// Await whichever of several acknowledgments completes first (BLOCKING)
+/// The completed ack ID is consumed and returned; its offset is written to `offset_out` (-1 on
+/// error) and its outcome to `result`. The other ack IDs stay registered throughout: they can be
+/// awaited from other threads during the call and still count as outstanding acknowledgments
+/// Returns 0 if nothing was awaited (empty or invalid arguments, unknown or repeated IDs, or all
+/// listed IDs consumed by concurrent calls before any completed)
 #[no_mangle]
-pub extern "C" fn zerobus_stream_ingest_json_record(
+pub extern "C" fn zerobus_stream_await_any_ack(
     stream: *mut CZerobusStream,
-    json_data: *const c_char,
+    ack_ids: *const u64,
+    count: usize,
+    offset_out: *mut i64,
     result: *mut CResult,
 ) -> u64 {
+    if ack_ids.is_null() || count == 0 {
+        write_coded_error_result(
+            result,
+            "Invalid ack ID array",
+            CErrorCode::InvalidArgument,
+            false,
+        );
+        return 0;
+    }
     let stream_ref = match validate_stream_ptr(stream) {
         Ok(s) => s,
         Err(msg) => {
-            write_error_result(result, msg, false);
-            return 0;
-        }
-    };
-
-    let json_str = match unsafe { c_str_to_string(json_data) } {
-        Ok(s) => s,
-        Err(e) => {
-            write_error_result(result, e, false);
+            write_coded_error_result(result, msg, CErrorCode::InvalidArgument, false);
             return 0;
         }
     };
 
-    // Queue the record and get the acknowledgment future
-    let ack_future_res = RUNTIME.block_on(async {
-        let payload = EncodedRecord::Json(json_str);
-        stream_ref.ingest_record(payload).await
-    });
-
-    match ack_future_res {
-        Ok(ack_future) => {
-            // Spawn a task to await the acknowledgment
-            let ack_id = ACK_COUNTER.fetch_add(1, Ordering::SeqCst);
-            let handle = RUNTIME.spawn(ack_future);
-
-            // Store the handle
-            ACK_REGISTRY.lock().unwrap().insert(ack_id, handle);
-
-            write_success_result(result);
-            ack_id
-        }
-        Err(err) => {
-            if !result.is_null() {
-                unsafe {
-                    *result = CResult::error(err);
-                }
-            }
-            0
-        }
+    let ack_ids = unsafe { std::slice::from_raw_parts(ack_ids, count) };
+    if let Err(ack_id) = stream_ref.acks.check_all_registered(ack_ids) {
+        write_coded_error_result(
+            result,
+            &format!("Invalid ack ID {}", ack_id),
+            CErrorCode::InvalidArgument,
+            false,
+        );
+        return 0;
     }
-}
 
-/// Await an acknowledgment (BLOCKING)
-/// Returns the offset on success, or -1 on error
-#[no_mangle]
-pub extern "C" fn zerobus_stream_await_ack(ack_id: u64, result: *mut CResult) -> i64 {
-    // Remove the handle from the registry
-    let handle = {
-        let mut registry = ACK_REGISTRY.lock().unwrap();
-        registry.remove(&ack_id)
+    let Some((index, handle)) = runtime().block_on(stream_ref.acks.take_first_settled(ack_ids))
+    else {
+        write_coded_error_result(
+            result,
+            "All ack IDs were consumed by concurrent calls",
+            CErrorCode::InvalidArgument,
+            false,
+        );
+        return 0;
     };
+    let res = runtime().block_on(handle);
 
-    match handle {
-        Some(h) => {
-            // Wait for the acknowledgment
-            let res = RUNTIME.block_on(h);
-
-            match res {
-                Ok(Ok(offset)) => {
-                    if !result.is_null() {
-                        unsafe {
-                            *result = CResult::success();
-                        }
-                    }
-                    offset
-                }
-                Ok(Err(err)) => {
-                    if !result.is_null() {
-                        unsafe {
-                            *result = CResult::error(err);
-                        }
-                    }
-                    -1
-                }
-                Err(_) => {
-                    if !result.is_null() {
-                        unsafe {
-                            *result = CResult {
-                                success: false,
-                                error_message: CString::new("Task panicked").unwrap().into_raw(),
-                                is_retryable: false,
-                            };
-                        }
-                    }
-                    -1
-                }
-            }
-        }
-        None => {
-            if !result.is_null() {
-                unsafe {
-                    *result = CResult {
-                        success: false,
-                        error_message: CString::new("Invalid ack ID").unwrap().into_raw(),
-                        is_retryable: false,
-                    };
-                }
-            }
-            -1
+    let offset = write_ack_result(result, res);
+    if !offset_out.is_null() {
+        unsafe {
+            *offset_out = offset;
         }
     }
+    ack_ids[index]
 }
 
 /// Try to get an acknowledgment without blocking
@@ -718,101 +3168,240 @@ pub extern "C" fn zerobus_stream_await_ack(ack_id: u64, result: *mut CResult) ->
 ///   -2: Error occurred (check result)
 #[no_mangle]
 pub extern "C" fn zerobus_stream_try_get_ack(
+    stream: *mut CZerobusStream,
     ack_id: u64,
     is_ready: *mut bool,
     result: *mut CResult,
 ) -> i64 {
-    let registry = ACK_REGISTRY.lock().unwrap();
+    let set_ready = |ready: bool| {
+        if !is_ready.is_null() {
+            unsafe {
+                *is_ready = ready;
+            }
+        }
+    };
 
-    if let Some(handle) = registry.get(&ack_id) {
-        if handle.is_finished() {
-            drop(registry);
-            // Remove and get the result
-            let handle = ACK_REGISTRY.lock().unwrap().remove(&ack_id).unwrap();
-            let res = RUNTIME.block_on(handle);
+    let stream_ref = match validate_stream_ptr(stream) {
+        Ok(s) => s,
+        Err(msg) => {
+            set_ready(false);
+            write_coded_error_result(result, msg, CErrorCode::InvalidArgument, false);
+            return -2;
+        }
+    };
 
-            if !is_ready.is_null() {
-                unsafe {
-                    *is_ready = true;
-                }
-            }
+    match stream_ref.acks.remove_if_finished(ack_id) {
+        Ok(Some(handle)) => {
+            let res = runtime().block_on(handle);
+            set_ready(true);
 
             match res {
                 Ok(Ok(offset)) => {
-                    if !result.is_null() {
-                        unsafe {
-                            *result = CResult::success();
-                        }
-                    }
+                    write_success_result(result);
                     offset
                 }
                 Ok(Err(err)) => {
-                    if !result.is_null() {
-                        unsafe {
-                            *result = CResult::error(err);
-                        }
-                    }
+                    write_zerobus_error(result, err);
                     -2
                 }
                 Err(_) => {
-                    if !result.is_null() {
-                        unsafe {
-                            *result = CResult {
-                                success: false,
-                                error_message: CString::new("Task panicked").unwrap().into_raw(),
-                                is_retryable: false,
-                            };
-                        }
-                    }
+                    write_coded_error_result(result, "Task panicked", CErrorCode::Internal, false);
                     -2
                 }
             }
-        } else {
+        }
+        Ok(None) => {
             // Still pending
-            if !is_ready.is_null() {
-                unsafe {
-                    *is_ready = false;
-                }
-            }
-            if !result.is_null() {
-                unsafe {
-                    *result = CResult::success();
-                }
-            }
+            set_ready(false);
+            write_success_result(result);
             -1
         }
-    } else {
-        // Invalid ID
-        if !is_ready.is_null() {
+        Err(()) => {
+            // Invalid ID
+            set_ready(false);
+            write_coded_error_result(result, "Invalid ack ID", CErrorCode::InvalidArgument, false);
+            -2
+        }
+    }
+}
+
+/// Get the number of acknowledgments of a stream that have not been awaited or delivered yet
+/// Returns 0 for a NULL stream
+#[no_mangle]
+pub extern "C" fn zerobus_stream_get_outstanding_acks(stream: *mut CZerobusStream) -> usize {
+    match validate_stream_ptr(stream) {
+        Ok(s) => s.outstanding_acks(),
+        Err(_) => 0,
+    }
+}
+
+/// A record that was ingested but never acknowledged
+#[repr(C)]
+pub struct CUnackedRecord {
+    /// Ack ID the record was ingested with; all records of a batch share one ID
+    /// (in fire-and-forget mode these IDs were not handed out but still follow ingestion order)
+    pub ack_id: u64,
+    /// Same values as CStreamConfigurationOptions.record_type: 1 = protobuf, 2 = JSON
+    pub record_type: i32,
+    /// Protobuf bytes or UTF-8 JSON (not NUL-terminated)
+    pub data: *mut u8,
+    pub data_len: usize,
+}
+
+/// A list of unacknowledged records, in ingestion order
+#[repr(C)]
+pub struct CUnackedRecords {
+    pub records: *mut CUnackedRecord,
+    pub count: usize,
+}
+
+impl CUnackedRecords {
+    fn empty() -> Self {
+        CUnackedRecords {
+            records: ptr::null_mut(),
+            count: 0,
+        }
+    }
+}
+
+/// Flatten unacknowledged batches into C records
+/// The SDK only keeps the unacknowledged tail of a stream, so the batches belong to
+/// the last `batches.len()` ack IDs up to and including `last_ack_id`
+pub(crate) fn unacked_batches_to_c<B>(batches: Vec<B>, last_ack_id: u64) -> CUnackedRecords
+where
+    B: IntoIterator<Item = EncodedRecord>,
+{
+    let first_ack_id = (last_ack_id + 1).saturating_sub(batches.len() as u64);
+
+    let mut records = Vec::new();
+    for (ack_id, batch) in (first_ack_id..).zip(batches) {
+        for record in batch {
+            let (record_type, bytes) = match record {
+                EncodedRecord::Proto(bytes) => (1, bytes),
+                EncodedRecord::Json(json) => (2, json.into_bytes()),
+            };
+            let data_len = bytes.len();
+            records.push(CUnackedRecord {
+                ack_id,
+                record_type,
+                data: Box::into_raw(bytes.into_boxed_slice()) as *mut u8,
+                data_len,
+            });
+        }
+    }
+
+    if records.is_empty() {
+        return CUnackedRecords::empty();
+    }
+    let count = records.len();
+    CUnackedRecords {
+        records: Box::into_raw(records.into_boxed_slice()) as *mut CUnackedRecord,
+        count,
+    }
+}
+
+/// Get the records of a failed or closed stream that were never acknowledged,
+/// e.g. to re-ingest them on a new stream or persist them elsewhere
+/// Fails with an invalid state error while the stream is still active
+/// The returned records must be released with zerobus_free_unacked_records
+#[no_mangle]
+pub extern "C" fn zerobus_stream_get_unacked_records(
+    stream: *mut CZerobusStream,
+    result: *mut CResult,
+) -> CUnackedRecords {
+    let stream_ref = match validate_stream_ptr(stream) {
+        Ok(s) => s,
+        Err(msg) => {
+            write_coded_error_result(result, msg, CErrorCode::InvalidArgument, false);
+            return CUnackedRecords::empty();
+        }
+    };
+
+    // Hold the ingest lock so no ack ID is handed out between reading the batches and the last ID
+    let _guard = stream_ref.ingest_lock.lock().unwrap();
+    let res = runtime().block_on(async { stream_ref.stream.get_unacked_batches().await });
+
+    match res {
+        Ok(batches) => {
+            write_success_result(result);
+            unacked_batches_to_c(batches, stream_ref.acks.last_id())
+        }
+        Err(err) => {
+            write_zerobus_error(result, err);
+            CUnackedRecords::empty()
+        }
+    }
+}
+
+/// Free records returned by zerobus_stream_get_unacked_records
+#[no_mangle]
+pub extern "C" fn zerobus_free_unacked_records(records: CUnackedRecords) {
+    if records.records.is_null() {
+        return;
+    }
+    unsafe {
+        let records = Box::from_raw(ptr::slice_from_raw_parts_mut(
+            records.records,
+            records.count,
+        ));
+        for record in records.iter() {
+            let _ = Box::from_raw(ptr::slice_from_raw_parts_mut(record.data, record.data_len));
+        }
+    }
+}
+
+/// Get a snapshot of the stream's ingestion metrics
+/// Writes zeroed metrics and returns false on error (check result)
+#[no_mangle]
+pub extern "C" fn zerobus_stream_get_metrics(
+    stream: *mut CZerobusStream,
+    metrics: *mut CStreamMetrics,
+    result: *mut CResult,
+) -> bool {
+    if metrics.is_null() {
+        write_coded_error_result(
+            result,
+            "Invalid metrics pointer",
+            CErrorCode::InvalidArgument,
+            false,
+        );
+        return false;
+    }
+
+    match validate_stream_ptr(stream) {
+        Ok(s) => {
             unsafe {
-                *is_ready = false;
+                *metrics = s.metrics.snapshot();
             }
+            write_success_result(result);
+            true
         }
-        if !result.is_null() {
+        Err(msg) => {
             unsafe {
-                *result = CResult {
-                    success: false,
-                    error_message: CString::new("Invalid ack ID").unwrap().into_raw(),
-                    is_retryable: false,
-                };
+                *metrics = CStreamMetrics::default();
             }
+            write_coded_error_result(result, msg, CErrorCode::InvalidArgument, false);
+            false
         }
-        -2
     }
 }
 
 /// Flush all pending records
+/// Also fails if a fire-and-forget record was rejected since the previous flush
 #[no_mangle]
 pub extern "C" fn zerobus_stream_flush(stream: *mut CZerobusStream, result: *mut CResult) -> bool {
     let stream_ref = match validate_stream_ptr(stream) {
         Ok(s) => s,
         Err(msg) => {
-            write_error_result(result, msg, false);
+            write_coded_error_result(result, msg, CErrorCode::InvalidArgument, false);
             return false;
         }
     };
 
-    let res = RUNTIME.block_on(async { stream_ref.flush().await });
+    // Records ingested so far reach the disk before the SDK is waited on
+    let synced = stream_ref.sync_spill();
+    let res = runtime().block_on(async { stream_ref.stream.flush().await });
+    let res = stream_ref.with_deferred_error(res).and(synced);
 
     match res {
         Ok(_) => {
@@ -820,11 +3409,7 @@ pub extern "C" fn zerobus_stream_flush(stream: *mut CZerobusStream, result: *mut
             true
         }
         Err(err) => {
-            if !result.is_null() {
-                unsafe {
-                    *result = CResult::error(err);
-                }
-            }
+            write_zerobus_error(result, err);
             false
         }
     }
@@ -836,12 +3421,15 @@ pub extern "C" fn zerobus_stream_close(stream: *mut CZerobusStream, result: *mut
     let stream_ref = match validate_stream_ptr_mut(stream) {
         Ok(s) => s,
         Err(msg) => {
-            write_error_result(result, msg, false);
+            write_coded_error_result(result, msg, CErrorCode::InvalidArgument, false);
             return false;
         }
     };
 
-    let res = RUNTIME.block_on(async { stream_ref.close().await });
+    // Records ingested so far reach the disk before the SDK is waited on
+    let synced = stream_ref.sync_spill();
+    let res = runtime().block_on(async { stream_ref.stream.close().await });
+    let res = stream_ref.with_deferred_error(res).and(synced);
 
     match res {
         Ok(_) => {
@@ -849,11 +3437,7 @@ pub extern "C" fn zerobus_stream_close(stream: *mut CZerobusStream, result: *mut
             true
         }
         Err(err) => {
-            if !result.is_null() {
-                unsafe {
-                    *result = CResult::error(err);
-                }
-            }
+            write_zerobus_error(result, err);
             false
         }
     }
@@ -882,5 +3466,16 @@ pub extern "C" fn zerobus_get_default_config() -> CStreamConfigurationOptions {
         server_lack_of_ack_timeout_ms: default_opts.server_lack_of_ack_timeout_ms,
         flush_timeout_ms: default_opts.flush_timeout_ms,
         record_type: 1, // RecordType::Proto
+        ack_callback: None,
+        ack_callback_user_data: ptr::null_mut(),
+        fire_and_forget: false,
+        validate_records: false,
+        transcode_json: false,
+        table_schema_json: ptr::null(),
+        spill_dir: ptr::null(),
+        spill_max_bytes: 0,
+        dead_letter_callback: None,
+        dead_letter_user_data: ptr::null_mut(),
+        dead_letter_path: ptr::null(),
     }
 }
@@ -0,0 +1,369 @@
+// Bridge from `tracing` events emitted by the SDK and this layer to a host log callback
+
+use once_cell::sync::{Lazy, OnceCell};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::fmt;
+use std::os::raw::c_char;
+use std::ptr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span;
+use tracing::{Event, Level, Metadata, Subscriber};
+
+use crate::{write_coded_error_result, write_success_result, CErrorCode, CResult};
+
+/// Severity of a log event passed to the log callback
+/// Also used as the minimum level when registering the callback (Off disables logging)
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CLogLevel {
+    Off = 0,
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+    Trace = 5,
+}
+
+impl CLogLevel {
+    pub(crate) fn from_i32(level: i32) -> Option<Self> {
+        match level {
+            0 => Some(CLogLevel::Off),
+            1 => Some(CLogLevel::Error),
+            2 => Some(CLogLevel::Warn),
+            3 => Some(CLogLevel::Info),
+            4 => Some(CLogLevel::Debug),
+            5 => Some(CLogLevel::Trace),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn filter(self) -> LevelFilter {
+        match self {
+            CLogLevel::Off => LevelFilter::OFF,
+            CLogLevel::Error => LevelFilter::ERROR,
+            CLogLevel::Warn => LevelFilter::WARN,
+            CLogLevel::Info => LevelFilter::INFO,
+            CLogLevel::Debug => LevelFilter::DEBUG,
+            CLogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
+
+impl From<&Level> for CLogLevel {
+    fn from(level: &Level) -> Self {
+        match *level {
+            Level::ERROR => CLogLevel::Error,
+            Level::WARN => CLogLevel::Warn,
+            Level::INFO => CLogLevel::Info,
+            Level::DEBUG => CLogLevel::Debug,
+            Level::TRACE => CLogLevel::Trace,
+        }
+    }
+}
+
+/// A key-value field attached to a log event
+#[repr(C)]
+pub struct CLogField {
+    pub key: *const c_char,
+    pub value: *const c_char,
+}
+
+/// Function pointer type for the log callback (nullable)
+/// Invoked on the thread that emitted the event; all strings are only valid during the call
+/// `fields` holds the event's fields followed by those of the spans it was emitted in
+pub type LogCallback = Option<
+    extern "C" fn(
+        user_data: *mut std::ffi::c_void,
+        level: CLogLevel,
+        target: *const c_char,
+        message: *const c_char,
+        fields: *const CLogField,
+        field_count: usize,
+    ),
+>;
+
+/// The registered callback together with its user data and minimum level
+#[derive(Clone, Copy)]
+pub(crate) struct LogSink {
+    pub(crate) callback: extern "C" fn(
+        *mut std::ffi::c_void,
+        CLogLevel,
+        *const c_char,
+        *const c_char,
+        *const CLogField,
+        usize,
+    ),
+    pub(crate) user_data: *mut std::ffi::c_void,
+    pub(crate) level: LevelFilter,
+}
+
+// Safety: The host is responsible for making the callback and its user data thread-safe
+unsafe impl Send for LogSink {}
+unsafe impl Sync for LogSink {}
+
+pub(crate) static LOG_SINK: Lazy<RwLock<Option<LogSink>>> = Lazy::new(|| RwLock::new(None));
+
+// Outcome of installing the global subscriber, which tracing only allows once per process
+static SUBSCRIBER_INSTALLED: OnceCell<Result<(), String>> = OnceCell::new();
+
+thread_local! {
+    // Spans entered on this thread, innermost last
+    static ENTERED_SPANS: RefCell<Vec<span::Id>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Add fields to a span's, replacing the values of keys it already has
+fn merge_fields(fields: &mut Vec<(String, String)>, values: Vec<(String, String)>) {
+    for (key, value) in values {
+        match fields.iter_mut().find(|(existing, _)| *existing == key) {
+            Some(field) => field.1 = value,
+            None => fields.push((key, value)),
+        }
+    }
+}
+
+fn current_sink() -> Option<LogSink> {
+    *LOG_SINK.read().unwrap()
+}
+
+/// Collects the message and the remaining fields of an event as strings
+#[derive(Default)]
+pub(crate) struct EventFields {
+    pub(crate) message: String,
+    pub(crate) fields: Vec<(String, String)>,
+}
+
+impl Visit for EventFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.fields
+                .push((field.name().to_string(), value.to_string()));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            self.fields
+                .push((field.name().to_string(), format!("{:?}", value)));
+        }
+    }
+}
+
+/// A span that is still referenced
+struct SpanData {
+    /// Fields of the span and its ancestors, the span's own taking precedence
+    fields: Vec<(String, String)>,
+    refs: usize,
+}
+
+/// Subscriber forwarding events to the registered log callback
+/// Events carry the fields of the span they were emitted in and of its ancestors
+#[derive(Default)]
+pub(crate) struct CallbackSubscriber {
+    next_span_id: AtomicU64,
+    spans: Mutex<HashMap<u64, SpanData>>,
+}
+
+impl CallbackSubscriber {
+    fn current_id(&self) -> Option<span::Id> {
+        ENTERED_SPANS.with(|entered| entered.borrow().last().cloned())
+    }
+
+    /// The span an event was emitted in
+    fn event_span(&self, event: &Event<'_>) -> Option<span::Id> {
+        match event.parent() {
+            Some(parent) => Some(parent.clone()),
+            None if event.is_contextual() => self.current_id(),
+            None => None,
+        }
+    }
+}
+
+impl Subscriber for CallbackSubscriber {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        // Spans are needed for the fields they attach to events, whatever their level
+        current_sink().is_some_and(|sink| {
+            sink.level != LevelFilter::OFF
+                && (metadata.is_span() || *metadata.level() <= sink.level)
+        })
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        // The SDK's spans are debug spans
+        Some(
+            current_sink()
+                .filter(|sink| sink.level != LevelFilter::OFF)
+                .map_or(LevelFilter::OFF, |sink| sink.level.max(LevelFilter::DEBUG)),
+        )
+    }
+
+    fn new_span(&self, attrs: &span::Attributes<'_>) -> span::Id {
+        let parent = if attrs.is_root() {
+            None
+        } else if let Some(parent) = attrs.parent() {
+            Some(parent.clone())
+        } else {
+            self.current_id()
+        };
+
+        let mut spans = self.spans.lock().unwrap();
+        let parent = parent.and_then(|parent| spans.get(&parent.into_u64()));
+        let mut fields = parent.map_or_else(Vec::new, |parent| parent.fields.clone());
+        let mut visitor = EventFields::default();
+        attrs.record(&mut visitor);
+        merge_fields(&mut fields, visitor.fields);
+
+        // Span IDs must be non-zero
+        let id = self.next_span_id.fetch_add(1, Ordering::Relaxed) + 1;
+        spans.insert(id, SpanData { fields, refs: 1 });
+        span::Id::from_u64(id)
+    }
+
+    fn record(&self, span: &span::Id, values: &span::Record<'_>) {
+        let mut visitor = EventFields::default();
+        values.record(&mut visitor);
+        if let Some(data) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+            merge_fields(&mut data.fields, visitor.fields);
+        }
+    }
+
+    fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        // Copy the sink out so the callback may re-register without deadlocking
+        let metadata = event.metadata();
+        let Some(sink) = current_sink().filter(|sink| *metadata.level() <= sink.level) else {
+            return;
+        };
+
+        let mut visitor = EventFields::default();
+        event.record(&mut visitor);
+
+        // Fields of the enclosing spans, e.g. the table of the SDK task, follow the event's own
+        if let Some(span) = self.event_span(event) {
+            if let Some(data) = self.spans.lock().unwrap().get(&span.into_u64()) {
+                for (key, value) in &data.fields {
+                    if !visitor.fields.iter().any(|(own, _)| own == key) {
+                        visitor.fields.push((key.clone(), value.clone()));
+                    }
+                }
+            }
+        }
+
+        let to_c = |s: &str| {
+            CString::new(s).unwrap_or_else(|_| CString::new(s.replace('\0', "")).unwrap())
+        };
+        let target = to_c(metadata.target());
+        let message = to_c(&visitor.message);
+        let strings: Vec<(CString, CString)> = visitor
+            .fields
+            .iter()
+            .map(|(key, value)| (to_c(key), to_c(value)))
+            .collect();
+        let fields: Vec<CLogField> = strings
+            .iter()
+            .map(|(key, value)| CLogField {
+                key: key.as_ptr(),
+                value: value.as_ptr(),
+            })
+            .collect();
+
+        (sink.callback)(
+            sink.user_data,
+            CLogLevel::from(metadata.level()),
+            target.as_ptr(),
+            message.as_ptr(),
+            if fields.is_empty() {
+                ptr::null()
+            } else {
+                fields.as_ptr()
+            },
+            fields.len(),
+        );
+    }
+
+    fn enter(&self, span: &span::Id) {
+        ENTERED_SPANS.with(|entered| entered.borrow_mut().push(span.clone()));
+    }
+
+    fn exit(&self, span: &span::Id) {
+        ENTERED_SPANS.with(|entered| {
+            let mut entered = entered.borrow_mut();
+            if let Some(pos) = entered.iter().rposition(|id| id == span) {
+                entered.remove(pos);
+            }
+        });
+    }
+
+    fn clone_span(&self, span: &span::Id) -> span::Id {
+        if let Some(data) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+            data.refs += 1;
+        }
+        span.clone()
+    }
+
+    fn try_close(&self, span: span::Id) -> bool {
+        let mut spans = self.spans.lock().unwrap();
+        let Some(data) = spans.get_mut(&span.into_u64()) else {
+            return false;
+        };
+        data.refs -= 1;
+        if data.refs > 0 {
+            return false;
+        }
+        spans.remove(&span.into_u64());
+        true
+    }
+}
+
+/// Forward log events of the SDK (recovery attempts, reconnects, token refreshes, ...) to a callback
+/// Events at `level` (a CLogLevel value) or more severe are delivered; a NULL callback or
+/// CLogLevel_Off stops forwarding. May be called again to replace the callback or change the level.
+/// `user_data` is passed to every call of the callback. Events already being delivered when the
+/// callback is replaced may still reach the previous callback with its user data.
+/// Fails if another tracing subscriber was already installed as the process-wide default.
+#[no_mangle]
+pub extern "C" fn zerobus_set_log_callback(
+    level: i32,
+    callback: LogCallback,
+    user_data: *mut std::ffi::c_void,
+    result: *mut CResult,
+) -> bool {
+    let Some(level) = CLogLevel::from_i32(level) else {
+        write_coded_error_result(
+            result,
+            "Invalid log level",
+            CErrorCode::InvalidArgument,
+            false,
+        );
+        return false;
+    };
+
+    let installed = SUBSCRIBER_INSTALLED.get_or_init(|| {
+        tracing::subscriber::set_global_default(CallbackSubscriber::default())
+            .map_err(|e| e.to_string())
+    });
+    if let Err(msg) = installed {
+        write_coded_error_result(result, msg, CErrorCode::InvalidState, false);
+        return false;
+    }
+
+    *LOG_SINK.write().unwrap() = callback.map(|callback| LogSink {
+        callback,
+        user_data,
+        level: level.filter(),
+    });
+    // Callsites cache whether they are enabled, so re-evaluate them for the new level
+    tracing::callsite::rebuild_interest_cache();
+
+    write_success_result(result);
+    true
+}
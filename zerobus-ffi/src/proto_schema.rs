@@ -0,0 +1,350 @@
+// Record schema derived from a stream's DescriptorProto, used to check records before enqueueing
+
+use prost::encoding::decode_varint;
+use prost_types::field_descriptor_proto::{Label, Type};
+use prost_types::DescriptorProto;
+use std::collections::{HashMap, HashSet};
+
+/// Deepest message nesting a record may have, the same limit prost decodes with
+const RECURSION_LIMIT: usize = 100;
+
+/// A field of a message, with its type reference resolved to a key of ProtoSchema::messages
+pub(crate) struct FieldSchema {
+    pub(crate) name: String,
+    pub(crate) kind: Type,
+    pub(crate) repeated: bool,
+    pub(crate) required: bool,
+    /// Path of the message type for message fields
+    pub(crate) message: Option<String>,
+    /// Path of the enum type for enum fields, None if the enum is not part of the descriptor
+    pub(crate) enum_type: Option<String>,
+    /// Index of the (non-synthetic) oneof the field belongs to
+    pub(crate) oneof: Option<i32>,
+}
+
+#[derive(Default)]
+pub(crate) struct MessageSchema {
+    pub(crate) fields: HashMap<u32, FieldSchema>,
+    /// Field numbers by field name and by JSON name (lowerCamelCase unless set in the descriptor)
+    pub(crate) by_name: HashMap<String, u32>,
+    /// Synthetic key/value message of a map field
+    pub(crate) map_entry: bool,
+}
+
+/// Owned, pre-resolved view of a self-contained DescriptorProto
+/// Messages and enums are keyed by their path below and including the root, e.g. "Event.Inner"
+pub(crate) struct ProtoSchema {
+    pub(crate) root: String,
+    pub(crate) messages: HashMap<String, MessageSchema>,
+    /// Enum values by name
+    pub(crate) enums: HashMap<String, HashMap<String, i32>>,
+}
+
+impl ProtoSchema {
+    pub(crate) fn new(descriptor: &DescriptorProto) -> Result<Self, String> {
+        let mut paths = Vec::new();
+        collect_paths(descriptor, descriptor.name().to_string(), &mut paths);
+
+        let mut enums = HashMap::new();
+        for (path, message) in &paths {
+            for enum_type in &message.enum_type {
+                let values = enum_type
+                    .value
+                    .iter()
+                    .map(|value| (value.name().to_string(), value.number()))
+                    .collect();
+                enums.insert(format!("{}.{}", path, enum_type.name()), values);
+            }
+        }
+
+        let mut messages = HashMap::new();
+        for (path, message) in &paths {
+            let mut schema = MessageSchema {
+                map_entry: message
+                    .options
+                    .as_ref()
+                    .is_some_and(|options| options.map_entry()),
+                ..Default::default()
+            };
+            for field in &message.field {
+                let number = field.number.filter(|n| *n > 0).ok_or_else(|| {
+                    format!("Field {} of {} has no valid number", field.name(), path)
+                })? as u32;
+                let kind = field.r#type();
+                let message_ref = match kind {
+                    Type::Message | Type::Group => Some(
+                        resolve(
+                            paths.iter().map(|(path, _)| path.as_str()),
+                            field.type_name(),
+                        )
+                        .ok_or_else(|| {
+                            format!(
+                                "Type {} of field {}.{} is not part of the descriptor",
+                                field.type_name(),
+                                path,
+                                field.name()
+                            )
+                        })?
+                        .to_string(),
+                    ),
+                    _ => None,
+                };
+                let enum_ref = match kind {
+                    Type::Enum => resolve(enums.keys().map(String::as_str), field.type_name())
+                        .map(str::to_string),
+                    _ => None,
+                };
+                let json_name = match &field.json_name {
+                    Some(json_name) => json_name.clone(),
+                    None => to_json_name(field.name()),
+                };
+
+                schema.by_name.insert(field.name().to_string(), number);
+                schema.by_name.insert(json_name, number);
+                schema.fields.insert(
+                    number,
+                    FieldSchema {
+                        name: field.name().to_string(),
+                        kind,
+                        repeated: field.label() == Label::Repeated,
+                        required: field.label() == Label::Required,
+                        message: message_ref,
+                        enum_type: enum_ref,
+                        oneof: field.oneof_index.filter(|_| !field.proto3_optional()),
+                    },
+                );
+            }
+            messages.insert(path.clone(), schema);
+        }
+
+        Ok(ProtoSchema {
+            root: descriptor.name().to_string(),
+            messages,
+            enums,
+        })
+    }
+
+    /// Decode an encoded record against the schema without materializing it
+    /// Errors name the offending field by its dotted path, e.g. "address.city"
+    pub(crate) fn validate(&self, record: &[u8]) -> Result<(), String> {
+        self.validate_message(&self.root, record, "", 0)
+    }
+
+    fn validate_message(
+        &self,
+        message: &str,
+        mut buf: &[u8],
+        path: &str,
+        depth: usize,
+    ) -> Result<(), String> {
+        let schema = &self.messages[message];
+        let mut seen = HashSet::new();
+
+        while !buf.is_empty() {
+            let key = decode_varint(&mut buf)
+                .map_err(|_| format!("truncated field key in {}", display_path(path)))?;
+            let number = (key >> 3) as u32;
+            let wire_type = key & 0x7;
+
+            let field = schema.fields.get(&number).ok_or_else(|| {
+                format!("unknown field number {} in {}", number, display_path(path))
+            })?;
+            let field_path = if path.is_empty() {
+                field.name.clone()
+            } else {
+                format!("{}.{}", path, field.name)
+            };
+            let fail = |problem: &str| format!("field '{}' ({}): {}", field_path, number, problem);
+            seen.insert(number);
+
+            match wire_type {
+                0 => {
+                    expect_wire_type(field, WIRE_VARINT).map_err(|e| fail(&e))?;
+                    decode_varint(&mut buf).map_err(|_| fail("truncated varint"))?;
+                }
+                1 => {
+                    expect_wire_type(field, WIRE_FIXED64).map_err(|e| fail(&e))?;
+                    buf = skip(buf, 8).ok_or_else(|| fail("truncated 64-bit value"))?;
+                }
+                5 => {
+                    expect_wire_type(field, WIRE_FIXED32).map_err(|e| fail(&e))?;
+                    buf = skip(buf, 4).ok_or_else(|| fail("truncated 32-bit value"))?;
+                }
+                2 => {
+                    let len = decode_varint(&mut buf).map_err(|_| fail("truncated length"))?;
+                    if len > buf.len() as u64 {
+                        return Err(fail("length exceeds the record"));
+                    }
+                    let (data, rest) = buf.split_at(len as usize);
+                    buf = rest;
+
+                    match field.kind {
+                        Type::String => {
+                            std::str::from_utf8(data).map_err(|_| fail("invalid UTF-8 string"))?;
+                        }
+                        Type::Bytes => {}
+                        Type::Message => {
+                            if depth == RECURSION_LIMIT {
+                                return Err(fail(&format!(
+                                    "messages nested deeper than {} levels",
+                                    RECURSION_LIMIT
+                                )));
+                            }
+                            let nested = field.message.as_deref().unwrap_or_default();
+                            self.validate_message(nested, data, &field_path, depth + 1)?;
+                        }
+                        _ if field.repeated && scalar_wire_type(field.kind).is_some() => {
+                            validate_packed(field.kind, data).map_err(|e| fail(&e))?;
+                        }
+                        _ => {
+                            return Err(fail(&format!(
+                                "length-delimited value for a {} field",
+                                type_name(field.kind)
+                            )))
+                        }
+                    }
+                }
+                _ => return Err(fail(&format!("unsupported wire type {}", wire_type))),
+            }
+        }
+
+        for (number, field) in &schema.fields {
+            if field.required && !seen.contains(number) {
+                let field_path = if path.is_empty() {
+                    field.name.clone()
+                } else {
+                    format!("{}.{}", path, field.name)
+                };
+                return Err(format!(
+                    "field '{}' ({}): required field is missing",
+                    field_path, number
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Collect every message of a descriptor together with its dotted path
+fn collect_paths<'a>(
+    message: &'a DescriptorProto,
+    path: String,
+    out: &mut Vec<(String, &'a DescriptorProto)>,
+) {
+    for nested in &message.nested_type {
+        collect_paths(nested, format!("{}.{}", path, nested.name()), out);
+    }
+    out.push((path, message));
+}
+
+/// Resolve a fully qualified type name (".pkg.Root.Nested") to the longest matching path
+pub(crate) fn resolve<'a>(
+    paths: impl Iterator<Item = &'a str>,
+    type_name: &str,
+) -> Option<&'a str> {
+    paths
+        .filter(|path| {
+            type_name.trim_start_matches('.') == *path || type_name.ends_with(&format!(".{}", path))
+        })
+        .max_by_key(|path| path.len())
+}
+
+/// Default JSON name protoc derives from a field name, e.g. "zip_code" -> "zipCode"
+fn to_json_name(name: &str) -> String {
+    let mut json_name = String::with_capacity(name.len());
+    let mut upper = false;
+    for c in name.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            json_name.push(c.to_ascii_uppercase());
+            upper = false;
+        } else {
+            json_name.push(c);
+        }
+    }
+    json_name
+}
+
+pub(crate) fn display_path(path: &str) -> String {
+    if path.is_empty() {
+        "record".to_string()
+    } else {
+        format!("'{}'", path)
+    }
+}
+
+const WIRE_VARINT: u64 = 0;
+const WIRE_FIXED64: u64 = 1;
+const WIRE_FIXED32: u64 = 5;
+
+/// Wire type of a non length-delimited scalar type, None for strings, bytes, messages and groups
+fn scalar_wire_type(kind: Type) -> Option<u64> {
+    match kind {
+        Type::Double | Type::Fixed64 | Type::Sfixed64 => Some(WIRE_FIXED64),
+        Type::Float | Type::Fixed32 | Type::Sfixed32 => Some(WIRE_FIXED32),
+        Type::Int64
+        | Type::Uint64
+        | Type::Int32
+        | Type::Bool
+        | Type::Uint32
+        | Type::Enum
+        | Type::Sint32
+        | Type::Sint64 => Some(WIRE_VARINT),
+        Type::String | Type::Bytes | Type::Message | Type::Group => None,
+    }
+}
+
+fn expect_wire_type(field: &FieldSchema, wire_type: u64) -> Result<(), String> {
+    if scalar_wire_type(field.kind) == Some(wire_type) {
+        Ok(())
+    } else {
+        Err(format!(
+            "wire type {} does not match a {} field",
+            wire_type,
+            type_name(field.kind)
+        ))
+    }
+}
+
+fn validate_packed(kind: Type, mut data: &[u8]) -> Result<(), String> {
+    match scalar_wire_type(kind) {
+        Some(WIRE_FIXED64) if data.len().is_multiple_of(8) => Ok(()),
+        Some(WIRE_FIXED32) if data.len().is_multiple_of(4) => Ok(()),
+        Some(WIRE_VARINT) => {
+            while !data.is_empty() {
+                decode_varint(&mut data).map_err(|_| "truncated packed varint".to_string())?;
+            }
+            Ok(())
+        }
+        _ => Err("packed values have an invalid length".to_string()),
+    }
+}
+
+fn skip(buf: &[u8], len: usize) -> Option<&[u8]> {
+    buf.get(len..)
+}
+
+/// Type name as written in .proto files
+pub(crate) fn type_name(kind: Type) -> &'static str {
+    match kind {
+        Type::Double => "double",
+        Type::Float => "float",
+        Type::Int64 => "int64",
+        Type::Uint64 => "uint64",
+        Type::Int32 => "int32",
+        Type::Fixed64 => "fixed64",
+        Type::Fixed32 => "fixed32",
+        Type::Bool => "bool",
+        Type::String => "string",
+        Type::Group => "group",
+        Type::Message => "message",
+        Type::Bytes => "bytes",
+        Type::Uint32 => "uint32",
+        Type::Enum => "enum",
+        Type::Sfixed32 => "sfixed32",
+        Type::Sfixed64 => "sfixed64",
+        Type::Sint32 => "sint32",
+        Type::Sint64 => "sint64",
+    }
+}
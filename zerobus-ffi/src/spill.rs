@@ -0,0 +1,446 @@
+// On-disk write-ahead buffer a stream persists its records in before queueing them, see spill_dir
+//
+// Records are appended to numbered segment files. A segment is deleted once every record in it
+// has been acknowledged; the segment being written is emptied instead, and deleted when the
+// buffer is dropped. Segments found when a stream is created are replayed into it, so every
+// persisted record is delivered at least once.
+
+use databricks_zerobus_ingest_sdk::EncodedRecord;
+use once_cell::sync::Lazy;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Segments are rotated once they grow past this size
+const SEGMENT_BYTES: u64 = 16 << 20;
+/// Leftover records are replayed in requests of at most this many records and bytes
+const REPLAY_BATCH_RECORDS: usize = 1000;
+const REPLAY_BATCH_BYTES: usize = 4 << 20;
+
+const SEGMENT_MAGIC: &[u8; 8] = b"ZBSPILL1";
+const SEGMENT_EXTENSION: &str = "zbspill";
+const KIND_PROTO: u8 = 1;
+const KIND_JSON: u8 = 2;
+/// Each record is framed by its kind and its little-endian u32 length
+const RECORD_HEADER_BYTES: usize = 5;
+
+/// Spill directories of live buffers, so two streams never write to the same one
+static OPEN_DIRS: Lazy<Mutex<HashSet<PathBuf>>> = Lazy::new(Default::default);
+
+pub(crate) struct SpillBuffer {
+    dir: PathBuf,
+    /// Cap on the size of all segment files, 0 for no cap
+    max_bytes: u64,
+    state: Mutex<SpillState>,
+}
+
+#[derive(Default)]
+struct SpillState {
+    segments: BTreeMap<u64, Segment>,
+    /// Segment new records are appended to
+    active: Option<(u64, File)>,
+    total_bytes: u64,
+    next_segment: u64,
+}
+
+struct Segment {
+    bytes: u64,
+    /// Records not acknowledged yet
+    pending: u64,
+}
+
+/// Records of one request held in the spill buffer, released once the request is acknowledged
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct SpillTicket {
+    segment: u64,
+    records: u64,
+    /// Byte range of the records in the segment, so they can be read back
+    offset: u64,
+    bytes: u64,
+}
+
+/// Persisted records that still have to be queued on a stream
+pub(crate) struct SpilledRecords {
+    pub(crate) ticket: SpillTicket,
+    pub(crate) records: Vec<EncodedRecord>,
+}
+
+#[derive(Debug)]
+pub(crate) enum SpillError {
+    /// The records would grow the spill directory past its size cap
+    Full {
+        max_bytes: u64,
+    },
+    Io(io::Error),
+}
+
+impl fmt::Display for SpillError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpillError::Full { max_bytes } => {
+                write!(f, "Spill directory is full ({} bytes)", max_bytes)
+            }
+            SpillError::Io(err) => write!(f, "Failed to write to the spill directory: {}", err),
+        }
+    }
+}
+
+impl From<io::Error> for SpillError {
+    fn from(err: io::Error) -> Self {
+        SpillError::Io(err)
+    }
+}
+
+impl SpillBuffer {
+    /// Open a spill directory, creating it if needed, and load the records left in it
+    /// The leftovers are returned in the order they were written
+    pub(crate) fn open(dir: &str, max_bytes: u64) -> io::Result<(Self, Vec<SpilledRecords>)> {
+        fs::create_dir_all(dir)?;
+        let dir = fs::canonicalize(dir)?;
+        if !OPEN_DIRS.lock().unwrap().insert(dir.clone()) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "already used by another stream",
+            ));
+        }
+
+        // From here on Drop releases the directory again
+        let buffer = SpillBuffer {
+            dir,
+            max_bytes,
+            state: Mutex::new(SpillState::default()),
+        };
+        let leftovers = buffer.load()?;
+        Ok((buffer, leftovers))
+    }
+
+    fn load(&self) -> io::Result<Vec<SpilledRecords>> {
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            if let Some(id) = segment_id(&entry?.path()) {
+                ids.push(id);
+            }
+        }
+        ids.sort_unstable();
+
+        let mut state = self.state.lock().unwrap();
+        state.next_segment = ids.last().map_or(1, |id| id + 1);
+
+        let mut leftovers = Vec::new();
+        for id in ids {
+            let path = self.segment_path(id);
+            let data = fs::read(&path)?;
+            let records = parse_segment(&data).map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {}", path.display(), err),
+                )
+            })?;
+            if records.is_empty() {
+                fs::remove_file(&path)?;
+                continue;
+            }
+
+            state.segments.insert(
+                id,
+                Segment {
+                    bytes: data.len() as u64,
+                    pending: records.len() as u64,
+                },
+            );
+            state.total_bytes += data.len() as u64;
+            let mut offset = SEGMENT_MAGIC.len() as u64;
+            for chunk in replay_chunks(records) {
+                let bytes = chunk.iter().map(framed_len).sum::<u64>();
+                leftovers.push(SpilledRecords {
+                    ticket: SpillTicket {
+                        segment: id,
+                        records: chunk.len() as u64,
+                        offset,
+                        bytes,
+                    },
+                    records: chunk,
+                });
+                offset += bytes;
+            }
+        }
+        Ok(leftovers)
+    }
+
+    /// Persist the records of one request
+    pub(crate) fn append(&self, records: &[EncodedRecord]) -> Result<SpillTicket, SpillError> {
+        let mut buf = Vec::new();
+        for record in records {
+            encode_record(record, &mut buf)?;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        self.reset_if_acked(&mut state)?;
+
+        let rotate = match &state.active {
+            Some((id, _)) => state.segments[id].bytes >= SEGMENT_BYTES,
+            None => true,
+        };
+        let needed = buf.len() as u64
+            + if rotate {
+                SEGMENT_MAGIC.len() as u64
+            } else {
+                0
+            };
+        if self.max_bytes > 0 && state.total_bytes + needed > self.max_bytes {
+            return Err(SpillError::Full {
+                max_bytes: self.max_bytes,
+            });
+        }
+        if rotate {
+            self.start_segment(&mut state)?;
+        }
+
+        let (id, file) = state.active.as_mut().expect("active segment");
+        let id = *id;
+        if let Err(err) = file.write_all(&buf) {
+            // The segment may now end in a partial record: stop writing to it, the records before
+            // it are still replayed
+            let _ = self.seal_active(&mut state);
+            return Err(err.into());
+        }
+
+        let segment = state.segments.get_mut(&id).expect("active segment");
+        let offset = segment.bytes;
+        segment.bytes += buf.len() as u64;
+        segment.pending += records.len() as u64;
+        state.total_bytes += buf.len() as u64;
+        Ok(SpillTicket {
+            segment: id,
+            records: records.len() as u64,
+            offset,
+            bytes: buf.len() as u64,
+        })
+    }
+
+    /// Read back the records of a ticket that has not been released yet
+    pub(crate) fn read(&self, ticket: &SpillTicket) -> io::Result<Vec<EncodedRecord>> {
+        let mut file = File::open(self.segment_path(ticket.segment))?;
+        file.seek(SeekFrom::Start(ticket.offset))?;
+        let mut data = vec![0; ticket.bytes as usize];
+        file.read_exact(&mut data)?;
+        parse_records(&data).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Forget records that have been acknowledged, deleting segments that no longer hold any
+    pub(crate) fn release(&self, ticket: SpillTicket) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(segment) = state.segments.get_mut(&ticket.segment) {
+            segment.pending = segment.pending.saturating_sub(ticket.records);
+        }
+        self.remove_if_acked(&mut state, ticket.segment);
+        if let Err(err) = self.reset_if_acked(&mut state) {
+            tracing::warn!(
+                segment = ticket.segment,
+                "Failed to empty acknowledged spill segment: {}",
+                err
+            );
+        }
+    }
+
+    /// Flush the segment being written to disk
+    pub(crate) fn sync(&self) -> io::Result<()> {
+        match &self.state.lock().unwrap().active {
+            Some((_, file)) => file.sync_data(),
+            None => Ok(()),
+        }
+    }
+
+    fn segment_path(&self, id: u64) -> PathBuf {
+        self.dir
+            .join(format!("segment-{:020}.{}", id, SEGMENT_EXTENSION))
+    }
+
+    fn start_segment(&self, state: &mut SpillState) -> io::Result<()> {
+        self.seal_active(state)?;
+
+        let id = state.next_segment;
+        let mut file = OpenOptions::new()
+            .append(true)
+            .create_new(true)
+            .open(self.segment_path(id))?;
+        file.write_all(SEGMENT_MAGIC)?;
+
+        state.next_segment += 1;
+        state.segments.insert(
+            id,
+            Segment {
+                bytes: SEGMENT_MAGIC.len() as u64,
+                pending: 0,
+            },
+        );
+        state.total_bytes += SEGMENT_MAGIC.len() as u64;
+        state.active = Some((id, file));
+        Ok(())
+    }
+
+    fn seal_active(&self, state: &mut SpillState) -> io::Result<()> {
+        let Some((id, file)) = state.active.take() else {
+            return Ok(());
+        };
+        let res = file.sync_data();
+        self.remove_if_acked(state, id);
+        res
+    }
+
+    /// Empty the segment being written once all of its records are acknowledged, so a healthy
+    /// stream does not keep acknowledged records on disk until the segment is rotated
+    fn reset_if_acked(&self, state: &mut SpillState) -> io::Result<()> {
+        let header = SEGMENT_MAGIC.len() as u64;
+        let Some((id, file)) = &state.active else {
+            return Ok(());
+        };
+        let segment = state.segments.get_mut(id).expect("active segment");
+        if segment.pending == 0 && segment.bytes > header {
+            file.set_len(header)?;
+            state.total_bytes -= segment.bytes - header;
+            segment.bytes = header;
+        }
+        Ok(())
+    }
+
+    fn remove_if_acked(&self, state: &mut SpillState, id: u64) {
+        let active = state.active.as_ref().map(|(active, _)| *active);
+        let acked = state
+            .segments
+            .get(&id)
+            .is_some_and(|segment| segment.pending == 0);
+        if active == Some(id) || !acked {
+            return;
+        }
+
+        if let Some(segment) = state.segments.remove(&id) {
+            state.total_bytes -= segment.bytes;
+        }
+        if let Err(err) = fs::remove_file(self.segment_path(id)) {
+            tracing::warn!(
+                segment = id,
+                "Failed to delete acknowledged spill segment: {}",
+                err
+            );
+        }
+    }
+}
+
+impl Drop for SpillBuffer {
+    fn drop(&mut self) {
+        // A fully acknowledged segment would only be skipped by the next open
+        let state = self.state.get_mut().unwrap_or_else(|err| err.into_inner());
+        let acked = state.active.as_ref().map(|(id, _)| *id).filter(|id| {
+            state
+                .segments
+                .get(id)
+                .is_some_and(|segment| segment.pending == 0)
+        });
+        if let Some(id) = acked {
+            let _ = fs::remove_file(self.segment_path(id));
+        }
+        OPEN_DIRS.lock().unwrap().remove(&self.dir);
+    }
+}
+
+/// Sequence number of a segment file, None for other files
+fn segment_id(path: &Path) -> Option<u64> {
+    if path.extension()? != SEGMENT_EXTENSION {
+        return None;
+    }
+    path.file_stem()?
+        .to_str()?
+        .strip_prefix("segment-")?
+        .parse()
+        .ok()
+}
+
+fn encode_record(record: &EncodedRecord, buf: &mut Vec<u8>) -> io::Result<()> {
+    let (kind, payload) = match record {
+        EncodedRecord::Proto(bytes) => (KIND_PROTO, bytes.as_slice()),
+        EncodedRecord::Json(json) => (KIND_JSON, json.as_bytes()),
+    };
+    let len = u32::try_from(payload.len()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "record too large for the spill buffer",
+        )
+    })?;
+    buf.push(kind);
+    buf.extend_from_slice(&len.to_le_bytes());
+    buf.extend_from_slice(payload);
+    Ok(())
+}
+
+/// Decode the records of a segment
+/// A partial record at the end, left by a crash or failed write, is ignored
+pub(crate) fn parse_segment(data: &[u8]) -> Result<Vec<EncodedRecord>, String> {
+    let Some(rest) = data.strip_prefix(SEGMENT_MAGIC.as_slice()) else {
+        // A crash while creating the segment can leave a partial header
+        if SEGMENT_MAGIC.starts_with(data) {
+            return Ok(Vec::new());
+        }
+        return Err("not a spill segment".to_string());
+    };
+    parse_records(rest)
+}
+
+/// Decode a sequence of framed records, ignoring a partial record at the end
+fn parse_records(mut rest: &[u8]) -> Result<Vec<EncodedRecord>, String> {
+    let mut records = Vec::new();
+    while rest.len() >= RECORD_HEADER_BYTES {
+        let kind = rest[0];
+        let len = u32::from_le_bytes([rest[1], rest[2], rest[3], rest[4]]) as usize;
+        let Some(payload) = rest.get(RECORD_HEADER_BYTES..RECORD_HEADER_BYTES + len) else {
+            break;
+        };
+        records.push(match kind {
+            KIND_PROTO => EncodedRecord::Proto(payload.to_vec()),
+            KIND_JSON => EncodedRecord::Json(
+                String::from_utf8(payload.to_vec())
+                    .map_err(|_| "invalid UTF-8 in a JSON record".to_string())?,
+            ),
+            _ => return Err(format!("unknown record kind {}", kind)),
+        });
+        rest = &rest[RECORD_HEADER_BYTES + len..];
+    }
+    Ok(records)
+}
+
+/// Size of a record in a segment, including its header
+fn framed_len(record: &EncodedRecord) -> u64 {
+    let len = match record {
+        EncodedRecord::Proto(bytes) => bytes.len(),
+        EncodedRecord::Json(json) => json.len(),
+    };
+    (RECORD_HEADER_BYTES + len) as u64
+}
+
+/// Split leftover records into requests of a reasonable size
+fn replay_chunks(records: Vec<EncodedRecord>) -> Vec<Vec<EncodedRecord>> {
+    let mut chunks = Vec::new();
+    let mut chunk = Vec::new();
+    let mut chunk_bytes = 0;
+    for record in records {
+        let len = match &record {
+            EncodedRecord::Proto(bytes) => bytes.len(),
+            EncodedRecord::Json(json) => json.len(),
+        };
+        if !chunk.is_empty()
+            && (chunk.len() >= REPLAY_BATCH_RECORDS || chunk_bytes + len > REPLAY_BATCH_BYTES)
+        {
+            chunks.push(std::mem::take(&mut chunk));
+            chunk_bytes = 0;
+        }
+        chunk_bytes += len;
+        chunk.push(record);
+    }
+    if !chunk.is_empty() {
+        chunks.push(chunk);
+    }
+    chunks
+}
@@ -0,0 +1,738 @@
+// Delta table schemas described as JSON column lists, used to check JSON records before enqueueing
+// and to derive the DescriptorProto of a table's proto records
+
+use prost_types::field_descriptor_proto::{Label, Type};
+use prost_types::{DescriptorProto, FieldDescriptorProto, MessageOptions};
+use serde_json::Value;
+use std::fmt;
+
+use crate::json_transcode::json_kind;
+
+/// Data type of a column in Delta / Unity Catalog terms
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum DataType {
+    Boolean,
+    TinyInt,
+    SmallInt,
+    Int,
+    BigInt,
+    Float,
+    Double,
+    Decimal { precision: u32, scale: u32 },
+    String,
+    Binary,
+    Date,
+    Timestamp,
+    TimestampNtz,
+    Variant,
+    Array(Box<DataType>),
+    Map(Box<DataType>, Box<DataType>),
+    Struct(Vec<Column>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Column {
+    pub(crate) name: String,
+    pub(crate) data_type: DataType,
+    pub(crate) nullable: bool,
+}
+
+/// Columns of a table, in declaration order
+#[derive(Debug)]
+pub(crate) struct TableSchema {
+    pub(crate) columns: Vec<Column>,
+}
+
+impl TableSchema {
+    /// Parse a column list
+    ///
+    /// Accepts either a JSON array of columns or an object with a "columns" array (the shape of
+    /// Unity Catalog's table info). Each column has a "name", a "type" (or "type_text") in Delta
+    /// SQL syntax, e.g. "BIGINT", "ARRAY<STRING>", "STRUCT<city: STRING, zip: INT NOT NULL>" or
+    /// "MAP<STRING, DOUBLE>", and an optional "nullable" flag defaulting to true.
+    pub(crate) fn parse(json: &str) -> Result<Self, String> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|e| format!("invalid table schema: {}", e))?;
+        let columns = match &value {
+            Value::Array(columns) => columns,
+            Value::Object(object) => match object.get("columns") {
+                Some(Value::Array(columns)) => columns,
+                _ => return Err("table schema has no \"columns\" array".to_string()),
+            },
+            _ => return Err("table schema must be a JSON array of columns".to_string()),
+        };
+
+        let columns = columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                parse_column(column).map_err(|e| format!("invalid column {}: {}", i, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if columns.is_empty() {
+            return Err("table schema has no columns".to_string());
+        }
+        check_unique(&columns)?;
+        Ok(TableSchema { columns })
+    }
+
+    /// Describe a record of the table as a self-contained proto2 message named `message_name`
+    ///
+    /// Columns become fields numbered from 1 in declaration order, `optional` when nullable and
+    /// `required` otherwise. Types map as follows:
+    ///
+    /// | Delta type                      | Protobuf type                                       |
+    /// |---------------------------------|-----------------------------------------------------|
+    /// | BOOLEAN                         | bool                                                |
+    /// | TINYINT, SMALLINT, INT          | int32                                               |
+    /// | BIGINT                          | int64                                               |
+    /// | FLOAT / DOUBLE                  | float / double                                      |
+    /// | DECIMAL(p,s)                    | string                                              |
+    /// | STRING, VARCHAR(n), CHAR(n)     | string                                              |
+    /// | BINARY                          | bytes                                               |
+    /// | DATE                            | int32 (days since 1970-01-01)                       |
+    /// | TIMESTAMP, TIMESTAMP_NTZ        | int64 (microseconds since the epoch)                |
+    /// | VARIANT                         | string (JSON)                                       |
+    /// | STRUCT<...>                     | nested message named after the column in PascalCase |
+    /// | ARRAY<T>                        | repeated T                                          |
+    /// | MAP<K, V>                       | map<K, V> (K must be an integer, string or bool)    |
+    ///
+    /// Arrays of arrays or maps and maps with array or map values have no protobuf equivalent
+    /// and are rejected, as are column names that are not valid protobuf identifiers.
+    pub(crate) fn to_descriptor(&self, message_name: &str) -> Result<DescriptorProto, String> {
+        if !is_proto_identifier(message_name) {
+            return Err(format!(
+                "'{}' is not a valid protobuf message name",
+                message_name
+            ));
+        }
+        struct_message(
+            message_name,
+            &format!(".{}", message_name),
+            &self.columns,
+            "",
+        )
+    }
+
+    /// Check a JSON record against the columns
+    /// Errors name the offending column by its dotted path, e.g. "column 'address.zip': ..."
+    pub(crate) fn validate(&self, record: &Value) -> Result<(), String> {
+        validate_struct(&self.columns, record, "")
+    }
+}
+
+fn parse_column(column: &Value) -> Result<Column, String> {
+    let Value::Object(column) = column else {
+        return Err("expected a JSON object".to_string());
+    };
+    let name = match column.get("name") {
+        Some(Value::String(name)) if !name.is_empty() => name.clone(),
+        _ => return Err("missing \"name\"".to_string()),
+    };
+    let type_text = match column.get("type").or_else(|| column.get("type_text")) {
+        Some(Value::String(type_text)) => type_text,
+        _ => return Err(format!("column '{}' has no \"type\"", name)),
+    };
+    let nullable = match column.get("nullable") {
+        None | Some(Value::Null) => true,
+        Some(Value::Bool(nullable)) => *nullable,
+        Some(_) => return Err(format!("\"nullable\" of column '{}' must be a bool", name)),
+    };
+    let data_type = DataType::parse(type_text).map_err(|e| format!("column '{}': {}", name, e))?;
+    Ok(Column {
+        name,
+        data_type,
+        nullable,
+    })
+}
+
+/// Delta column names are case-insensitive, so "id" and "ID" collide
+fn check_unique(columns: &[Column]) -> Result<(), String> {
+    for (i, column) in columns.iter().enumerate() {
+        if columns[..i]
+            .iter()
+            .any(|other| other.name.eq_ignore_ascii_case(&column.name))
+        {
+            return Err(format!("duplicate column '{}'", column.name));
+        }
+    }
+    Ok(())
+}
+
+impl DataType {
+    /// Parse a type in Delta SQL syntax (case-insensitive)
+    pub(crate) fn parse(type_text: &str) -> Result<Self, String> {
+        let mut parser = TypeParser {
+            input: type_text,
+            pos: 0,
+        };
+        let data_type = parser.parse_type()?;
+        parser.skip_whitespace();
+        if parser.pos != type_text.len() {
+            return Err(format!(
+                "unexpected \"{}\" in type {}",
+                &type_text[parser.pos..],
+                type_text
+            ));
+        }
+        Ok(data_type)
+    }
+}
+
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataType::Boolean => write!(f, "BOOLEAN"),
+            DataType::TinyInt => write!(f, "TINYINT"),
+            DataType::SmallInt => write!(f, "SMALLINT"),
+            DataType::Int => write!(f, "INT"),
+            DataType::BigInt => write!(f, "BIGINT"),
+            DataType::Float => write!(f, "FLOAT"),
+            DataType::Double => write!(f, "DOUBLE"),
+            DataType::Decimal { precision, scale } => write!(f, "DECIMAL({},{})", precision, scale),
+            DataType::String => write!(f, "STRING"),
+            DataType::Binary => write!(f, "BINARY"),
+            DataType::Date => write!(f, "DATE"),
+            DataType::Timestamp => write!(f, "TIMESTAMP"),
+            DataType::TimestampNtz => write!(f, "TIMESTAMP_NTZ"),
+            DataType::Variant => write!(f, "VARIANT"),
+            DataType::Array(element) => write!(f, "ARRAY<{}>", element),
+            DataType::Map(key, value) => write!(f, "MAP<{}, {}>", key, value),
+            DataType::Struct(fields) => {
+                write!(f, "STRUCT<")?;
+                for (i, field) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", field.name, field.data_type)?;
+                    if !field.nullable {
+                        write!(f, " NOT NULL")?;
+                    }
+                }
+                write!(f, ">")
+            }
+        }
+    }
+}
+
+/// Recursive descent parser for Delta SQL types
+struct TypeParser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl TypeParser<'_> {
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consume `c` (after whitespace) if it is next
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.input[self.pos..].starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(format!(
+                "expected '{}' at position {} of {}",
+                c, self.pos, self.input
+            ))
+        }
+    }
+
+    /// An identifier, or a backtick-quoted name
+    fn ident(&mut self) -> Result<String, String> {
+        self.skip_whitespace();
+        let rest = &self.input[self.pos..];
+        if let Some(quoted) = rest.strip_prefix('`') {
+            let end = quoted
+                .find('`')
+                .ok_or_else(|| format!("unterminated quoted name in {}", self.input))?;
+            self.pos += end + 2;
+            return Ok(quoted[..end].to_string());
+        }
+
+        let end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        if end == 0 {
+            return Err(format!(
+                "expected a name at position {} of {}",
+                self.pos, self.input
+            ));
+        }
+        self.pos += end;
+        Ok(rest[..end].to_string())
+    }
+
+    fn number(&mut self) -> Result<u32, String> {
+        let ident = self.ident()?;
+        ident
+            .parse()
+            .map_err(|_| format!("expected a number, got {} in {}", ident, self.input))
+    }
+
+    fn parse_type(&mut self) -> Result<DataType, String> {
+        let name = self.ident()?.to_ascii_uppercase();
+        let data_type = match name.as_str() {
+            "BOOLEAN" => DataType::Boolean,
+            "TINYINT" | "BYTE" => DataType::TinyInt,
+            "SMALLINT" | "SHORT" => DataType::SmallInt,
+            "INT" | "INTEGER" => DataType::Int,
+            "BIGINT" | "LONG" => DataType::BigInt,
+            "FLOAT" | "REAL" => DataType::Float,
+            "DOUBLE" => DataType::Double,
+            "DECIMAL" | "DEC" | "NUMERIC" => {
+                let (mut precision, mut scale) = (10, 0);
+                if self.eat('(') {
+                    precision = self.number()?;
+                    if self.eat(',') {
+                        scale = self.number()?;
+                    }
+                    self.expect(')')?;
+                }
+                DataType::Decimal { precision, scale }
+            }
+            "STRING" => DataType::String,
+            "VARCHAR" | "CHAR" => {
+                self.expect('(')?;
+                self.number()?;
+                self.expect(')')?;
+                DataType::String
+            }
+            "BINARY" => DataType::Binary,
+            "DATE" => DataType::Date,
+            "TIMESTAMP" => DataType::Timestamp,
+            "TIMESTAMP_NTZ" => DataType::TimestampNtz,
+            "VARIANT" => DataType::Variant,
+            "ARRAY" => {
+                self.expect('<')?;
+                let element = self.parse_type()?;
+                self.expect('>')?;
+                DataType::Array(Box::new(element))
+            }
+            "MAP" => {
+                self.expect('<')?;
+                let key = self.parse_type()?;
+                self.expect(',')?;
+                let value = self.parse_type()?;
+                self.expect('>')?;
+                DataType::Map(Box::new(key), Box::new(value))
+            }
+            "STRUCT" => {
+                self.expect('<')?;
+                let mut fields = Vec::new();
+                if !self.eat('>') {
+                    loop {
+                        fields.push(self.parse_field()?);
+                        if self.eat('>') {
+                            break;
+                        }
+                        self.expect(',')?;
+                    }
+                }
+                check_unique(&fields)?;
+                DataType::Struct(fields)
+            }
+            _ => return Err(format!("unsupported type {}", name)),
+        };
+        Ok(data_type)
+    }
+
+    /// A struct field: `name[:] type [NOT NULL]`
+    fn parse_field(&mut self) -> Result<Column, String> {
+        let name = self.ident()?;
+        self.eat(':');
+        let data_type = self.parse_type()?;
+
+        let checkpoint = self.pos;
+        let mut nullable = true;
+        if self
+            .ident()
+            .is_ok_and(|word| word.eq_ignore_ascii_case("NOT"))
+        {
+            if !self.ident()?.eq_ignore_ascii_case("NULL") {
+                return Err(format!("expected NOT NULL in {}", self.input));
+            }
+            nullable = false;
+        } else {
+            self.pos = checkpoint;
+        }
+        Ok(Column {
+            name,
+            data_type,
+            nullable,
+        })
+    }
+}
+
+fn validate_struct(columns: &[Column], value: &Value, path: &str) -> Result<(), String> {
+    let Value::Object(object) = value else {
+        return Err(if path.is_empty() {
+            format!("expected a JSON object, got {}", json_kind(value))
+        } else {
+            format!(
+                "column '{}': expected a JSON object, got {}",
+                path,
+                json_kind(value)
+            )
+        });
+    };
+    let column_path = |name: &str| {
+        if path.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", path, name)
+        }
+    };
+
+    if let Some(key) = object.keys().find(|key| {
+        !columns
+            .iter()
+            .any(|column| column.name.eq_ignore_ascii_case(key))
+    }) {
+        return Err(format!("unknown column '{}'", column_path(key)));
+    }
+
+    for column in columns {
+        let value = object
+            .iter()
+            .find(|(key, _)| column.name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value);
+        match value {
+            None | Some(Value::Null) if !column.nullable => {
+                return Err(format!(
+                    "column '{}': must not be null",
+                    column_path(&column.name)
+                ));
+            }
+            None | Some(Value::Null) => {}
+            Some(value) => validate_value(&column.data_type, value, &column_path(&column.name))?,
+        }
+    }
+    Ok(())
+}
+
+fn validate_value(data_type: &DataType, value: &Value, path: &str) -> Result<(), String> {
+    let integer_in = |min: i64, max: i64| match value {
+        Value::Number(n) => n.as_i64().is_some_and(|n| (min..=max).contains(&n)),
+        _ => false,
+    };
+    let valid = match data_type {
+        DataType::Boolean => value.is_boolean(),
+        DataType::TinyInt => integer_in(i8::MIN.into(), i8::MAX.into()),
+        DataType::SmallInt => integer_in(i16::MIN.into(), i16::MAX.into()),
+        DataType::Int => integer_in(i32::MIN.into(), i32::MAX.into()),
+        DataType::BigInt => integer_in(i64::MIN, i64::MAX),
+        DataType::Float | DataType::Double => {
+            value.is_number() || matches!(value.as_str(), Some("NaN" | "Infinity" | "-Infinity"))
+        }
+        DataType::Decimal { .. } => {
+            value.is_number() || value.as_str().is_some_and(|s| s.parse::<f64>().is_ok())
+        }
+        DataType::String | DataType::Binary => value.is_string(),
+        // Dates and timestamps may be given as strings or as days / microseconds since the epoch
+        DataType::Date | DataType::Timestamp | DataType::TimestampNtz => {
+            value.is_string() || value.is_i64()
+        }
+        DataType::Variant => true,
+        DataType::Array(element) => match value {
+            Value::Array(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if !item.is_null() {
+                        validate_value(element, item, &format!("{}[{}]", path, i))?;
+                    }
+                }
+                true
+            }
+            _ => false,
+        },
+        DataType::Map(_, value_type) => match value {
+            Value::Object(entries) => {
+                for (key, item) in entries {
+                    if !item.is_null() {
+                        validate_value(value_type, item, &format!("{}[{}]", path, key))?;
+                    }
+                }
+                true
+            }
+            _ => false,
+        },
+        DataType::Struct(fields) => return validate_struct(fields, value, path),
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "column '{}': expected {}, got {}",
+            path,
+            data_type,
+            json_kind(value)
+        ))
+    }
+}
+
+/// Check that a JSON record is a JSON object and, given a table schema, that it matches it
+pub(crate) fn validate_json_record(json: &str, schema: Option<&TableSchema>) -> Result<(), String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| format!("invalid JSON: {}", e))?;
+    match schema {
+        Some(schema) => schema.validate(&value),
+        None if value.is_object() => Ok(()),
+        None => Err(format!("expected a JSON object, got {}", json_kind(&value))),
+    }
+}
+
+fn is_proto_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Message type name derived from a column name, e.g. "shipping_address" -> "ShippingAddress"
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Protobuf type of a column that maps to a scalar field
+fn scalar_type(data_type: &DataType) -> Option<Type> {
+    match data_type {
+        DataType::Boolean => Some(Type::Bool),
+        DataType::TinyInt | DataType::SmallInt | DataType::Int | DataType::Date => {
+            Some(Type::Int32)
+        }
+        DataType::BigInt | DataType::Timestamp | DataType::TimestampNtz => Some(Type::Int64),
+        DataType::Float => Some(Type::Float),
+        DataType::Double => Some(Type::Double),
+        DataType::Decimal { .. } | DataType::String | DataType::Variant => Some(Type::String),
+        DataType::Binary => Some(Type::Bytes),
+        DataType::Array(_) | DataType::Map(_, _) | DataType::Struct(_) => None,
+    }
+}
+
+/// Build the message of a struct; `full_name` is its fully qualified name, `path` the column path
+fn struct_message(
+    name: &str,
+    full_name: &str,
+    columns: &[Column],
+    path: &str,
+) -> Result<DescriptorProto, String> {
+    let mut message = DescriptorProto {
+        name: Some(name.to_string()),
+        ..Default::default()
+    };
+
+    for (number, column) in (1..).zip(columns) {
+        let column_path = if path.is_empty() {
+            column.name.clone()
+        } else {
+            format!("{}.{}", path, column.name)
+        };
+        if !is_proto_identifier(&column.name) {
+            return Err(format!(
+                "column '{}': name is not a valid protobuf field name",
+                column_path
+            ));
+        }
+        let label = if column.nullable {
+            Label::Optional
+        } else {
+            Label::Required
+        };
+        let type_name = pascal_case(&column.name);
+
+        let field = match &column.data_type {
+            DataType::Array(element) => {
+                if matches!(**element, DataType::Array(_) | DataType::Map(_, _)) {
+                    return Err(format!(
+                        "column '{}': {} has no protobuf equivalent",
+                        column_path, column.data_type
+                    ));
+                }
+                let field = FieldSpec {
+                    name: &column.name,
+                    number,
+                    label: Label::Repeated,
+                    type_name: &type_name,
+                    path: &column_path,
+                };
+                field.build(element, &mut message, full_name)?
+            }
+            DataType::Map(key, value) => map_field(
+                &mut message,
+                full_name,
+                column,
+                number,
+                key,
+                value,
+                &column_path,
+            )?,
+            data_type => {
+                let field = FieldSpec {
+                    name: &column.name,
+                    number,
+                    label,
+                    type_name: &type_name,
+                    path: &column_path,
+                };
+                field.build(data_type, &mut message, full_name)?
+            }
+        };
+        message.field.push(field);
+    }
+    Ok(message)
+}
+
+/// A field to add to a message; struct types become a nested message named `type_name`
+struct FieldSpec<'a> {
+    name: &'a str,
+    number: i32,
+    label: Label,
+    type_name: &'a str,
+    path: &'a str,
+}
+
+impl FieldSpec<'_> {
+    fn build(
+        &self,
+        data_type: &DataType,
+        parent: &mut DescriptorProto,
+        parent_name: &str,
+    ) -> Result<FieldDescriptorProto, String> {
+        let mut field = FieldDescriptorProto {
+            name: Some(self.name.to_string()),
+            number: Some(self.number),
+            label: Some(self.label as i32),
+            ..Default::default()
+        };
+
+        match (scalar_type(data_type), data_type) {
+            (Some(kind), _) => field.r#type = Some(kind as i32),
+            (None, DataType::Struct(columns)) => {
+                let full_name = format!("{}.{}", parent_name, self.type_name);
+                add_nested(
+                    parent,
+                    struct_message(self.type_name, &full_name, columns, self.path)?,
+                    self.path,
+                )?;
+                field.r#type = Some(Type::Message as i32);
+                field.type_name = Some(full_name);
+            }
+            (None, _) => {
+                return Err(format!(
+                    "column '{}': {} has no protobuf equivalent",
+                    self.path, data_type
+                ))
+            }
+        }
+        Ok(field)
+    }
+}
+
+fn add_nested(
+    parent: &mut DescriptorProto,
+    nested: DescriptorProto,
+    path: &str,
+) -> Result<(), String> {
+    if parent
+        .nested_type
+        .iter()
+        .any(|other| other.name() == nested.name())
+    {
+        return Err(format!(
+            "column '{}': message name {} is already used by another column",
+            path,
+            nested.name()
+        ));
+    }
+    parent.nested_type.push(nested);
+    Ok(())
+}
+
+/// A map column: a repeated field of a synthetic `<Column>Entry` message, as protoc generates it
+fn map_field(
+    parent: &mut DescriptorProto,
+    parent_name: &str,
+    column: &Column,
+    number: i32,
+    key: &DataType,
+    value: &DataType,
+    path: &str,
+) -> Result<FieldDescriptorProto, String> {
+    let valid_key = matches!(
+        key,
+        DataType::TinyInt
+            | DataType::SmallInt
+            | DataType::Int
+            | DataType::BigInt
+            | DataType::String
+            | DataType::Boolean
+    );
+    if !valid_key || matches!(value, DataType::Array(_) | DataType::Map(_, _)) {
+        return Err(format!(
+            "column '{}': {} has no protobuf equivalent",
+            path, column.data_type
+        ));
+    }
+
+    let type_name = pascal_case(&column.name);
+    let entry_name = format!("{}Entry", type_name);
+    let entry_full_name = format!("{}.{}", parent_name, entry_name);
+    let mut entry = DescriptorProto {
+        name: Some(entry_name),
+        options: Some(MessageOptions {
+            map_entry: Some(true),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let spec = |name, number| FieldSpec {
+        name,
+        number,
+        label: Label::Optional,
+        // Map entries cannot declare nested types, so struct values live next to the entry
+        type_name: "",
+        path,
+    };
+    entry
+        .field
+        .push(spec("key", 1).build(key, parent, parent_name)?);
+    let value_type_name = format!("{}Value", type_name);
+    let value_spec = FieldSpec {
+        type_name: &value_type_name,
+        ..spec("value", 2)
+    };
+    entry
+        .field
+        .push(value_spec.build(value, parent, parent_name)?);
+    add_nested(parent, entry, path)?;
+
+    Ok(FieldDescriptorProto {
+        name: Some(column.name.clone()),
+        number: Some(number),
+        label: Some(Label::Repeated as i32),
+        r#type: Some(Type::Message as i32),
+        type_name: Some(entry_full_name),
+        ..Default::default()
+    })
+}
@@ -1,14 +1,62 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
+    use crate::dead_letter::{dead_letter_line, DeadLetterSink};
+    use crate::descriptor::flatten_descriptor_set;
+    use crate::logging::{
+        zerobus_set_log_callback, CLogField, CLogLevel, CallbackSubscriber, LogSink, LOG_SINK,
+    };
+    use crate::proto_schema::ProtoSchema;
+    use crate::spill::{parse_segment, SpillBuffer, SpillError};
+    use crate::table_schema::{validate_json_record, Column, DataType, TableSchema};
     use crate::{
-        intern_header_key, validate_sdk_ptr, validate_stream_ptr, write_error_result,
-        write_success_result, zerobus_free_error_message, zerobus_get_default_config, CHeaders,
-        CResult, CStreamConfigurationOptions, CallbackHeadersProvider, RecordType,
-        StreamConfigurationOptions, ZerobusError,
+        await_registered_ack, build_runtime, deliver_ack, intern_header_key, open_dead_letter,
+        open_spill, record_schemas, runtime, split_batch_buffer, unacked_batches_to_c,
+        validate_sdk_ptr, validate_stream_options, validate_stream_ptr, write_coded_error_result,
+        write_ingest_result, write_success_result, zerobus_descriptor_proto_from_table_schema,
+        zerobus_free_ack_id_mappings, zerobus_free_bytes, zerobus_free_error_message,
+        zerobus_free_unacked_records, zerobus_get_default_config, zerobus_record_buffer_alloc,
+        zerobus_record_buffer_free, zerobus_runtime_init,
+        zerobus_sdk_create_stream_with_headers_provider, zerobus_sdk_free, zerobus_sdk_new,
+        zerobus_sdk_recreate_stream, zerobus_sdk_set_use_tls, zerobus_stream_await_ack,
+        zerobus_stream_await_ack_timeout, zerobus_stream_await_acks, zerobus_stream_await_any_ack,
+        zerobus_stream_close, zerobus_stream_free, zerobus_stream_get_metrics,
+        zerobus_stream_get_outstanding_acks, zerobus_stream_get_unacked_records,
+        zerobus_stream_ingest_json_batch, zerobus_stream_ingest_json_batch_timeout,
+        zerobus_stream_ingest_json_record_bytes, zerobus_stream_ingest_json_record_bytes_timeout,
+        zerobus_stream_ingest_proto_batch, zerobus_stream_ingest_proto_batch_timeout,
+        zerobus_stream_ingest_proto_record, zerobus_stream_ingest_proto_record_buffer,
+        zerobus_stream_ingest_proto_record_buffer_timeout,
+        zerobus_stream_ingest_proto_record_timeout, zerobus_stream_try_ingest_json_batch,
+        zerobus_stream_try_ingest_json_record_bytes, zerobus_stream_try_ingest_proto_batch,
+        zerobus_stream_try_ingest_proto_record, zerobus_stream_try_ingest_proto_record_buffer,
+        AckCallbackHandle, AckRegistry, CAckIdMapping, CAckIdMappings, CErrorCode, CHeaders,
+        CResult, CRuntimeConfig, CStreamConfigurationOptions, CStreamMetrics, CUnackedRecords,
+        CZerobusSdk, CZerobusStream, CallbackHeadersProvider, CallbackTracker, EncodedRecord,
+        InflightRequests, IngestError, RecordType, StreamConfigurationOptions, StreamMetrics,
+        ZerobusError,
+    };
+    use databricks_zerobus_ingest_sdk::databricks::zerobus::{
+        ephemeral_stream_request::Payload as RequestPayload,
+        ephemeral_stream_response::Payload as ResponsePayload,
+        ingest_record_batch_request::Batch,
+        zerobus_server::{Zerobus, ZerobusServer},
+        CreateIngestStreamResponse, EphemeralStreamRequest, EphemeralStreamResponse,
+        IngestRecordBatchRequest, IngestRecordRequest, IngestRecordResponse,
     };
     use databricks_zerobus_ingest_sdk::HeadersProvider;
+    use prost::Message;
+    use prost_types::{
+        DescriptorProto, EnumDescriptorProto, EnumValueDescriptorProto, FieldDescriptorProto,
+        FileDescriptorProto, FileDescriptorSet, MessageOptions,
+    };
     use std::ffi::{CStr, CString};
     use std::ptr;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+    use tonic::transport::Server;
+    use tonic::{Request, Response, Status, Streaming};
 
     // Helper for c_str_to_string since it's private
     unsafe fn test_c_str_to_string(
@@ -34,6 +82,11 @@ mod tests {
         assert_eq!(result.err().unwrap(), "SDK pointer is null");
     }
 
+    #[test]
+    fn test_outstanding_acks_null_stream() {
+        assert_eq!(zerobus_stream_get_outstanding_acks(ptr::null_mut()), 0);
+    }
+
     #[test]
     fn test_validate_stream_ptr_null() {
         let result = validate_stream_ptr(ptr::null_mut());
@@ -47,13 +100,20 @@ mod tests {
             success: true,
             error_message: ptr::null_mut(),
             is_retryable: false,
+            error_code: CErrorCode::Ok,
         };
 
-        write_error_result(&mut result as *mut CResult, "Test error", true);
+        write_coded_error_result(
+            &mut result as *mut CResult,
+            "Test error",
+            CErrorCode::InvalidState,
+            true,
+        );
 
         assert!(!result.success);
         assert!(!result.error_message.is_null());
         assert!(result.is_retryable);
+        assert_eq!(result.error_code, CErrorCode::InvalidState);
 
         // Clean up
         unsafe {
@@ -65,10 +125,12 @@ mod tests {
 
     #[test]
     fn test_write_success_result() {
+        let error_message = CString::new("error").unwrap().into_raw();
         let mut result = CResult {
             success: false,
-            error_message: CString::new("error").unwrap().into_raw(),
+            error_message,
             is_retryable: true,
+            error_code: CErrorCode::Internal,
         };
 
         write_success_result(&mut result as *mut CResult);
@@ -76,12 +138,18 @@ mod tests {
         assert!(result.success);
         assert!(result.error_message.is_null());
         assert!(!result.is_retryable);
+        assert_eq!(result.error_code, CErrorCode::Ok);
+
+        // Clean up
+        unsafe {
+            let _ = CString::from_raw(error_message);
+        }
     }
 
     #[test]
     fn test_write_error_result_with_null_pointer() {
         // Should not panic when result pointer is null
-        write_error_result(ptr::null_mut(), "Test error", false);
+        write_coded_error_result(ptr::null_mut(), "Test error", CErrorCode::Internal, false);
         // If we get here, test passed
     }
 
@@ -142,6 +210,7 @@ mod tests {
         assert!(result.success);
         assert!(result.error_message.is_null());
         assert!(!result.is_retryable);
+        assert_eq!(result.error_code, CErrorCode::Ok);
     }
 
     #[test]
@@ -155,6 +224,7 @@ mod tests {
         // Verify error message
         let msg = unsafe { CStr::from_ptr(result.error_message).to_string_lossy() };
         assert!(msg.contains("Test error"));
+        assert_eq!(result.error_code, CErrorCode::InvalidArgument);
 
         // Clean up
         unsafe {
@@ -162,6 +232,85 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cresult_error_keeps_retryability() {
+        let result = CResult::error(ZerobusError::ChannelCreationError(
+            "connection refused".to_string(),
+        ));
+
+        assert!(!result.success);
+        assert!(result.is_retryable);
+        assert_eq!(result.error_code, CErrorCode::ChannelError);
+
+        zerobus_free_error_message(result.error_message);
+    }
+
+    #[test]
+    fn test_error_code_mapping() {
+        let cases = [
+            (
+                ZerobusError::ChannelCreationError("x".to_string()),
+                CErrorCode::ChannelError,
+            ),
+            (
+                ZerobusError::FailedToEstablishTlsConnectionError,
+                CErrorCode::TlsError,
+            ),
+            (
+                ZerobusError::InvalidZerobusEndpointError("x".to_string()),
+                CErrorCode::InvalidEndpoint,
+            ),
+            (
+                ZerobusError::InvalidUCEndpointError("x".to_string()),
+                CErrorCode::InvalidEndpoint,
+            ),
+            (
+                ZerobusError::InvalidTableName("x".to_string()),
+                CErrorCode::InvalidTableName,
+            ),
+            (
+                ZerobusError::InvalidUCTokenError("x".to_string()),
+                CErrorCode::AuthFailure,
+            ),
+            (
+                ZerobusError::InvalidArgument("x".to_string()),
+                CErrorCode::InvalidArgument,
+            ),
+            (
+                ZerobusError::UnexpectedStreamResponseError("x".to_string()),
+                CErrorCode::UnexpectedResponse,
+            ),
+            (
+                ZerobusError::InvalidStateError("x".to_string()),
+                CErrorCode::InvalidState,
+            ),
+            (
+                ZerobusError::CreateStreamError(tonic::Status::unavailable("x")),
+                CErrorCode::CreateStreamFailed,
+            ),
+            (
+                ZerobusError::CreateStreamError(tonic::Status::unauthenticated("x")),
+                CErrorCode::AuthFailure,
+            ),
+            (
+                ZerobusError::StreamClosedError(tonic::Status::internal("x")),
+                CErrorCode::StreamClosed,
+            ),
+            (
+                ZerobusError::StreamClosedError(tonic::Status::permission_denied("x")),
+                CErrorCode::AuthFailure,
+            ),
+            (
+                ZerobusError::StreamClosedError(tonic::Status::deadline_exceeded("x")),
+                CErrorCode::Timeout,
+            ),
+        ];
+
+        for (err, expected) in cases {
+            assert_eq!(CErrorCode::from(&err), expected, "{:?}", err);
+        }
+    }
+
     // ========================================================================
     // Configuration Tests
     // ========================================================================
@@ -177,12 +326,13 @@ mod tests {
             server_lack_of_ack_timeout_ms: 10000,
             flush_timeout_ms: 2000,
             record_type: 1, // Proto
+            ..zerobus_get_default_config()
         };
 
         let rust_config: StreamConfigurationOptions = c_config.into();
 
         assert_eq!(rust_config.max_inflight_requests, 100);
-        assert_eq!(rust_config.recovery, true);
+        assert!(rust_config.recovery);
         assert_eq!(rust_config.recovery_timeout_ms, 5000);
         assert_eq!(rust_config.recovery_retries, 3);
         assert_eq!(rust_config.record_type, RecordType::Proto);
@@ -199,6 +349,7 @@ mod tests {
             server_lack_of_ack_timeout_ms: 0,
             flush_timeout_ms: 0,
             record_type: 2, // Json
+            ..zerobus_get_default_config()
         };
 
         let rust_config: StreamConfigurationOptions = c_config.into();
@@ -216,6 +367,7 @@ mod tests {
             server_lack_of_ack_timeout_ms: 0,
             flush_timeout_ms: 0,
             record_type: 999, // Invalid
+            ..zerobus_get_default_config()
         };
 
         let rust_config: StreamConfigurationOptions = c_config.into();
@@ -229,6 +381,32 @@ mod tests {
         // Verify it returns reasonable defaults
         assert!(config.max_inflight_requests > 0);
         assert_eq!(config.record_type, 1); // Proto
+        assert!(config.ack_callback.is_none());
+        assert!(config.ack_callback_user_data.is_null());
+        assert!(!config.fire_and_forget);
+    }
+
+    #[test]
+    fn test_validate_stream_options() {
+        extern "C" fn noop_ack_callback(
+            _user_data: *mut std::ffi::c_void,
+            _ack_id: u64,
+            _offset: i64,
+            _result: *const CResult,
+        ) {
+        }
+
+        let mut config = zerobus_get_default_config();
+        assert!(validate_stream_options(&config).is_ok());
+
+        config.fire_and_forget = true;
+        assert!(validate_stream_options(&config).is_ok());
+
+        config.ack_callback = Some(noop_ack_callback);
+        assert!(validate_stream_options(&config).is_err());
+
+        config.fire_and_forget = false;
+        assert!(validate_stream_options(&config).is_ok());
     }
 
     // ========================================================================
@@ -260,6 +438,50 @@ mod tests {
         assert_eq!(result.unwrap(), "");
     }
 
+    // ========================================================================
+    // Batch Buffer Tests
+    // ========================================================================
+
+    #[test]
+    fn test_split_batch_buffer_valid() {
+        let data = b"abcdefgh";
+        let offsets = [0usize, 3, 3, 8];
+        let records =
+            unsafe { split_batch_buffer(data.as_ptr(), data.len(), offsets.as_ptr(), 3) }.unwrap();
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0], b"abc");
+        assert_eq!(records[1], b"");
+        assert_eq!(records[2], b"defgh");
+    }
+
+    #[test]
+    fn test_split_batch_buffer_out_of_bounds() {
+        let data = b"abc";
+        let offsets = [0usize, 2, 4];
+        let result = unsafe { split_batch_buffer(data.as_ptr(), data.len(), offsets.as_ptr(), 2) };
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_split_batch_buffer_decreasing_offsets() {
+        let data = b"abcdef";
+        let offsets = [0usize, 4, 2];
+        let result = unsafe { split_batch_buffer(data.as_ptr(), data.len(), offsets.as_ptr(), 2) };
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_split_batch_buffer_empty_or_null() {
+        let data = b"abc";
+        let offsets = [0usize, 3];
+        let empty = unsafe { split_batch_buffer(data.as_ptr(), data.len(), offsets.as_ptr(), 0) };
+        assert_eq!(empty.unwrap_err(), "Batch must contain at least one record");
+
+        let null_offsets = unsafe { split_batch_buffer(data.as_ptr(), data.len(), ptr::null(), 1) };
+        assert_eq!(null_offsets.unwrap_err(), "Invalid offsets pointer");
+    }
+
     // ========================================================================
     // Memory Management Tests
     // ========================================================================
@@ -277,6 +499,2861 @@ mod tests {
         // If we get here without crashing, test passed
     }
 
+    // ========================================================================
+    // Ack Callback Tests
+    // ========================================================================
+
+    extern "C" fn recording_ack_callback(
+        user_data: *mut std::ffi::c_void,
+        ack_id: u64,
+        offset: i64,
+        result: *const CResult,
+    ) {
+        let acks =
+            unsafe { &*(user_data as *const std::sync::Mutex<Vec<(u64, i64, bool, String)>>) };
+        let result = unsafe { &*result };
+        let message = if result.error_message.is_null() {
+            String::new()
+        } else {
+            unsafe {
+                CStr::from_ptr(result.error_message)
+                    .to_string_lossy()
+                    .into_owned()
+            }
+        };
+        acks.lock()
+            .unwrap()
+            .push((ack_id, offset, result.success, message));
+    }
+
+    #[test]
+    fn test_deliver_ack_invokes_callback() {
+        let acks = std::sync::Mutex::new(Vec::<(u64, i64, bool, String)>::new());
+        let callback = AckCallbackHandle::new(
+            recording_ack_callback,
+            &acks as *const _ as *mut std::ffi::c_void,
+        );
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let tracker = Arc::new(CallbackTracker::default());
+        tracker.pending.store(2, Ordering::SeqCst);
+        rt.block_on(deliver_ack(callback, Arc::clone(&tracker), 7, async {
+            Ok(42)
+        }));
+        rt.block_on(deliver_ack(callback, Arc::clone(&tracker), 8, async {
+            Err(ZerobusError::InvalidArgument("bad record".to_string()))
+        }));
+        assert_eq!(tracker.pending.load(Ordering::SeqCst), 0);
+
+        let acks = acks.lock().unwrap();
+        assert_eq!(acks.len(), 2);
+        assert_eq!(acks[0], (7, 42, true, String::new()));
+        assert_eq!(acks[1].0, 8);
+        assert_eq!(acks[1].1, -1);
+        assert!(!acks[1].2);
+        assert!(acks[1].3.contains("bad record"));
+    }
+
+    #[test]
+    fn test_deliver_ack_skipped_after_close() {
+        let acks = std::sync::Mutex::new(Vec::<(u64, i64, bool, String)>::new());
+        let callback = AckCallbackHandle::new(
+            recording_ack_callback,
+            &acks as *const _ as *mut std::ffi::c_void,
+        );
+        let tracker = Arc::new(CallbackTracker::default());
+        tracker.pending.store(1, Ordering::SeqCst);
+        tracker.close();
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(deliver_ack(callback, Arc::clone(&tracker), 1, async {
+            Ok(1)
+        }));
+
+        assert!(acks.lock().unwrap().is_empty());
+        assert_eq!(tracker.pending.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_callback_tracker_close_waits_for_running_callback() {
+        let tracker = Arc::new(CallbackTracker::default());
+        let finished = Arc::new(AtomicBool::new(false));
+        let (entered_tx, entered_rx) = std::sync::mpsc::channel();
+        let worker = {
+            let tracker = Arc::clone(&tracker);
+            let finished = Arc::clone(&finished);
+            std::thread::spawn(move || {
+                tracker.run_if_open(|| {
+                    entered_tx.send(()).unwrap();
+                    std::thread::sleep(std::time::Duration::from_millis(50));
+                    finished.store(true, Ordering::SeqCst);
+                })
+            })
+        };
+
+        // Freeing the stream must not return while its callback still runs
+        entered_rx.recv().unwrap();
+        tracker.close();
+        assert!(finished.load(Ordering::SeqCst));
+        assert!(worker.join().unwrap());
+        assert!(!tracker.run_if_open(|| panic!("callback after close")));
+    }
+
+    // ========================================================================
+    // Ack Registry Tests
+    // ========================================================================
+
+    #[test]
+    fn test_ack_registry_ids_are_sequential() {
+        let registry = AckRegistry::default();
+        assert_eq!(registry.next_id(), 1);
+        assert_eq!(registry.next_id(), 2);
+        assert_eq!(registry.next_id(), 3);
+    }
+
+    #[test]
+    fn test_ack_registry_remove_if_finished() {
+        let registry = AckRegistry::default();
+
+        let (tx, rx) = tokio::sync::oneshot::channel::<i64>();
+        registry.spawn(1, async move { Ok(rx.await.unwrap()) });
+
+        assert!(registry.remove_if_finished(2).is_err());
+        assert!(registry.remove_if_finished(1).unwrap().is_none());
+        assert_eq!(registry.len(), 1);
+
+        tx.send(5).unwrap();
+        let handle = loop {
+            if let Some(handle) = registry.remove_if_finished(1).unwrap() {
+                break handle;
+            }
+            std::thread::yield_now();
+        };
+        assert_eq!(runtime().block_on(handle).unwrap().unwrap(), 5);
+        assert_eq!(registry.len(), 0);
+    }
+
+    #[test]
+    fn test_ack_registry_abort_all() {
+        let registry = AckRegistry::default();
+        let (tx, rx) = tokio::sync::oneshot::channel::<i64>();
+        registry.spawn(
+            registry.next_id(),
+            async move { Ok(rx.await.unwrap_or(-1)) },
+        );
+
+        registry.abort_all();
+
+        assert_eq!(registry.len(), 0);
+        // The aborted task drops its receiver
+        while !tx.is_closed() {
+            std::thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn test_ack_registry_last_id() {
+        let registry = AckRegistry::default();
+        assert_eq!(registry.last_id(), 0);
+        registry.next_id();
+        registry.next_id();
+        assert_eq!(registry.last_id(), 2);
+    }
+
+    // ========================================================================
+    // Unacked Records Tests
+    // ========================================================================
+
+    #[test]
+    fn test_unacked_batches_map_to_trailing_ack_ids() {
+        let batches = vec![
+            vec![EncodedRecord::Proto(vec![1, 2])],
+            vec![
+                EncodedRecord::Json("{\"a\":1}".to_string()),
+                EncodedRecord::Json("{}".to_string()),
+            ],
+        ];
+
+        let c_records = unacked_batches_to_c(batches, 7);
+        assert_eq!(c_records.count, 3);
+
+        let records = unsafe { std::slice::from_raw_parts(c_records.records, c_records.count) };
+        let ids: Vec<u64> = records.iter().map(|r| r.ack_id).collect();
+        assert_eq!(ids, vec![6, 7, 7]);
+        assert_eq!(records[0].record_type, 1);
+        assert_eq!(records[1].record_type, 2);
+        let json = unsafe { std::slice::from_raw_parts(records[1].data, records[1].data_len) };
+        assert_eq!(json, b"{\"a\":1}");
+
+        zerobus_free_unacked_records(c_records);
+    }
+
+    #[test]
+    fn test_unacked_batches_empty() {
+        let c_records = unacked_batches_to_c(Vec::<Vec<EncodedRecord>>::new(), 3);
+        assert!(c_records.records.is_null());
+        assert_eq!(c_records.count, 0);
+
+        zerobus_free_unacked_records(CUnackedRecords {
+            records: ptr::null_mut(),
+            count: 0,
+        });
+    }
+
+    // ========================================================================
+    // Recreate Stream Tests
+    // ========================================================================
+
+    #[test]
+    fn test_ack_id_mappings_roundtrip() {
+        let mappings = CAckIdMappings::from_vec(vec![
+            CAckIdMapping {
+                old_ack_id: 4,
+                new_ack_id: 1,
+            },
+            CAckIdMapping {
+                old_ack_id: 5,
+                new_ack_id: 2,
+            },
+        ]);
+        assert_eq!(mappings.count, 2);
+        let entries = unsafe { std::slice::from_raw_parts(mappings.mappings, mappings.count) };
+        assert_eq!(entries[1].old_ack_id, 5);
+        assert_eq!(entries[1].new_ack_id, 2);
+        zerobus_free_ack_id_mappings(mappings);
+
+        let empty = CAckIdMappings::from_vec(Vec::new());
+        assert!(empty.mappings.is_null());
+        zerobus_free_ack_id_mappings(empty);
+    }
+
+    #[test]
+    fn test_recreate_stream_null_pointers() {
+        let mut mappings = CAckIdMappings {
+            mappings: ptr::NonNull::dangling().as_ptr(),
+            count: 7,
+        };
+        let mut result = CResult::success();
+
+        let stream = zerobus_sdk_recreate_stream(
+            ptr::null_mut(),
+            ptr::null_mut(),
+            0,
+            &mut mappings,
+            &mut result,
+        );
+
+        assert!(stream.is_null());
+        assert!(!result.success);
+        assert_eq!(result.error_code, CErrorCode::InvalidArgument);
+        assert!(mappings.mappings.is_null());
+        assert_eq!(mappings.count, 0);
+        zerobus_free_error_message(result.error_message);
+    }
+
+    // ========================================================================
+    // Runtime Configuration Tests
+    // ========================================================================
+
+    #[test]
+    fn test_build_runtime_thread_names() {
+        let prefix = CString::new("zb-test").unwrap();
+        let config = CRuntimeConfig {
+            worker_threads: 2,
+            thread_name_prefix: prefix.as_ptr(),
+            ..CRuntimeConfig::default()
+        };
+
+        let rt = build_runtime(&config).unwrap();
+        let name = rt
+            .block_on(rt.spawn(async { std::thread::current().name().map(str::to_string) }))
+            .unwrap()
+            .unwrap();
+        assert!(
+            name.starts_with("zb-test-"),
+            "unexpected thread name {}",
+            name
+        );
+    }
+
+    #[test]
+    fn test_build_runtime_current_thread() {
+        let config = CRuntimeConfig {
+            current_thread: true,
+            ..CRuntimeConfig::default()
+        };
+
+        let rt = build_runtime(&config).unwrap();
+        assert_eq!(rt.block_on(async { 7 }), 7);
+    }
+
+    #[test]
+    fn test_build_runtime_invalid_config() {
+        let prefix = [0xffu8, 0x00];
+        let config = CRuntimeConfig {
+            thread_name_prefix: prefix.as_ptr() as *const std::ffi::c_char,
+            ..CRuntimeConfig::default()
+        };
+
+        let (error_code, _) = build_runtime(&config).err().unwrap();
+        assert_eq!(error_code, CErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn test_runtime_init_after_start_fails() {
+        runtime();
+
+        let mut result = CResult::success();
+        assert!(!zerobus_runtime_init(ptr::null(), &mut result));
+        assert!(!result.success);
+        assert_eq!(result.error_code, CErrorCode::InvalidState);
+        zerobus_free_error_message(result.error_message);
+    }
+
+    // ========================================================================
+    // Stream Metrics Tests
+    // ========================================================================
+
+    #[test]
+    fn test_stream_metrics_snapshot() {
+        let metrics = StreamMetrics::default();
+        metrics.record_ingested(3, 300);
+        metrics.record_ingested(1, 50);
+        metrics.record_ingested(2, 20);
+        metrics.record_ack(true, 3, std::time::Duration::from_micros(100));
+        metrics.record_ack(true, 1, std::time::Duration::from_micros(300));
+        metrics.record_ack(false, 2, std::time::Duration::from_micros(900));
+
+        assert_eq!(
+            metrics.snapshot(),
+            CStreamMetrics {
+                records_ingested: 6,
+                bytes_ingested: 370,
+                records_acked: 4,
+                records_failed: 2,
+                records_inflight: 0,
+                ack_latency_avg_us: 200,
+                ack_latency_max_us: 300,
+                recoveries: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_stream_metrics_inflight() {
+        let metrics = StreamMetrics::default();
+        metrics.record_ingested(5, 10);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.records_inflight, 5);
+        assert_eq!(snapshot.ack_latency_avg_us, 0);
+    }
+
+    #[test]
+    fn test_stream_metrics_recoveries() {
+        let metrics = StreamMetrics::default();
+        assert_eq!(metrics.snapshot().recoveries, 0);
+
+        // Connection attempts until the stream is created are not recoveries
+        metrics.record_connection_attempt();
+        metrics.record_connection_attempt();
+        metrics.record_stream_created();
+        assert_eq!(metrics.snapshot().recoveries, 0);
+        metrics.record_connection_attempt();
+        metrics.record_connection_attempt();
+        assert_eq!(metrics.snapshot().recoveries, 2);
+    }
+
+    #[test]
+    fn test_get_metrics_null_stream() {
+        let mut metrics = CStreamMetrics {
+            records_ingested: 1,
+            ..CStreamMetrics::default()
+        };
+        let mut result = CResult::success();
+
+        assert!(!zerobus_stream_get_metrics(
+            ptr::null_mut(),
+            &mut metrics,
+            &mut result
+        ));
+        assert_eq!(metrics, CStreamMetrics::default());
+        assert!(!result.success);
+        zerobus_free_error_message(result.error_message);
+    }
+
+    // ========================================================================
+    // Logging Tests
+    // ========================================================================
+
+    #[test]
+    fn test_log_callback_receives_events() {
+        static EVENTS: std::sync::Mutex<Vec<(CLogLevel, String, String, Vec<(String, String)>)>> =
+            std::sync::Mutex::new(Vec::new());
+
+        extern "C" fn test_callback(
+            _user_data: *mut std::ffi::c_void,
+            level: CLogLevel,
+            target: *const std::os::raw::c_char,
+            message: *const std::os::raw::c_char,
+            fields: *const CLogField,
+            field_count: usize,
+        ) {
+            let to_string = |s| unsafe { CStr::from_ptr(s).to_string_lossy().into_owned() };
+            let fields = if field_count == 0 {
+                Vec::new()
+            } else {
+                unsafe { std::slice::from_raw_parts(fields, field_count) }
+                    .iter()
+                    .map(|f| (to_string(f.key), to_string(f.value)))
+                    .collect()
+            };
+            EVENTS
+                .lock()
+                .unwrap()
+                .push((level, to_string(target), to_string(message), fields));
+        }
+
+        *LOG_SINK.write().unwrap() = Some(LogSink {
+            callback: test_callback,
+            user_data: ptr::null_mut(),
+            level: CLogLevel::Info.filter(),
+        });
+        tracing::subscriber::with_default(CallbackSubscriber::default(), || {
+            tracing::warn!(target: "zerobus_test", attempt = 3, "reconnecting");
+            tracing::debug!(target: "zerobus_test", "filtered out");
+
+            let stream = tracing::info_span!(
+                target: "zerobus_test",
+                "stream",
+                table = "main.default.events",
+                attempt = tracing::field::Empty
+            );
+            let _stream = stream.enter();
+            stream.record("attempt", 1);
+            let _ack = tracing::info_span!(target: "zerobus_test", "ack", attempt = 2).entered();
+            tracing::error!(target: "zerobus_test", offset = 7, "ack failed");
+        });
+        *LOG_SINK.write().unwrap() = None;
+
+        let field = |key: &str, value: &str| (key.to_string(), value.to_string());
+        let events = EVENTS.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].0, CLogLevel::Warn);
+        assert_eq!(events[0].1, "zerobus_test");
+        assert_eq!(events[0].2, "reconnecting");
+        assert_eq!(events[0].3, vec![field("attempt", "3")]);
+        // Span fields follow the event's own; the innermost span's values win
+        assert_eq!(events[1].2, "ack failed");
+        assert_eq!(
+            events[1].3,
+            vec![
+                field("offset", "7"),
+                field("table", "main.default.events"),
+                field("attempt", "2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_set_log_callback_invalid_level() {
+        let mut result = CResult::success();
+        assert!(!zerobus_set_log_callback(
+            9,
+            None,
+            ptr::null_mut(),
+            &mut result
+        ));
+        assert_eq!(result.error_code, CErrorCode::InvalidArgument);
+        zerobus_free_error_message(result.error_message);
+    }
+
+    // ========================================================================
+    // Descriptor Set Tests
+    // ========================================================================
+
+    fn message_field(name: &str, number: i32, type_name: &str) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.to_string()),
+            number: Some(number),
+            r#type: Some(if type_name.ends_with("Status") {
+                14
+            } else {
+                11
+            }),
+            type_name: Some(type_name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn message(name: &str, field: Vec<FieldDescriptorProto>) -> DescriptorProto {
+        DescriptorProto {
+            name: Some(name.to_string()),
+            field,
+            ..Default::default()
+        }
+    }
+
+    fn file(package: &str, message_type: Vec<DescriptorProto>) -> FileDescriptorProto {
+        FileDescriptorProto {
+            name: Some(format!("{}.proto", package)),
+            package: Some(package.to_string()),
+            message_type,
+            ..Default::default()
+        }
+    }
+
+    fn test_descriptor_set() -> FileDescriptorSet {
+        let timestamp = file("google.protobuf", vec![message("Timestamp", vec![])]);
+
+        let mut address = message("Address", vec![]);
+        address.nested_type.push(message(
+            "Geo",
+            vec![message_field("at", 1, ".google.protobuf.Timestamp")],
+        ));
+        let mut common = file("common", vec![address]);
+        common.enum_type.push(EnumDescriptorProto {
+            name: Some("Status".to_string()),
+            ..Default::default()
+        });
+
+        let mut event = message(
+            "Event",
+            vec![
+                message_field("ts", 1, ".google.protobuf.Timestamp"),
+                message_field("status", 2, ".common.Status"),
+                message_field("geo", 3, ".common.Address.Geo"),
+                message_field("inner", 4, ".app.Event.Inner"),
+            ],
+        );
+        event.nested_type.push(message(
+            "Inner",
+            vec![message_field("ts", 1, ".google.protobuf.Timestamp")],
+        ));
+
+        FileDescriptorSet {
+            file: vec![timestamp, common, file("app", vec![event])],
+        }
+    }
+
+    #[test]
+    fn test_flatten_descriptor_set_copies_dependencies() {
+        let root = flatten_descriptor_set(&test_descriptor_set(), "app.Event").unwrap();
+
+        let type_names: Vec<&str> = root.field.iter().map(|f| f.type_name()).collect();
+        assert_eq!(
+            type_names,
+            vec![
+                ".app.Event.google_protobuf_Timestamp",
+                ".app.Event.common_Status",
+                ".app.Event.common_Address.Geo",
+                ".app.Event.Inner",
+            ]
+        );
+        assert_eq!(
+            root.nested_type[0].field[0].type_name(),
+            ".app.Event.google_protobuf_Timestamp"
+        );
+
+        let nested: Vec<&str> = root.nested_type.iter().map(|m| m.name()).collect();
+        assert_eq!(
+            nested,
+            vec!["Inner", "google_protobuf_Timestamp", "common_Address"]
+        );
+        assert_eq!(
+            root.nested_type[2].nested_type[0].field[0].type_name(),
+            ".app.Event.google_protobuf_Timestamp"
+        );
+        assert_eq!(root.enum_type[0].name(), "common_Status");
+    }
+
+    #[test]
+    fn test_flatten_descriptor_set_leading_dot_and_nested_root() {
+        let root = flatten_descriptor_set(&test_descriptor_set(), ".app.Event.Inner").unwrap();
+        assert_eq!(root.name(), "Inner");
+        assert_eq!(
+            root.field[0].type_name(),
+            ".app.Event.Inner.google_protobuf_Timestamp"
+        );
+        assert_eq!(root.nested_type.len(), 1);
+    }
+
+    #[test]
+    fn test_flatten_descriptor_set_errors() {
+        let set = test_descriptor_set();
+        assert!(flatten_descriptor_set(&set, "app.Missing").is_err());
+        assert!(flatten_descriptor_set(&set, "common.Status").is_err());
+
+        let mut incomplete = set.clone();
+        incomplete.file.remove(0);
+        let err = flatten_descriptor_set(&incomplete, "app.Event").unwrap_err();
+        assert!(err.contains(".google.protobuf.Timestamp"), "{}", err);
+    }
+
+    #[test]
+    fn test_flatten_descriptor_set_timestamp_field() {
+        let well_known = file(
+            "google.protobuf",
+            vec![
+                message(
+                    "Timestamp",
+                    vec![
+                        scalar_field("seconds", 1, 3, 1),
+                        scalar_field("nanos", 2, 5, 1),
+                    ],
+                ),
+                message(
+                    "Duration",
+                    vec![
+                        scalar_field("seconds", 1, 3, 1),
+                        scalar_field("nanos", 2, 5, 1),
+                    ],
+                ),
+            ],
+        );
+        let span = message(
+            "Span",
+            vec![
+                message_field("start", 1, ".google.protobuf.Timestamp"),
+                message_field("took", 2, ".google.protobuf.Duration"),
+            ],
+        );
+        let set = FileDescriptorSet {
+            file: vec![well_known, file("app", vec![span])],
+        };
+
+        let root = flatten_descriptor_set(&set, "app.Span").unwrap();
+        let type_names: Vec<&str> = root.field.iter().map(|f| f.type_name()).collect();
+        assert_eq!(
+            type_names,
+            vec![
+                ".app.Span.google_protobuf_Timestamp",
+                ".app.Span.google_protobuf_Duration",
+            ]
+        );
+        let nested: Vec<&str> = root.nested_type.iter().map(|m| m.name()).collect();
+        assert_eq!(
+            nested,
+            vec!["google_protobuf_Timestamp", "google_protobuf_Duration"]
+        );
+        assert_eq!(root.nested_type[0].field.len(), 2);
+
+        // Records of the flattened message carry the well-known types as submessages
+        let schema = ProtoSchema::new(&root).unwrap();
+        let record = schema
+            .transcode_json(r#"{"start": "1970-01-01T00:00:01.5Z", "took": "2s"}"#)
+            .unwrap();
+        assert_eq!(
+            record,
+            vec![
+                0x0a, 0x08, 0x08, 0x01, 0x10, 0x80, 0xca, 0xb5, 0xee, 0x01, // start
+                0x12, 0x02, 0x08, 0x02, // took
+            ]
+        );
+        assert_eq!(schema.validate(&record), Ok(()));
+    }
+
+    // ========================================================================
+    // Record Validation Tests
+    // ========================================================================
+
+    fn scalar_field(name: &str, number: i32, r#type: i32, label: i32) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.to_string()),
+            number: Some(number),
+            r#type: Some(r#type),
+            label: Some(label),
+            ..Default::default()
+        }
+    }
+
+    // message Order { int64 id = 1; string name = 2; repeated int32 tags = 3; Address address = 4;
+    //   message Address { string city = 1; required int32 zip = 2; } }
+    fn order_schema() -> ProtoSchema {
+        let mut order = message(
+            "Order",
+            vec![
+                scalar_field("id", 1, 3, 1),
+                scalar_field("name", 2, 9, 1),
+                scalar_field("tags", 3, 5, 3),
+                message_field("address", 4, ".shop.Order.Address"),
+            ],
+        );
+        order.nested_type.push(message(
+            "Address",
+            vec![scalar_field("city", 1, 9, 1), scalar_field("zip", 2, 5, 2)],
+        ));
+        ProtoSchema::new(&order).unwrap()
+    }
+
+    #[test]
+    fn test_validate_valid_record() {
+        let schema = order_schema();
+        let record = [
+            0x08, 0x01, // id = 1
+            0x12, 0x02, b'a', b'b', // name = "ab"
+            0x1a, 0x02, 0x01, 0x02, // tags = [1, 2] (packed)
+            0x18, 0x03, // tags += 3 (unpacked)
+            0x22, 0x05, 0x0a, 0x01, b'x', 0x10, 0x05, // address = { city: "x", zip: 5 }
+        ];
+        assert_eq!(schema.validate(&record), Ok(()));
+        assert_eq!(schema.validate(&[]), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_reports_offending_field() {
+        let schema = order_schema();
+
+        // id sent as a length-delimited value
+        let err = schema.validate(&[0x0a, 0x01, 0x00]).unwrap_err();
+        assert!(err.starts_with("field 'id' (1):"), "{}", err);
+
+        let err = schema.validate(&[0x12, 0x01, 0xff]).unwrap_err();
+        assert_eq!(err, "field 'name' (2): invalid UTF-8 string");
+
+        let err = schema.validate(&[0x12, 0x05, b'a']).unwrap_err();
+        assert_eq!(err, "field 'name' (2): length exceeds the record");
+
+        let err = schema.validate(&[0x28, 0x01]).unwrap_err();
+        assert_eq!(err, "unknown field number 5 in record");
+    }
+
+    #[test]
+    fn test_validate_nested_message() {
+        let schema = order_schema();
+
+        let err = schema
+            .validate(&[0x22, 0x03, 0x0a, 0x01, 0xff])
+            .unwrap_err();
+        assert_eq!(err, "field 'address.city' (1): invalid UTF-8 string");
+
+        // zip is a proto2 required field
+        let err = schema
+            .validate(&[0x22, 0x03, 0x0a, 0x01, b'x'])
+            .unwrap_err();
+        assert_eq!(err, "field 'address.zip' (2): required field is missing");
+    }
+
+    #[test]
+    fn test_validate_limits_nesting_depth() {
+        // message Node { Node child = 1; }
+        let schema =
+            ProtoSchema::new(&message("Node", vec![message_field("child", 1, ".Node")])).unwrap();
+        let nested = |levels: usize| {
+            let mut record = Vec::new();
+            for _ in 0..levels {
+                let mut outer = vec![0x0a];
+                prost::encoding::encode_varint(record.len() as u64, &mut outer);
+                outer.extend_from_slice(&record);
+                record = outer;
+            }
+            record
+        };
+
+        assert_eq!(schema.validate(&nested(100)), Ok(()));
+        let err = schema.validate(&nested(101)).unwrap_err();
+        assert!(
+            err.ends_with("(1): messages nested deeper than 100 levels"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_record_schema_is_opt_in() {
+        let descriptor = message("Order", vec![scalar_field("id", 1, 3, 1)]);
+        let mut opts = zerobus_get_default_config();
+        assert!(!opts.validate_records);
+        assert!(record_schemas(Some(&opts), Some(&descriptor))
+            .unwrap()
+            .proto
+            .is_none());
+
+        opts.validate_records = true;
+        assert!(record_schemas(Some(&opts), Some(&descriptor))
+            .unwrap()
+            .proto
+            .is_some());
+        assert!(record_schemas(Some(&opts), None).unwrap().proto.is_none());
+
+        let unresolved = message("Order", vec![message_field("x", 1, ".Missing")]);
+        assert!(matches!(
+            record_schemas(Some(&opts), Some(&unresolved)),
+            Err(ZerobusError::InvalidArgument(_))
+        ));
+    }
+
+    // ========================================================================
+    // JSON Transcoding Tests
+    // ========================================================================
+
+    // message Doc { bytes blob = 1; map<string, int64> counts = 2; Status status = 3;
+    //   google.protobuf.Timestamp ts = 4; oneof choice { string a = 5; string b = 6; } }
+    fn doc_schema() -> ProtoSchema {
+        let mut counts = message_field("counts", 2, ".Doc.CountsEntry");
+        counts.label = Some(3);
+        let mut a = scalar_field("a", 5, 9, 1);
+        a.oneof_index = Some(0);
+        let mut b = scalar_field("b", 6, 9, 1);
+        b.oneof_index = Some(0);
+        let mut doc = message(
+            "Doc",
+            vec![
+                scalar_field("blob", 1, 12, 1),
+                counts,
+                message_field("status", 3, ".Doc.Status"),
+                message_field("ts", 4, ".Doc.google_protobuf_Timestamp"),
+                a,
+                b,
+            ],
+        );
+
+        let mut entry = message(
+            "CountsEntry",
+            vec![scalar_field("key", 1, 9, 1), scalar_field("value", 2, 3, 1)],
+        );
+        entry.options = Some(MessageOptions {
+            map_entry: Some(true),
+            ..Default::default()
+        });
+        doc.nested_type.push(entry);
+        doc.nested_type.push(message(
+            "google_protobuf_Timestamp",
+            vec![
+                scalar_field("seconds", 1, 3, 1),
+                scalar_field("nanos", 2, 5, 1),
+            ],
+        ));
+        doc.enum_type.push(EnumDescriptorProto {
+            name: Some("Status".to_string()),
+            value: ["UNKNOWN", "ACTIVE"]
+                .iter()
+                .zip(0..)
+                .map(|(name, number)| EnumValueDescriptorProto {
+                    name: Some(name.to_string()),
+                    number: Some(number),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        });
+        ProtoSchema::new(&doc).unwrap()
+    }
+
+    #[test]
+    fn test_transcode_json_scalars() {
+        let schema = order_schema();
+
+        let record = schema
+            .transcode_json(r#"{"id": "5", "name": "ab", "tags": [1, "2"]}"#)
+            .unwrap();
+        assert_eq!(
+            record,
+            vec![0x08, 0x05, 0x12, 0x02, b'a', b'b', 0x1a, 0x02, 0x01, 0x02]
+        );
+
+        let record = schema
+            .transcode_json(r#"{"address": {"city": "x", "zip": 5}, "id": null}"#)
+            .unwrap();
+        assert_eq!(record, vec![0x22, 0x05, 0x0a, 0x01, b'x', 0x10, 0x05]);
+        assert_eq!(schema.validate(&record), Ok(()));
+    }
+
+    #[test]
+    fn test_transcode_json_enum_map_bytes_timestamp() {
+        let schema = doc_schema();
+
+        let record = schema
+            .transcode_json(
+                r#"{"blob": "AQI=", "counts": {"a": 1}, "status": "ACTIVE", "ts": "1970-01-01T00:00:01.5Z"}"#,
+            )
+            .unwrap();
+        assert_eq!(
+            record,
+            vec![
+                0x0a, 0x02, 0x01, 0x02, // blob
+                0x12, 0x05, 0x0a, 0x01, b'a', 0x10, 0x01, // counts entry
+                0x18, 0x01, // status
+                0x22, 0x08, 0x08, 0x01, 0x10, 0x80, 0xca, 0xb5, 0xee, 0x01, // ts
+            ]
+        );
+        assert_eq!(schema.validate(&record), Ok(()));
+
+        // Offsets are applied and enums may be given by number
+        let record = schema
+            .transcode_json(r#"{"status": 1, "ts": "1970-01-01T01:00:00+01:00"}"#)
+            .unwrap();
+        assert_eq!(record, vec![0x18, 0x01, 0x22, 0x00]);
+    }
+
+    #[test]
+    fn test_transcode_json_field_errors() {
+        let order = order_schema();
+        let cases = [
+            (
+                r#"{"id": "abc"}"#,
+                "field 'id' (1): expected int64, got string \"abc\"",
+            ),
+            (
+                r#"{"address": {"zip": 1.5}}"#,
+                "field 'address.zip' (2): expected int32, got number 1.5",
+            ),
+            (
+                r#"{"tags": [1, null]}"#,
+                "field 'tags[1]' (3): null is not allowed in arrays",
+            ),
+            (r#"{"nope": 1}"#, "unknown field 'nope' in record"),
+            ("[1]", "record: expected a JSON object, got an array"),
+        ];
+        for (json, expected) in cases {
+            assert_eq!(order.transcode_json(json).unwrap_err(), expected);
+        }
+        assert!(order
+            .transcode_json("not json")
+            .unwrap_err()
+            .starts_with("invalid JSON"));
+
+        let doc = doc_schema();
+        let cases = [
+            (
+                r#"{"status": "NOPE"}"#,
+                "field 'status' (3): unknown enum value \"NOPE\"",
+            ),
+            (
+                r#"{"ts": "1970-02-30T00:00:00Z"}"#,
+                "'ts': invalid value \"1970-02-30T00:00:00Z\"",
+            ),
+            (
+                r#"{"a": "x", "b": "y"}"#,
+                "field 'b' (6): another field of the same oneof is already set",
+            ),
+        ];
+        for (json, expected) in cases {
+            assert_eq!(doc.transcode_json(json).unwrap_err(), expected);
+        }
+    }
+
+    #[test]
+    fn test_transcode_json_options() {
+        let descriptor = message("Order", vec![scalar_field("id", 1, 3, 1)]);
+        let mut opts = zerobus_get_default_config();
+        assert!(!opts.transcode_json);
+        opts.transcode_json = true;
+        assert!(validate_stream_options(&opts).is_ok());
+        assert!(record_schemas(Some(&opts), Some(&descriptor))
+            .unwrap()
+            .proto
+            .is_some());
+        assert!(matches!(
+            record_schemas(Some(&opts), None),
+            Err(ZerobusError::InvalidArgument(_))
+        ));
+
+        opts.record_type = 2;
+        assert!(validate_stream_options(&opts).is_err());
+    }
+
+    // ========================================================================
+    // JSON Validation Tests
+    // ========================================================================
+
+    const ORDERS_SCHEMA: &str = r#"[
+        {"name": "id", "type": "BIGINT", "nullable": false},
+        {"name": "tags", "type": "ARRAY<STRING>"},
+        {"name": "address", "type": "STRUCT<city: STRING, zip: INT NOT NULL>"},
+        {"name": "totals", "type_text": "map<string, double>"},
+        {"name": "created", "type": "TIMESTAMP"}
+    ]"#;
+
+    #[test]
+    fn test_parse_data_type() {
+        let parsed =
+            DataType::parse("array<struct<city: string, `zip code` INT NOT NULL>>").unwrap();
+        assert_eq!(
+            parsed,
+            DataType::Array(Box::new(DataType::Struct(vec![
+                Column {
+                    name: "city".to_string(),
+                    data_type: DataType::String,
+                    nullable: true,
+                },
+                Column {
+                    name: "zip code".to_string(),
+                    data_type: DataType::Int,
+                    nullable: false,
+                },
+            ])))
+        );
+        assert_eq!(
+            parsed.to_string(),
+            "ARRAY<STRUCT<city: STRING, zip code: INT NOT NULL>>"
+        );
+        assert_eq!(
+            DataType::parse("DECIMAL(10, 2)").unwrap(),
+            DataType::Decimal {
+                precision: 10,
+                scale: 2
+            }
+        );
+        assert_eq!(
+            DataType::parse("MAP<STRING, LONG>").unwrap().to_string(),
+            "MAP<STRING, BIGINT>"
+        );
+
+        assert!(DataType::parse("GEOMETRY").is_err());
+        assert!(DataType::parse("MAP<STRING>").is_err());
+        assert!(DataType::parse("ARRAY<INT").is_err());
+        assert!(DataType::parse("INT INT").is_err());
+    }
+
+    #[test]
+    fn test_parse_table_schema_errors() {
+        assert_eq!(TableSchema::parse(ORDERS_SCHEMA).unwrap().columns.len(), 5);
+        assert!(TableSchema::parse(r#"{"columns": [{"name": "id", "type": "INT"}]}"#).is_ok());
+
+        let cases = [
+            ("[]", "table schema has no columns"),
+            (
+                r#"[{"name": "id"}]"#,
+                "invalid column 0: column 'id' has no \"type\"",
+            ),
+            (
+                r#"[{"name": "id", "type": "INT"}, {"name": "ID", "type": "INT"}]"#,
+                "duplicate column 'ID'",
+            ),
+            (
+                r#"[{"name": "id", "type": "INT", "nullable": "no"}]"#,
+                "invalid column 0: \"nullable\" of column 'id' must be a bool",
+            ),
+        ];
+        for (json, expected) in cases {
+            assert_eq!(TableSchema::parse(json).unwrap_err(), expected);
+        }
+    }
+
+    #[test]
+    fn test_validate_json_record_requires_object() {
+        assert_eq!(validate_json_record(r#"{"a": 1}"#, None), Ok(()));
+        assert_eq!(
+            validate_json_record("[1, 2]", None).unwrap_err(),
+            "expected a JSON object, got an array"
+        );
+        assert!(validate_json_record("not json", None)
+            .unwrap_err()
+            .starts_with("invalid JSON"));
+    }
+
+    #[test]
+    fn test_validate_json_record_against_columns() {
+        let schema = TableSchema::parse(ORDERS_SCHEMA).unwrap();
+        let valid = r#"{"ID": 1, "tags": ["a", null], "address": {"zip": 12345},
+            "totals": {"eur": 1.5}, "created": 1700000000000000}"#;
+        assert_eq!(validate_json_record(valid, Some(&schema)), Ok(()));
+
+        let cases = [
+            (r#"{"tags": []}"#, "column 'id': must not be null"),
+            (r#"{"id": null}"#, "column 'id': must not be null"),
+            (
+                r#"{"id": 1.5}"#,
+                "column 'id': expected BIGINT, got number 1.5",
+            ),
+            (
+                r#"{"id": 1, "tags": ["a", 2]}"#,
+                "column 'tags[1]': expected STRING, got number 2",
+            ),
+            (
+                r#"{"id": 1, "address": {"city": "x"}}"#,
+                "column 'address.zip': must not be null",
+            ),
+            (
+                r#"{"id": 1, "address": {"zip": 99999999999}}"#,
+                "column 'address.zip': expected INT, got number 99999999999",
+            ),
+            (
+                r#"{"id": 1, "totals": {"eur": "x"}}"#,
+                "column 'totals[eur]': expected DOUBLE, got string \"x\"",
+            ),
+            (r#"{"id": 1, "extra": true}"#, "unknown column 'extra'"),
+        ];
+        for (json, expected) in cases {
+            assert_eq!(
+                validate_json_record(json, Some(&schema)).unwrap_err(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_table_schema_options() {
+        let schema_json = CString::new(ORDERS_SCHEMA).unwrap();
+        let mut opts = zerobus_get_default_config();
+        assert!(opts.table_schema_json.is_null());
+        opts.table_schema_json = schema_json.as_ptr();
+
+        // Only valid together with validate_records on a JSON stream
+        assert!(validate_stream_options(&opts).is_err());
+        opts.validate_records = true;
+        assert!(validate_stream_options(&opts).is_err());
+        opts.record_type = 2;
+        assert!(validate_stream_options(&opts).is_ok());
+        assert!(record_schemas(Some(&opts), None).unwrap().table.is_some());
+
+        let invalid = CString::new(r#"[{"name": "id", "type": "BLOB"}]"#).unwrap();
+        opts.table_schema_json = invalid.as_ptr();
+        assert!(matches!(
+            record_schemas(Some(&opts), None),
+            Err(ZerobusError::InvalidArgument(_))
+        ));
+    }
+
+    // ========================================================================
+    // Table Schema Descriptor Tests
+    // ========================================================================
+
+    #[test]
+    fn test_table_schema_to_descriptor_scalars() {
+        let schema = TableSchema::parse(
+            r#"[
+                {"name": "flag", "type": "BOOLEAN"},
+                {"name": "small", "type": "SMALLINT"},
+                {"name": "id", "type": "BIGINT", "nullable": false},
+                {"name": "ratio", "type": "FLOAT"},
+                {"name": "score", "type": "DOUBLE"},
+                {"name": "price", "type": "DECIMAL(10,2)"},
+                {"name": "name", "type": "STRING"},
+                {"name": "blob", "type": "BINARY"},
+                {"name": "day", "type": "DATE"},
+                {"name": "created", "type": "TIMESTAMP"},
+                {"name": "payload", "type": "VARIANT"}
+            ]"#,
+        )
+        .unwrap();
+        let descriptor = schema.to_descriptor("Orders").unwrap();
+        assert_eq!(descriptor.name(), "Orders");
+
+        let fields: Vec<(&str, i32, i32, i32)> = descriptor
+            .field
+            .iter()
+            .map(|f| (f.name(), f.number(), f.r#type.unwrap(), f.label.unwrap()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("flag", 1, 8, 1),
+                ("small", 2, 5, 1),
+                ("id", 3, 3, 2),
+                ("ratio", 4, 2, 1),
+                ("score", 5, 1, 1),
+                ("price", 6, 9, 1),
+                ("name", 7, 9, 1),
+                ("blob", 8, 12, 1),
+                ("day", 9, 5, 1),
+                ("created", 10, 3, 1),
+                ("payload", 11, 9, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_table_schema_to_descriptor_nested_types() {
+        let schema = TableSchema::parse(
+            r#"[
+                {"name": "tags", "type": "ARRAY<STRING>"},
+                {"name": "shipping_address", "type": "STRUCT<city: STRING, zip: INT NOT NULL>"},
+                {"name": "items", "type": "ARRAY<STRUCT<sku: STRING>>"},
+                {"name": "totals", "type": "MAP<STRING, STRUCT<amount: DOUBLE>>"}
+            ]"#,
+        )
+        .unwrap();
+        let descriptor = schema.to_descriptor("Orders").unwrap();
+
+        let tags = &descriptor.field[0];
+        assert_eq!((tags.r#type.unwrap(), tags.label.unwrap()), (9, 3));
+        let address = &descriptor.field[1];
+        assert_eq!(address.type_name(), ".Orders.ShippingAddress");
+        assert_eq!(descriptor.field[2].type_name(), ".Orders.Items");
+        assert_eq!(descriptor.field[2].label.unwrap(), 3);
+        assert_eq!(descriptor.field[3].type_name(), ".Orders.TotalsEntry");
+
+        let nested: Vec<&str> = descriptor.nested_type.iter().map(|m| m.name()).collect();
+        assert_eq!(
+            nested,
+            vec!["ShippingAddress", "Items", "TotalsValue", "TotalsEntry"]
+        );
+        assert_eq!(descriptor.nested_type[0].field[1].label.unwrap(), 2);
+        let entry = &descriptor.nested_type[3];
+        assert!(entry.options.as_ref().unwrap().map_entry());
+        assert_eq!(entry.field[1].type_name(), ".Orders.TotalsValue");
+
+        // The descriptor describes records the proto layer can produce and check
+        let schema = ProtoSchema::new(&descriptor).unwrap();
+        let record = schema
+            .transcode_json(
+                r#"{"items": [{"sku": "a"}], "shipping_address": {"zip": 1},
+                    "tags": ["x"], "totals": {"eur": {"amount": 2.5}}}"#,
+            )
+            .unwrap();
+        assert_eq!(schema.validate(&record), Ok(()));
+    }
+
+    #[test]
+    fn test_table_schema_to_descriptor_errors() {
+        let cases = [
+            (
+                r#"[{"name": "grid", "type": "ARRAY<ARRAY<INT>>"}]"#,
+                "column 'grid': ARRAY<ARRAY<INT>> has no protobuf equivalent",
+            ),
+            (
+                r#"[{"name": "m", "type": "MAP<DOUBLE, INT>"}]"#,
+                "column 'm': MAP<DOUBLE, INT> has no protobuf equivalent",
+            ),
+            (
+                r#"[{"name": "s", "type": "STRUCT<`bad name`: INT>"}]"#,
+                "column 's.bad name': name is not a valid protobuf field name",
+            ),
+            (
+                r#"[{"name": "a_b", "type": "STRUCT<x: INT>"}, {"name": "a__b", "type": "STRUCT<x: INT>"}]"#,
+                "column 'a__b': message name AB is already used by another column",
+            ),
+        ];
+        for (json, expected) in cases {
+            let schema = TableSchema::parse(json).unwrap();
+            assert_eq!(schema.to_descriptor("Row").unwrap_err(), expected);
+        }
+
+        let schema = TableSchema::parse(r#"[{"name": "id", "type": "INT"}]"#).unwrap();
+        assert!(schema.to_descriptor("my.table").is_err());
+    }
+
+    #[test]
+    fn test_descriptor_proto_from_table_schema_ffi() {
+        let schema_json = CString::new(r#"[{"name": "id", "type": "BIGINT"}]"#).unwrap();
+        let message_name = CString::new("Row").unwrap();
+        let mut result = CResult {
+            success: false,
+            error_message: ptr::null_mut(),
+            is_retryable: false,
+            error_code: CErrorCode::Ok,
+        };
+
+        let bytes = zerobus_descriptor_proto_from_table_schema(
+            schema_json.as_ptr(),
+            message_name.as_ptr(),
+            &mut result,
+        );
+        assert!(result.success);
+        let encoded = unsafe { std::slice::from_raw_parts(bytes.data, bytes.len) };
+        let descriptor = DescriptorProto::decode(encoded).unwrap();
+        assert_eq!(descriptor.name(), "Row");
+        assert_eq!(descriptor.field[0].name(), "id");
+        zerobus_free_bytes(bytes);
+
+        let invalid = CString::new("{}").unwrap();
+        let bytes = zerobus_descriptor_proto_from_table_schema(
+            invalid.as_ptr(),
+            message_name.as_ptr(),
+            &mut result,
+        );
+        assert!(!result.success);
+        assert!(bytes.data.is_null());
+        assert_eq!(result.error_code, CErrorCode::InvalidArgument);
+        zerobus_free_error_message(result.error_message);
+    }
+
+    // ========================================================================
+    // JSON Byte Slice Ingestion Tests
+    // ========================================================================
+
+    #[test]
+    fn test_ingest_json_record_bytes_invalid_pointers() {
+        let json = br#"{"a": 1}"#;
+        let mut result = CResult::success();
+
+        let ack_id =
+            zerobus_stream_ingest_json_record_bytes(ptr::null_mut(), ptr::null(), 3, &mut result);
+        assert_eq!(ack_id, 0);
+        assert!(!result.success);
+        let msg = unsafe { CStr::from_ptr(result.error_message) };
+        assert_eq!(msg.to_str().unwrap(), "Invalid data pointer");
+        zerobus_free_error_message(result.error_message);
+
+        let mut result = CResult::success();
+        let ack_id = zerobus_stream_ingest_json_record_bytes(
+            ptr::null_mut(),
+            json.as_ptr(),
+            json.len(),
+            &mut result,
+        );
+        assert_eq!(ack_id, 0);
+        assert!(!result.success);
+        assert_eq!(result.error_code, CErrorCode::InvalidArgument);
+        zerobus_free_error_message(result.error_message);
+
+        // A NULL pointer is fine for an empty slice, the stream is checked next
+        let mut result = CResult::success();
+        zerobus_stream_ingest_json_record_bytes(ptr::null_mut(), ptr::null(), 0, &mut result);
+        let msg = unsafe { CStr::from_ptr(result.error_message) };
+        assert_ne!(msg.to_str().unwrap(), "Invalid data pointer");
+        zerobus_free_error_message(result.error_message);
+    }
+
+    // ========================================================================
+    // Record Buffer Tests
+    // ========================================================================
+
+    #[test]
+    fn test_record_buffer_alloc_and_free() {
+        assert!(zerobus_record_buffer_alloc(0).is_null());
+        zerobus_record_buffer_free(ptr::null_mut(), 0);
+
+        let buffer = zerobus_record_buffer_alloc(16);
+        assert!(!buffer.is_null());
+        let data = unsafe { std::slice::from_raw_parts_mut(buffer, 16) };
+        assert!(data.iter().all(|b| *b == 0));
+        data[..3].copy_from_slice(&[0x08, 0x96, 0x01]);
+        zerobus_record_buffer_free(buffer, 16);
+    }
+
+    #[test]
+    fn test_ingest_proto_record_buffer_takes_ownership_on_error() {
+        // The buffer is released even though the stream is invalid
+        let buffer = zerobus_record_buffer_alloc(8);
+        let mut result = CResult::success();
+        let ack_id =
+            zerobus_stream_ingest_proto_record_buffer(ptr::null_mut(), buffer, 3, 8, &mut result);
+        assert_eq!(ack_id, 0);
+        assert!(!result.success);
+        assert_eq!(result.error_code, CErrorCode::InvalidArgument);
+        zerobus_free_error_message(result.error_message);
+
+        // A length past the capacity is rejected without touching the buffer
+        let buffer = zerobus_record_buffer_alloc(4);
+        let mut result = CResult::success();
+        zerobus_stream_ingest_proto_record_buffer(ptr::null_mut(), buffer, 5, 4, &mut result);
+        assert!(!result.success);
+        let msg = unsafe { CStr::from_ptr(result.error_message) };
+        assert_eq!(msg.to_str().unwrap(), "Invalid record buffer");
+        zerobus_free_error_message(result.error_message);
+        zerobus_record_buffer_free(buffer, 4);
+    }
+
+    // ========================================================================
+    // Backpressure Tests
+    // ========================================================================
+
+    #[test]
+    fn test_write_ingest_result_would_block() {
+        let mut result = CResult::success();
+        assert_eq!(
+            write_ingest_result(&mut result, Err(IngestError::WouldBlock)),
+            0
+        );
+        assert!(!result.success);
+        assert!(result.is_retryable);
+        assert_eq!(result.error_code, CErrorCode::WouldBlock);
+        zerobus_free_error_message(result.error_message);
+
+        let mut result = CResult::success();
+        assert_eq!(write_ingest_result(&mut result, Ok(7)), 7);
+        assert!(result.success);
+    }
+
+    #[test]
+    fn test_inflight_release_wakes_waiter() {
+        let inflight = Arc::new(InflightRequests::default());
+        inflight.acquire();
+        inflight.acquire();
+        assert_eq!(inflight.count(), 2);
+
+        let changed = inflight.changed.notified();
+        let releaser = Arc::clone(&inflight);
+        std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            releaser.release();
+        });
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let woken = rt.block_on(async {
+            tokio::time::timeout(std::time::Duration::from_secs(5), changed).await
+        });
+        assert!(woken.is_ok());
+        assert_eq!(inflight.count(), 1);
+    }
+
+    #[test]
+    fn test_try_ingest_invalid_stream() {
+        let data = [0x08, 0x01];
+        let json = br#"{"a": 1}"#;
+
+        let mut result = CResult::success();
+        let ack_id = zerobus_stream_try_ingest_proto_record(
+            ptr::null_mut(),
+            data.as_ptr(),
+            data.len(),
+            &mut result,
+        );
+        assert_eq!(ack_id, 0);
+        assert_eq!(result.error_code, CErrorCode::InvalidArgument);
+        zerobus_free_error_message(result.error_message);
+
+        let mut result = CResult::success();
+        zerobus_stream_ingest_proto_record_timeout(
+            ptr::null_mut(),
+            ptr::null(),
+            0,
+            100,
+            &mut result,
+        );
+        let msg = unsafe { CStr::from_ptr(result.error_message) };
+        assert_eq!(msg.to_str().unwrap(), "Invalid data pointer");
+        zerobus_free_error_message(result.error_message);
+
+        let mut result = CResult::success();
+        zerobus_stream_try_ingest_json_record_bytes(
+            ptr::null_mut(),
+            json.as_ptr(),
+            json.len(),
+            &mut result,
+        );
+        assert_eq!(result.error_code, CErrorCode::InvalidArgument);
+        zerobus_free_error_message(result.error_message);
+
+        let mut result = CResult::success();
+        zerobus_stream_ingest_json_record_bytes_timeout(
+            ptr::null_mut(),
+            json.as_ptr(),
+            json.len(),
+            100,
+            &mut result,
+        );
+        assert_eq!(result.error_code, CErrorCode::InvalidArgument);
+        zerobus_free_error_message(result.error_message);
+    }
+
+    // ========================================================================
+    // Multi-Ack Await Tests
+    // ========================================================================
+
+    #[test]
+    fn test_ack_registry_remove_many() {
+        let registry = AckRegistry::default();
+        for id in 1..=3 {
+            registry.spawn(id, async move { Ok(id as i64 * 10) });
+        }
+
+        let handles = registry.remove_many(&[3, 7, 1, 3]);
+        let present: Vec<bool> = handles.iter().map(Option::is_some).collect();
+        assert_eq!(present, vec![true, false, true, false]);
+        assert_eq!(registry.len(), 1);
+        registry.abort_all();
+    }
+
+    #[test]
+    fn test_ack_registry_check_all_registered() {
+        let registry = AckRegistry::default();
+        for id in 1..=3 {
+            registry.spawn(id, async move { Ok(id as i64) });
+        }
+
+        assert_eq!(registry.check_all_registered(&[1, 9, 2]).unwrap_err(), 9);
+        assert_eq!(registry.check_all_registered(&[2, 2]).unwrap_err(), 2);
+        assert!(registry.check_all_registered(&[3, 1]).is_ok());
+        assert_eq!(registry.len(), 3);
+        registry.abort_all();
+    }
+
+    #[test]
+    fn test_take_first_settled_returns_earliest_ack() {
+        let registry = AckRegistry::default();
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        registry.spawn(1, async move {
+            let _ = rx.await;
+            Ok(1)
+        });
+        registry.spawn(2, async {
+            Err(ZerobusError::InvalidStateError("x".to_string()))
+        });
+
+        let (index, handle) = runtime()
+            .block_on(registry.take_first_settled(&[1, 2]))
+            .unwrap();
+        assert_eq!(index, 1);
+        let res = runtime().block_on(handle);
+        assert!(matches!(res, Ok(Err(ZerobusError::InvalidStateError(_)))));
+
+        // Only the winner was removed
+        assert_eq!(registry.len(), 1);
+        tx.send(()).unwrap();
+        let (index, handle) = runtime()
+            .block_on(registry.take_first_settled(&[1, 2]))
+            .unwrap();
+        assert_eq!(index, 0);
+        assert_eq!(runtime().block_on(handle).unwrap().unwrap(), 1);
+
+        // Nothing left to wait for
+        assert!(runtime()
+            .block_on(registry.take_first_settled(&[1, 2]))
+            .is_none());
+    }
+
+    #[test]
+    fn test_take_first_settled_allows_concurrent_awaits() {
+        let registry = Arc::new(AckRegistry::default());
+        let (tx1, rx1) = tokio::sync::oneshot::channel::<()>();
+        let (tx2, rx2) = tokio::sync::oneshot::channel::<()>();
+        registry.spawn(1, async move {
+            let _ = rx1.await;
+            Ok(10)
+        });
+        registry.spawn(2, async move {
+            let _ = rx2.await;
+            Ok(20)
+        });
+
+        let racer = {
+            let registry = Arc::clone(&registry);
+            std::thread::spawn(move || {
+                let (index, handle) = runtime()
+                    .block_on(registry.take_first_settled(&[1, 2]))
+                    .unwrap();
+                (index, runtime().block_on(handle).unwrap().unwrap())
+            })
+        };
+
+        // While the race is on, both acks stay registered and can be awaited one by one
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert_eq!(registry.len(), 2);
+        let wait = Some(std::time::Duration::from_millis(10));
+        let mut result = CResult::success();
+        assert_eq!(await_registered_ack(&registry, 2, wait, &mut result), -1);
+        assert_eq!(result.error_code, CErrorCode::WaitTimedOut);
+        zerobus_free_error_message(result.error_message);
+
+        tx2.send(()).unwrap();
+        let mut result = CResult::success();
+        let offset = await_registered_ack(&registry, 2, None, &mut result);
+
+        // Ack 2 went to whichever call took it first; the race ends with ack 1 if it lost
+        if offset == 20 {
+            assert!(result.success);
+            tx1.send(()).unwrap();
+            assert_eq!(racer.join().unwrap(), (0, 10));
+        } else {
+            assert_eq!(result.error_code, CErrorCode::InvalidArgument);
+            zerobus_free_error_message(result.error_message);
+            assert_eq!(racer.join().unwrap(), (1, 20));
+            assert_eq!(registry.len(), 1);
+            registry.abort_all();
+        }
+    }
+
+    #[test]
+    fn test_await_acks_invalid_arguments() {
+        let ids = [1u64, 2];
+        let mut offsets = [0i64; 2];
+
+        let mut result = CResult::success();
+        let ok = zerobus_stream_await_acks(
+            ptr::null_mut(),
+            ids.as_ptr(),
+            2,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            &mut result,
+        );
+        assert!(!ok);
+        let msg = unsafe { CStr::from_ptr(result.error_message) };
+        assert_eq!(msg.to_str().unwrap(), "Invalid ack ID array");
+        zerobus_free_error_message(result.error_message);
+
+        let mut result = CResult::success();
+        let ok = zerobus_stream_await_acks(
+            ptr::null_mut(),
+            ids.as_ptr(),
+            2,
+            offsets.as_mut_ptr(),
+            ptr::null_mut(),
+            &mut result,
+        );
+        assert!(!ok);
+        assert_eq!(result.error_code, CErrorCode::InvalidArgument);
+        zerobus_free_error_message(result.error_message);
+
+        let mut offset = 0i64;
+        let mut result = CResult::success();
+        let ack_id = zerobus_stream_await_any_ack(
+            ptr::null_mut(),
+            ids.as_ptr(),
+            0,
+            &mut offset,
+            &mut result,
+        );
+        assert_eq!(ack_id, 0);
+        assert!(!result.success);
+        zerobus_free_error_message(result.error_message);
+    }
+
+    // ========================================================================
+    // Timed Await Tests
+    // ========================================================================
+
+    #[test]
+    fn test_await_timeout_keeps_pending_ack() {
+        let registry = AckRegistry::default();
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        registry.spawn(1, async move {
+            let _ = rx.await;
+            Ok(42)
+        });
+
+        let wait = Some(std::time::Duration::from_millis(10));
+        let mut result = CResult::success();
+        assert_eq!(await_registered_ack(&registry, 1, wait, &mut result), -1);
+        assert_eq!(result.error_code, CErrorCode::WaitTimedOut);
+        assert!(result.is_retryable);
+        zerobus_free_error_message(result.error_message);
+        assert_eq!(registry.len(), 1);
+
+        // Once acknowledged, the same ack ID can be awaited again and is consumed
+        tx.send(()).unwrap();
+        let wait = Some(std::time::Duration::from_secs(5));
+        let mut result = CResult::success();
+        assert_eq!(await_registered_ack(&registry, 1, wait, &mut result), 42);
+        assert!(result.success);
+        assert_eq!(registry.len(), 0);
+    }
+
+    #[test]
+    fn test_await_timeout_invalid_ack_id() {
+        let registry = AckRegistry::default();
+        let wait = Some(std::time::Duration::ZERO);
+        let mut result = CResult::success();
+        assert_eq!(await_registered_ack(&registry, 5, wait, &mut result), -1);
+        assert_eq!(result.error_code, CErrorCode::InvalidArgument);
+        zerobus_free_error_message(result.error_message);
+
+        let mut result = CResult::success();
+        zerobus_stream_await_ack_timeout(ptr::null_mut(), 1, 10, &mut result);
+        assert!(!result.success);
+        zerobus_free_error_message(result.error_message);
+    }
+
+    // ========================================================================
+    // Spill Buffer Tests
+    // ========================================================================
+
+    /// Empty directory under the system temp dir, unique to the test
+    fn spill_test_dir(name: &str) -> std::path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("zerobus-ffi-spill-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn proto_records(payloads: &[&[u8]]) -> Vec<EncodedRecord> {
+        payloads
+            .iter()
+            .map(|payload| EncodedRecord::Proto(payload.to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn test_spill_buffer_replays_unacknowledged_records() {
+        let dir = spill_test_dir("replay");
+        let dir_str = dir.to_str().unwrap();
+
+        let (spill, leftovers) = SpillBuffer::open(dir_str, 0).unwrap();
+        assert!(leftovers.is_empty());
+        let first = spill.append(&proto_records(&[b"a", b"b"])).unwrap();
+        let second = spill
+            .append(&[EncodedRecord::Json("{\"id\": 1}".to_string())])
+            .unwrap();
+        spill.release(first);
+        spill.release(second);
+        // Acknowledging every record empties the segment being written right away
+        let segment = std::fs::read_dir(&dir).unwrap().next().unwrap().unwrap();
+        assert_eq!(segment.metadata().unwrap().len(), 8);
+        let third = spill.append(&proto_records(&[b"c"])).unwrap();
+        // Records still held can be read back from disk
+        assert!(matches!(
+            spill.read(&third).unwrap().as_slice(),
+            [EncodedRecord::Proto(payload)] if payload == b"c"
+        ));
+        drop(spill);
+
+        let (spill, leftovers) = SpillBuffer::open(dir_str, 0).unwrap();
+        assert_eq!(leftovers.len(), 1);
+        assert_eq!(leftovers[0].ticket, third);
+        assert!(matches!(
+            leftovers[0].records.as_slice(),
+            [EncodedRecord::Proto(payload)] if payload == b"c"
+        ));
+
+        // Once everything is acknowledged no segment is left behind, so nothing is replayed
+        let fourth = spill.append(&proto_records(&[b"d"])).unwrap();
+        for leftover in leftovers {
+            spill.release(leftover.ticket);
+        }
+        spill.release(fourth);
+        drop(spill);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        let (spill, leftovers) = SpillBuffer::open(dir_str, 0).unwrap();
+        assert!(leftovers.is_empty());
+
+        drop(spill);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_spill_buffer_reads_back_held_records() {
+        let dir = spill_test_dir("read");
+        let dir_str = dir.to_str().unwrap();
+
+        let (spill, _) = SpillBuffer::open(dir_str, 0).unwrap();
+        let first = spill.append(&proto_records(&[b"a", b"bc"])).unwrap();
+        let second = spill
+            .append(&[EncodedRecord::Json("{\"id\": 1}".to_string())])
+            .unwrap();
+        assert_eq!(spill.read(&first).unwrap().len(), 2);
+        assert!(matches!(
+            spill.read(&second).unwrap().as_slice(),
+            [EncodedRecord::Json(json)] if json == "{\"id\": 1}"
+        ));
+        drop(spill);
+
+        // Tickets of replayed records point at the same bytes
+        let (spill, leftovers) = SpillBuffer::open(dir_str, 0).unwrap();
+        assert_eq!(leftovers.len(), 1);
+        let records = spill.read(&leftovers[0].ticket).unwrap();
+        assert_eq!(records.len(), 3);
+        assert!(matches!(&records[1], EncodedRecord::Proto(payload) if payload == b"bc"));
+
+        drop(spill);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_spill_buffer_size_cap() {
+        let dir = spill_test_dir("cap");
+        let (spill, _) = SpillBuffer::open(dir.to_str().unwrap(), 64).unwrap();
+
+        spill.append(&proto_records(&[&[0; 32]])).unwrap();
+        let err = spill.append(&proto_records(&[&[0; 32]])).unwrap_err();
+        assert!(matches!(err, SpillError::Full { max_bytes: 64 }));
+
+        let mut result = CResult::success();
+        assert_eq!(
+            write_ingest_result(&mut result, Err(IngestError::Spill(err))),
+            0
+        );
+        assert_eq!(result.error_code, CErrorCode::SpillFull);
+        assert!(result.is_retryable);
+        zerobus_free_error_message(result.error_message);
+
+        drop(spill);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_spill_dir_used_by_one_stream_at_a_time() {
+        let dir = spill_test_dir("exclusive");
+        let dir_str = dir.to_str().unwrap();
+
+        let (spill, _) = SpillBuffer::open(dir_str, 0).unwrap();
+        let err = SpillBuffer::open(dir_str, 0).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+        drop(spill);
+        assert!(SpillBuffer::open(dir_str, 0).is_ok());
+
+        // Through the stream options
+        let spill_dir = CString::new(dir_str).unwrap();
+        let mut config = zerobus_get_default_config();
+        assert!(open_spill(Some(&config)).unwrap().0.is_none());
+        config.spill_dir = spill_dir.as_ptr();
+        let (spill, leftovers) = open_spill(Some(&config)).unwrap();
+        assert!(spill.is_some() && leftovers.is_empty());
+        assert!(open_spill(Some(&config)).is_err());
+
+        drop(spill);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_spill_segment() {
+        let mut data = b"ZBSPILL1".to_vec();
+        data.extend_from_slice(&[1, 2, 0, 0, 0, 0xAB, 0xCD]);
+        data.extend_from_slice(&[2, 2, 0, 0, 0]);
+        data.extend_from_slice(b"{}");
+        let records = parse_segment(&data).unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(matches!(&records[1], EncodedRecord::Json(json) if json == "{}"));
+
+        // A record cut short by a crash is ignored
+        data.extend_from_slice(&[1, 10, 0, 0, 0, 1, 2]);
+        assert_eq!(parse_segment(&data).unwrap().len(), 2);
+        assert!(parse_segment(b"ZBSP").unwrap().is_empty());
+
+        assert!(parse_segment(b"not a segment").is_err());
+        let mut bad_kind = b"ZBSPILL1".to_vec();
+        bad_kind.extend_from_slice(&[9, 0, 0, 0, 0]);
+        assert!(parse_segment(&bad_kind).is_err());
+    }
+
+    #[test]
+    fn test_write_ingest_result_spilled() {
+        let mut result = CResult::success();
+        let err = ZerobusError::StreamClosedError(tonic::Status::internal("stream failed"));
+        assert_eq!(
+            write_ingest_result(&mut result, Err(IngestError::Spilled(err))),
+            0
+        );
+        assert_eq!(result.error_code, CErrorCode::Spilled);
+        assert!(!result.is_retryable);
+        zerobus_free_error_message(result.error_message);
+    }
+
+    // ========================================================================
+    // Dead-Letter Tests
+    // ========================================================================
+
+    #[test]
+    fn test_dead_letter_line() {
+        let err = ZerobusError::InvalidArgument("bad record".to_string());
+        let line = dead_letter_line(3, &EncodedRecord::Proto(vec![0x08, 0x96, 0x01]), &err);
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["ack_id"], 3);
+        assert_eq!(value["record_type"], "proto");
+        assert_eq!(value["payload"], "CJYB");
+        assert_eq!(value["error_code"], CErrorCode::InvalidArgument as i32);
+        assert!(value["error_message"]
+            .as_str()
+            .unwrap()
+            .contains("bad record"));
+
+        let line = dead_letter_line(4, &EncodedRecord::Json("{\"id\": 1}".to_string()), &err);
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["record_type"], "json");
+        assert_eq!(value["payload"], "{\"id\": 1}");
+    }
+
+    #[test]
+    fn test_dead_letter_sink_delivers_permanent_failures() {
+        static DELIVERED: std::sync::Mutex<Vec<(u64, i32, Vec<u8>, CErrorCode)>> =
+            std::sync::Mutex::new(Vec::new());
+        extern "C" fn on_dead_letter(
+            _user_data: *mut std::ffi::c_void,
+            ack_id: u64,
+            record_type: i32,
+            data: *const u8,
+            data_len: usize,
+            result: *const CResult,
+        ) {
+            let data = unsafe { std::slice::from_raw_parts(data, data_len) }.to_vec();
+            let error_code = unsafe { (*result).error_code };
+            DELIVERED
+                .lock()
+                .unwrap()
+                .push((ack_id, record_type, data, error_code));
+        }
+
+        let dir = spill_test_dir("dead-letter");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("dead.jsonl");
+        let sink = DeadLetterSink::new(
+            Some(on_dead_letter),
+            ptr::null_mut(),
+            Some(path.to_str().unwrap()),
+        )
+        .unwrap()
+        .unwrap();
+
+        let records = proto_records(&[b"a", b"b"]);
+        let tracker = CallbackTracker::default();
+        let err = ZerobusError::StreamClosedError(tonic::Status::invalid_argument("rejected"));
+        assert!(sink.deliver(7, &records, &err, &tracker));
+        // Retryable failures are recovered by recreating the stream instead
+        let retryable = ZerobusError::StreamClosedError(tonic::Status::unavailable("x"));
+        assert!(!sink.deliver(8, &records, &retryable, &tracker));
+        // So are the records of a stream that failed for good as a whole
+        let stream_failure = ZerobusError::StreamClosedError(tonic::Status::unauthenticated("x"));
+        assert!(!sink.deliver(8, &records, &stream_failure, &tracker));
+        assert!(sink.deliver(
+            10,
+            &[],
+            &ZerobusError::InvalidArgument("x".to_string()),
+            &tracker
+        ));
+        // The callback is not invoked once the stream is freed, but the file is still written
+        tracker.close();
+        assert!(sink.deliver(9, &records[..1], &err, &tracker));
+
+        let delivered = DELIVERED.lock().unwrap().clone();
+        assert_eq!(delivered.len(), 2);
+        assert_eq!(delivered[0], (7, 1, b"a".to_vec(), CErrorCode::from(&err)));
+        assert_eq!(delivered[1].2, b"b".to_vec());
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let ack_ids: Vec<u64> = contents
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()["ack_id"]
+                    .as_u64()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(ack_ids, vec![7, 7, 9]);
+
+        drop(sink);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_open_dead_letter_from_options() {
+        let mut config = zerobus_get_default_config();
+        assert!(open_dead_letter(Some(&config)).unwrap().is_none());
+        assert!(open_dead_letter(None).unwrap().is_none());
+
+        let missing = CString::new("/nonexistent-zerobus-dir/dead.jsonl").unwrap();
+        config.dead_letter_path = missing.as_ptr();
+        let err = open_dead_letter(Some(&config)).err().unwrap();
+        assert!(matches!(err, ZerobusError::InvalidArgument(_)));
+    }
+
+    // ========================================================================
+    // Mock Server
+    // ========================================================================
+
+    /// In-process Zerobus server that acknowledges every request and records what it received
+    #[derive(Clone, Default)]
+    struct MockZerobus {
+        requests: Arc<std::sync::Mutex<Vec<RequestPayload>>>,
+        /// A proto batch holding this record fails the stream with `reject_code`
+        reject: Option<Vec<u8>>,
+        /// Status code of the rejection (InvalidArgument if None)
+        reject_code: Option<tonic::Code>,
+        /// Only the first request holding `reject` is rejected, so a recovered stream succeeds
+        reject_once: bool,
+        /// Set once a request was rejected
+        rejected: Arc<AtomicBool>,
+        /// Ingest requests received after the rejected one before the rejection is sent, so
+        /// they are in flight when the stream fails
+        reject_after: usize,
+        /// Ingest requests are received but never acknowledged
+        stall: bool,
+    }
+
+    impl MockZerobus {
+        /// Records of the batch requests received so far, one entry per request
+        fn batches(&self) -> Vec<Batch> {
+            self.requests
+                .lock()
+                .unwrap()
+                .iter()
+                .filter_map(|request| match request {
+                    RequestPayload::IngestRecordBatch(batch) => batch.batch.clone(),
+                    _ => None,
+                })
+                .collect()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Zerobus for MockZerobus {
+        type EphemeralStreamStream = ReceiverStream<Result<EphemeralStreamResponse, Status>>;
+
+        async fn ephemeral_stream(
+            &self,
+            request: Request<Streaming<EphemeralStreamRequest>>,
+        ) -> Result<Response<Self::EphemeralStreamStream>, Status> {
+            let mut incoming = request.into_inner();
+            let (tx, rx) = tokio::sync::mpsc::channel(16);
+            let requests = Arc::clone(&self.requests);
+            let reject = self.reject.clone();
+            let reject_code = self.reject_code.unwrap_or(tonic::Code::InvalidArgument);
+            let reject_once = self.reject_once;
+            let already_rejected = Arc::clone(&self.rejected);
+            let reject_after = self.reject_after;
+            let stall = self.stall;
+            tokio::spawn(async move {
+                let mut held = None;
+                while let Ok(Some(request)) = incoming.message().await {
+                    let Some(payload) = request.payload else {
+                        continue;
+                    };
+                    if let Some(received) = held.as_mut() {
+                        requests.lock().unwrap().push(payload);
+                        *received += 1;
+                        if *received == reject_after {
+                            let _ = tx
+                                .send(Err(Status::new(reject_code, "record rejected")))
+                                .await;
+                            break;
+                        }
+                        continue;
+                    }
+                    let rejected = match (&payload, &reject) {
+                        (
+                            RequestPayload::IngestRecordBatch(IngestRecordBatchRequest {
+                                batch: Some(Batch::ProtoEncodedBatch(batch)),
+                                ..
+                            }),
+                            Some(reject),
+                        ) => {
+                            batch.records.contains(reject)
+                                && !(reject_once && already_rejected.swap(true, Ordering::SeqCst))
+                        }
+                        _ => false,
+                    };
+                    if rejected && reject_after > 0 {
+                        requests.lock().unwrap().push(payload);
+                        held = Some(0);
+                        continue;
+                    }
+                    if rejected {
+                        requests.lock().unwrap().push(payload);
+                        let _ = tx
+                            .send(Err(Status::new(reject_code, "record rejected")))
+                            .await;
+                        break;
+                    }
+                    if stall && !matches!(payload, RequestPayload::CreateStream(_)) {
+                        requests.lock().unwrap().push(payload);
+                        continue;
+                    }
+                    let reply = match &payload {
+                        RequestPayload::CreateStream(_) => {
+                            ResponsePayload::CreateStreamResponse(CreateIngestStreamResponse {
+                                stream_id: Some("mock-stream".to_string()),
+                            })
+                        }
+                        RequestPayload::IngestRecord(IngestRecordRequest { offset_id, .. })
+                        | RequestPayload::IngestRecordBatch(IngestRecordBatchRequest {
+                            offset_id,
+                            ..
+                        }) => ResponsePayload::IngestRecordResponse(IngestRecordResponse {
+                            durability_ack_up_to_offset: *offset_id,
+                        }),
+                    };
+                    requests.lock().unwrap().push(payload);
+                    let reply = EphemeralStreamResponse {
+                        payload: Some(reply),
+                    };
+                    if tx.send(Ok(reply)).await.is_err() {
+                        break;
+                    }
+                }
+            });
+            Ok(Response::new(ReceiverStream::new(rx)))
+        }
+    }
+
+    fn start_mock_server() -> (MockZerobus, *mut CZerobusSdk) {
+        serve_mock(MockZerobus::default())
+    }
+
+    /// Serve a mock on a local port and return an SDK pointed at it
+    fn serve_mock(mock: MockZerobus) -> (MockZerobus, *mut CZerobusSdk) {
+        let listener = runtime()
+            .block_on(tokio::net::TcpListener::bind("127.0.0.1:0"))
+            .unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        runtime().spawn(
+            Server::builder()
+                .add_service(ZerobusServer::new(mock.clone()))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let endpoint = CString::new(endpoint).unwrap();
+        let catalog_url = CString::new("http://localhost").unwrap();
+        let mut result = CResult::success();
+        let sdk = zerobus_sdk_new(endpoint.as_ptr(), catalog_url.as_ptr(), &mut result);
+        assert!(result.success);
+        zerobus_sdk_set_use_tls(sdk, false);
+        (mock, sdk)
+    }
+
+    extern "C" fn no_headers(_user_data: *mut std::ffi::c_void) -> CHeaders {
+        CHeaders {
+            headers: ptr::null_mut(),
+            count: 0,
+            error_message: ptr::null_mut(),
+        }
+    }
+
+    /// Open a stream on the mock server, with the descriptor for proto streams
+    fn mock_stream(
+        sdk: *mut CZerobusSdk,
+        descriptor: Option<&DescriptorProto>,
+        options: &CStreamConfigurationOptions,
+    ) -> *mut CZerobusStream {
+        let table_name = CString::new("main.default.events").unwrap();
+        let descriptor = descriptor.map(|descriptor| descriptor.encode_to_vec());
+        let (descriptor_ptr, descriptor_len) = match &descriptor {
+            Some(bytes) => (bytes.as_ptr(), bytes.len()),
+            None => (ptr::null(), 0),
+        };
+        let mut result = CResult::success();
+        let stream = zerobus_sdk_create_stream_with_headers_provider(
+            sdk,
+            table_name.as_ptr(),
+            descriptor_ptr,
+            descriptor_len,
+            no_headers,
+            ptr::null_mut(),
+            options,
+            &mut result,
+        );
+        assert!(result.success, "{:?}", unsafe {
+            test_c_str_to_string(result.error_message)
+        });
+        stream
+    }
+
+    fn close_mock_stream(sdk: *mut CZerobusSdk, stream: *mut CZerobusStream) {
+        let mut result = CResult::success();
+        assert!(zerobus_stream_close(stream, &mut result));
+        zerobus_stream_free(stream);
+        zerobus_sdk_free(sdk);
+    }
+
+    /// Concatenate records into the data and offsets arguments of the batch functions
+    fn batch_buffer(records: &[&[u8]]) -> (Vec<u8>, Vec<usize>) {
+        let mut data = Vec::new();
+        let mut offsets = vec![0];
+        for record in records {
+            data.extend_from_slice(record);
+            offsets.push(data.len());
+        }
+        (data, offsets)
+    }
+
+    // message Event { int64 id = 1; string name = 2; }
+    fn event_descriptor() -> DescriptorProto {
+        message(
+            "Event",
+            vec![scalar_field("id", 1, 3, 1), scalar_field("name", 2, 9, 1)],
+        )
+    }
+
+    // ========================================================================
+    // Batch Ingestion Tests
+    // ========================================================================
+
+    #[test]
+    fn test_proto_batch_gets_one_ack_id() {
+        let (mock, sdk) = start_mock_server();
+        let stream = mock_stream(
+            sdk,
+            Some(&event_descriptor()),
+            &zerobus_get_default_config(),
+        );
+
+        let mut result = CResult::success();
+        let (data, offsets) = batch_buffer(&[&[0x08, 0x01], &[0x08, 0x02], &[0x08, 0x03]]);
+        let first = zerobus_stream_ingest_proto_batch(
+            stream,
+            data.as_ptr(),
+            data.len(),
+            offsets.as_ptr(),
+            3,
+            &mut result,
+        );
+        assert!(result.success);
+        let (data, offsets) = batch_buffer(&[&[0x08, 0x04], &[0x08, 0x05]]);
+        let second = zerobus_stream_ingest_proto_batch(
+            stream,
+            data.as_ptr(),
+            data.len(),
+            offsets.as_ptr(),
+            2,
+            &mut result,
+        );
+        assert!(result.success);
+        assert_eq!((first, second), (1, 2));
+
+        // Each batch is one request, acknowledged with a single offset
+        assert_eq!(zerobus_stream_await_ack(stream, first, &mut result), 0);
+        assert_eq!(zerobus_stream_await_ack(stream, second, &mut result), 1);
+        let sizes: Vec<_> = mock
+            .batches()
+            .iter()
+            .map(|batch| match batch {
+                Batch::ProtoEncodedBatch(batch) => batch.records.len(),
+                Batch::JsonBatch(_) => panic!("JSON batch on a proto stream"),
+            })
+            .collect();
+        assert_eq!(sizes, [3, 2]);
+
+        close_mock_stream(sdk, stream);
+    }
+
+    #[test]
+    fn test_batch_validation_error_names_the_record() {
+        let (mock, sdk) = start_mock_server();
+        let mut options = zerobus_get_default_config();
+        options.validate_records = true;
+        let stream = mock_stream(sdk, Some(&event_descriptor()), &options);
+
+        // Record 1 sends id as a length-delimited value
+        let (data, offsets) = batch_buffer(&[&[0x08, 0x01], &[0x0a, 0x01, 0x00]]);
+        let mut result = CResult::success();
+        let ack_id = zerobus_stream_ingest_proto_batch(
+            stream,
+            data.as_ptr(),
+            data.len(),
+            offsets.as_ptr(),
+            2,
+            &mut result,
+        );
+        assert_eq!(ack_id, 0);
+        assert_eq!(result.error_code, CErrorCode::InvalidArgument);
+        let msg = unsafe { test_c_str_to_string(result.error_message).unwrap() };
+        assert!(msg.contains("record 1: field 'id' (1):"), "{}", msg);
+        zerobus_free_error_message(result.error_message);
+
+        // Nothing of the rejected batch reaches the server
+        assert!(mock.batches().is_empty());
+        close_mock_stream(sdk, stream);
+    }
+
+    #[test]
+    fn test_json_batch_is_transcoded() {
+        let (mock, sdk) = start_mock_server();
+        let mut options = zerobus_get_default_config();
+        options.transcode_json = true;
+        let stream = mock_stream(sdk, Some(&event_descriptor()), &options);
+
+        let (data, offsets) = batch_buffer(&[b"{\"id\": 1, \"name\": \"a\"}", b"{\"id\": 2}"]);
+        let mut result = CResult::success();
+        let ack_id = zerobus_stream_ingest_json_batch(
+            stream,
+            data.as_ptr(),
+            data.len(),
+            offsets.as_ptr(),
+            2,
+            &mut result,
+        );
+        assert!(result.success);
+        assert_eq!(zerobus_stream_await_ack(stream, ack_id, &mut result), 0);
+        match mock.batches().as_slice() {
+            [Batch::ProtoEncodedBatch(batch)] => assert_eq!(
+                batch.records,
+                [vec![0x08, 0x01, 0x12, 0x01, b'a'], vec![0x08, 0x02]]
+            ),
+            _ => panic!("expected one proto batch"),
+        }
+
+        // A record that does not fit the descriptor is reported by its index
+        let (data, offsets) = batch_buffer(&[b"{\"id\": 3}", b"{\"unknown\": 1}"]);
+        let ack_id = zerobus_stream_ingest_json_batch(
+            stream,
+            data.as_ptr(),
+            data.len(),
+            offsets.as_ptr(),
+            2,
+            &mut result,
+        );
+        assert_eq!(ack_id, 0);
+        assert_eq!(result.error_code, CErrorCode::InvalidArgument);
+        let msg = unsafe { test_c_str_to_string(result.error_message).unwrap() };
+        assert!(msg.contains("record 1: unknown field 'unknown'"), "{}", msg);
+        zerobus_free_error_message(result.error_message);
+
+        close_mock_stream(sdk, stream);
+    }
+
+    #[test]
+    fn test_empty_batch_is_rejected() {
+        let (mock, sdk) = start_mock_server();
+        let mut options = zerobus_get_default_config();
+        options.record_type = 2; // RecordType::Json
+        let stream = mock_stream(sdk, None, &options);
+
+        let offsets = [0usize];
+        let mut result = CResult::success();
+        let ack_id = zerobus_stream_ingest_json_batch(
+            stream,
+            ptr::null(),
+            0,
+            offsets.as_ptr(),
+            0,
+            &mut result,
+        );
+        assert_eq!(ack_id, 0);
+        assert!(!result.success);
+        assert_eq!(result.error_code, CErrorCode::InvalidArgument);
+        let msg = unsafe { test_c_str_to_string(result.error_message).unwrap() };
+        assert_eq!(msg, "Batch must contain at least one record");
+        zerobus_free_error_message(result.error_message);
+
+        assert!(mock.batches().is_empty());
+        close_mock_stream(sdk, stream);
+    }
+
+    #[test]
+    fn test_try_ingest_record_buffer_keeps_buffer_when_saturated() {
+        let (_mock, sdk) = serve_mock(MockZerobus {
+            stall: true,
+            ..Default::default()
+        });
+        let mut options = zerobus_get_default_config();
+        options.max_inflight_requests = 1;
+        let stream = mock_stream(sdk, Some(&event_descriptor()), &options);
+
+        let mut result = CResult::success();
+        let record = [0x08, 0x01];
+        zerobus_stream_ingest_proto_record(stream, record.as_ptr(), record.len(), &mut result);
+        assert!(result.success);
+
+        // Neither call takes the buffer while the only slot is in use
+        let buffer = zerobus_record_buffer_alloc(2);
+        let ack_id =
+            zerobus_stream_try_ingest_proto_record_buffer(stream, buffer, 2, 2, &mut result);
+        assert_eq!(ack_id, 0);
+        assert_eq!(result.error_code, CErrorCode::WouldBlock);
+        zerobus_free_error_message(result.error_message);
+        let mut result = CResult::success();
+        zerobus_stream_ingest_proto_record_buffer_timeout(stream, buffer, 2, 2, 10, &mut result);
+        assert_eq!(result.error_code, CErrorCode::WouldBlock);
+        zerobus_free_error_message(result.error_message);
+        unsafe { std::slice::from_raw_parts_mut(buffer, 2) }.copy_from_slice(&record);
+        zerobus_record_buffer_free(buffer, 2);
+
+        zerobus_stream_free(stream);
+        zerobus_sdk_free(sdk);
+    }
+
+    #[test]
+    fn test_try_ingest_batch_when_saturated() {
+        let (_mock, sdk) = serve_mock(MockZerobus {
+            stall: true,
+            ..Default::default()
+        });
+        let mut options = zerobus_get_default_config();
+        options.max_inflight_requests = 1;
+        let stream = mock_stream(sdk, Some(&event_descriptor()), &options);
+
+        let mut result = CResult::success();
+        let (data, offsets) = batch_buffer(&[&[0x08, 0x01], &[0x08, 0x02]]);
+        let ack_id = zerobus_stream_try_ingest_proto_batch(
+            stream,
+            data.as_ptr(),
+            data.len(),
+            offsets.as_ptr(),
+            2,
+            &mut result,
+        );
+        assert_eq!(ack_id, 1);
+        assert!(result.success);
+
+        // The only slot is taken, so neither call queues its batch
+        let ack_id = zerobus_stream_try_ingest_proto_batch(
+            stream,
+            data.as_ptr(),
+            data.len(),
+            offsets.as_ptr(),
+            2,
+            &mut result,
+        );
+        assert_eq!(ack_id, 0);
+        assert_eq!(result.error_code, CErrorCode::WouldBlock);
+        zerobus_free_error_message(result.error_message);
+        let mut result = CResult::success();
+        zerobus_stream_ingest_proto_batch_timeout(
+            stream,
+            data.as_ptr(),
+            data.len(),
+            offsets.as_ptr(),
+            2,
+            10,
+            &mut result,
+        );
+        assert_eq!(result.error_code, CErrorCode::WouldBlock);
+        assert!(result.is_retryable);
+        zerobus_free_error_message(result.error_message);
+        let mut metrics = CStreamMetrics::default();
+        assert!(zerobus_stream_get_metrics(
+            stream,
+            &mut metrics,
+            &mut result
+        ));
+        assert_eq!(metrics.records_ingested, 2);
+
+        zerobus_stream_free(stream);
+        zerobus_sdk_free(sdk);
+    }
+
+    #[test]
+    fn test_try_ingest_json_batch_when_saturated() {
+        let (_mock, sdk) = serve_mock(MockZerobus {
+            stall: true,
+            ..Default::default()
+        });
+        let mut options = zerobus_get_default_config();
+        options.max_inflight_requests = 1;
+        options.record_type = 2; // RecordType::Json
+        let stream = mock_stream(sdk, None, &options);
+
+        let mut result = CResult::success();
+        let (data, offsets) = batch_buffer(&[b"{\"id\": 1}", b"{\"id\": 2}"]);
+        let ack_id = zerobus_stream_ingest_json_batch_timeout(
+            stream,
+            data.as_ptr(),
+            data.len(),
+            offsets.as_ptr(),
+            2,
+            1000,
+            &mut result,
+        );
+        assert_eq!(ack_id, 1);
+        assert!(result.success);
+
+        let ack_id = zerobus_stream_try_ingest_json_batch(
+            stream,
+            data.as_ptr(),
+            data.len(),
+            offsets.as_ptr(),
+            2,
+            &mut result,
+        );
+        assert_eq!(ack_id, 0);
+        assert_eq!(result.error_code, CErrorCode::WouldBlock);
+        zerobus_free_error_message(result.error_message);
+        let mut metrics = CStreamMetrics::default();
+        assert!(zerobus_stream_get_metrics(
+            stream,
+            &mut metrics,
+            &mut result
+        ));
+        assert_eq!(metrics.records_ingested, 2);
+
+        zerobus_stream_free(stream);
+        zerobus_sdk_free(sdk);
+    }
+
+    #[test]
+    fn test_try_ingest_does_not_wait_past_deadline_for_busy_lock() {
+        let (_mock, sdk) = start_mock_server();
+        let stream = mock_stream(
+            sdk,
+            Some(&event_descriptor()),
+            &zerobus_get_default_config(),
+        );
+        let record = [0x08, 0x01];
+
+        // Another ingest call holds the stream while it is far below its limit
+        let guard = validate_stream_ptr(stream)
+            .unwrap()
+            .ingest_lock
+            .lock()
+            .unwrap();
+        let mut result = CResult::success();
+        let started = std::time::Instant::now();
+        zerobus_stream_try_ingest_proto_record(stream, record.as_ptr(), record.len(), &mut result);
+        assert_eq!(result.error_code, CErrorCode::WouldBlock);
+        zerobus_free_error_message(result.error_message);
+        let mut result = CResult::success();
+        zerobus_stream_ingest_proto_record_timeout(
+            stream,
+            record.as_ptr(),
+            record.len(),
+            20,
+            &mut result,
+        );
+        assert_eq!(result.error_code, CErrorCode::WouldBlock);
+        zerobus_free_error_message(result.error_message);
+        assert!(started.elapsed() < std::time::Duration::from_millis(80));
+        drop(guard);
+
+        // Once the lock is free the record is queued
+        let mut result = CResult::success();
+        let ack_id = zerobus_stream_try_ingest_proto_record(
+            stream,
+            record.as_ptr(),
+            record.len(),
+            &mut result,
+        );
+        assert!(result.success);
+        assert_eq!(zerobus_stream_await_ack(stream, ack_id, &mut result), 0);
+        close_mock_stream(sdk, stream);
+    }
+
+    #[test]
+    fn test_concurrent_stream_free_releases_once() {
+        let (_mock, sdk) = start_mock_server();
+        let stream = mock_stream(
+            sdk,
+            Some(&event_descriptor()),
+            &zerobus_get_default_config(),
+        );
+
+        let stream_addr = stream as usize;
+        let barrier = Arc::new(std::sync::Barrier::new(4));
+        let frees: Vec<_> = (0..4)
+            .map(|_| {
+                let barrier = Arc::clone(&barrier);
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    zerobus_stream_free(stream_addr as *mut CZerobusStream);
+                })
+            })
+            .collect();
+        for free in frees {
+            free.join().unwrap();
+        }
+
+        zerobus_stream_free(ptr::null_mut());
+        zerobus_sdk_free(sdk);
+    }
+
+    // ========================================================================
+    // Stream Recovery Tests
+    // ========================================================================
+
+    #[test]
+    fn test_metrics_count_stream_recoveries() {
+        let flaky_record = vec![0x08, 0x07];
+        let (mock, sdk) = serve_mock(MockZerobus {
+            reject: Some(flaky_record.clone()),
+            reject_code: Some(tonic::Code::Unavailable),
+            reject_once: true,
+            ..Default::default()
+        });
+        let mut options = zerobus_get_default_config();
+        options.recovery_backoff_ms = 10;
+        let stream = mock_stream(sdk, Some(&event_descriptor()), &options);
+        let healthy = mock_stream(sdk, Some(&event_descriptor()), &options);
+
+        // The SDK reopens the stream and resends the rejected batch
+        let mut result = CResult::success();
+        let (data, offsets) = batch_buffer(&[&[0x08, 0x01], &flaky_record]);
+        let ack_id = zerobus_stream_ingest_proto_batch(
+            stream,
+            data.as_ptr(),
+            data.len(),
+            offsets.as_ptr(),
+            2,
+            &mut result,
+        );
+        assert!(result.success);
+        assert_eq!(zerobus_stream_await_ack(stream, ack_id, &mut result), 0);
+        assert!(result.success);
+        assert!(mock.rejected.load(Ordering::SeqCst));
+
+        let mut metrics = CStreamMetrics::default();
+        assert!(zerobus_stream_get_metrics(
+            stream,
+            &mut metrics,
+            &mut result
+        ));
+        assert_eq!(metrics.recoveries, 1);
+        assert_eq!(metrics.records_acked, 2);
+        assert!(zerobus_stream_get_metrics(
+            healthy,
+            &mut metrics,
+            &mut result
+        ));
+        assert_eq!(metrics.recoveries, 0);
+
+        assert!(zerobus_stream_close(healthy, &mut result));
+        zerobus_stream_free(healthy);
+        close_mock_stream(sdk, stream);
+    }
+
+    // ========================================================================
+    // Dead-Letter Recreation Tests
+    // ========================================================================
+
+    #[test]
+    fn test_recreate_skips_dead_lettered_batches() {
+        let rejected_record = vec![0x08, 0x63];
+        let (mock, sdk) = serve_mock(MockZerobus {
+            reject: Some(rejected_record.clone()),
+            ..Default::default()
+        });
+        let dir = spill_test_dir("recreate-dead-letter");
+        std::fs::create_dir_all(&dir).unwrap();
+        let spill_dir = CString::new(dir.join("spill").to_str().unwrap()).unwrap();
+        let dead_letters = dir.join("dead.jsonl");
+        let dead_letter_path = CString::new(dead_letters.to_str().unwrap()).unwrap();
+        let mut options = zerobus_get_default_config();
+        options.spill_dir = spill_dir.as_ptr();
+        options.dead_letter_path = dead_letter_path.as_ptr();
+        let stream = mock_stream(sdk, Some(&event_descriptor()), &options);
+
+        let mut result = CResult::success();
+        let (data, offsets) = batch_buffer(&[&[0x08, 0x01]]);
+        let acked = zerobus_stream_ingest_proto_batch(
+            stream,
+            data.as_ptr(),
+            data.len(),
+            offsets.as_ptr(),
+            1,
+            &mut result,
+        );
+        assert_eq!(zerobus_stream_await_ack(stream, acked, &mut result), 0);
+        let (data, offsets) = batch_buffer(&[&[0x08, 0x02], &rejected_record]);
+        let rejected = zerobus_stream_ingest_proto_batch(
+            stream,
+            data.as_ptr(),
+            data.len(),
+            offsets.as_ptr(),
+            2,
+            &mut result,
+        );
+        assert!(result.success);
+        assert_eq!(zerobus_stream_await_ack(stream, rejected, &mut result), -1);
+        assert_eq!(result.error_code, CErrorCode::InvalidArgument);
+        zerobus_free_error_message(result.error_message);
+
+        // The SDK records the failed batch shortly after failing its ack
+        for _ in 0..100 {
+            let unacked = zerobus_stream_get_unacked_records(stream, &mut result);
+            let count = unacked.count;
+            zerobus_free_unacked_records(unacked);
+            if count > 0 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        let mut mappings = CAckIdMappings::empty();
+        let new_stream = zerobus_sdk_recreate_stream(sdk, stream, 5000, &mut mappings, &mut result);
+        assert!(result.success);
+        // The dead-lettered batch is neither mapped nor sent again
+        assert_eq!(mappings.count, 0);
+        zerobus_free_ack_id_mappings(mappings);
+        let sent = mock
+            .batches()
+            .iter()
+            .filter(|batch| {
+                matches!(batch, Batch::ProtoEncodedBatch(batch) if batch.records.contains(&rejected_record))
+            })
+            .count();
+        assert_eq!(sent, 1);
+
+        let contents = std::fs::read_to_string(&dead_letters).unwrap();
+        assert_eq!(contents.lines().count(), 2);
+        zerobus_stream_free(stream);
+        close_mock_stream(sdk, new_stream);
+
+        // Its records were dropped from the spill buffer as well
+        let (_spill, leftovers) = SpillBuffer::open(spill_dir.to_str().unwrap(), 0).unwrap();
+        assert!(leftovers.is_empty());
+        drop(_spill);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_recreate_fails_if_requests_do_not_settle() {
+        let failing_record = vec![0x08, 0x63];
+        let (_mock, sdk) = serve_mock(MockZerobus {
+            reject: Some(failing_record.clone()),
+            reject_once: true,
+            ..Default::default()
+        });
+        let stream = mock_stream(
+            sdk,
+            Some(&event_descriptor()),
+            &zerobus_get_default_config(),
+        );
+
+        let mut result = CResult::success();
+        let (data, offsets) = batch_buffer(&[&[0x08, 0x02], &failing_record]);
+        let failed = zerobus_stream_ingest_proto_batch(
+            stream,
+            data.as_ptr(),
+            data.len(),
+            offsets.as_ptr(),
+            2,
+            &mut result,
+        );
+        assert_eq!(zerobus_stream_await_ack(stream, failed, &mut result), -1);
+        zerobus_free_error_message(result.error_message);
+
+        // A request of the old stream that has not settled yet
+        let inflight = &validate_stream_ptr(stream).unwrap().inflight;
+        inflight.acquire();
+        let mut mappings = CAckIdMappings::empty();
+        let mut result = CResult::success();
+        let new_stream = zerobus_sdk_recreate_stream(sdk, stream, 50, &mut mappings, &mut result);
+        assert!(new_stream.is_null());
+        assert_eq!(result.error_code, CErrorCode::WaitTimedOut);
+        assert!(result.is_retryable);
+        zerobus_free_error_message(result.error_message);
+
+        // Once it has settled, recreation goes through
+        inflight.release();
+        let new_stream = zerobus_sdk_recreate_stream(sdk, stream, 5000, &mut mappings, &mut result);
+        assert!(result.success);
+        assert_eq!(mappings.count, 1);
+        zerobus_free_ack_id_mappings(mappings);
+        zerobus_stream_free(stream);
+        close_mock_stream(sdk, new_stream);
+    }
+
+    #[test]
+    fn test_recreate_resends_batches_failed_with_a_rejected_one() {
+        let rejected_record = vec![0x08, 0x63];
+        let valid_record = vec![0x08, 0x03];
+        let (mock, sdk) = serve_mock(MockZerobus {
+            reject: Some(rejected_record.clone()),
+            reject_after: 1,
+            ..Default::default()
+        });
+        let dir = spill_test_dir("recreate-rejected-neighbour");
+        std::fs::create_dir_all(&dir).unwrap();
+        let spill_dir = CString::new(dir.join("spill").to_str().unwrap()).unwrap();
+        let dead_letters = dir.join("dead.jsonl");
+        let dead_letter_path = CString::new(dead_letters.to_str().unwrap()).unwrap();
+        let mut options = zerobus_get_default_config();
+        options.spill_dir = spill_dir.as_ptr();
+        options.dead_letter_path = dead_letter_path.as_ptr();
+        let stream = mock_stream(sdk, Some(&event_descriptor()), &options);
+
+        let mut result = CResult::success();
+        let (data, offsets) = batch_buffer(&[&[0x08, 0x02], &rejected_record]);
+        let rejected = zerobus_stream_ingest_proto_batch(
+            stream,
+            data.as_ptr(),
+            data.len(),
+            offsets.as_ptr(),
+            2,
+            &mut result,
+        );
+        assert!(result.success);
+        // Still in flight when the server rejects the batch before it
+        let (data, offsets) = batch_buffer(&[&[0x08, 0x04], &valid_record]);
+        let valid = zerobus_stream_ingest_proto_batch(
+            stream,
+            data.as_ptr(),
+            data.len(),
+            offsets.as_ptr(),
+            2,
+            &mut result,
+        );
+        assert!(result.success);
+        assert_eq!(zerobus_stream_await_ack(stream, rejected, &mut result), -1);
+        assert_eq!(result.error_code, CErrorCode::InvalidArgument);
+        zerobus_free_error_message(result.error_message);
+        // The SDK fails the valid batch with the same error
+        assert_eq!(zerobus_stream_await_ack(stream, valid, &mut result), -1);
+        assert_eq!(result.error_code, CErrorCode::InvalidArgument);
+        zerobus_free_error_message(result.error_message);
+
+        // The SDK records the failed batches shortly after failing their acks
+        for _ in 0..100 {
+            let unacked = zerobus_stream_get_unacked_records(stream, &mut result);
+            let count = unacked.count;
+            zerobus_free_unacked_records(unacked);
+            if count == 4 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        let mut mappings = CAckIdMappings::empty();
+        let new_stream = zerobus_sdk_recreate_stream(sdk, stream, 5000, &mut mappings, &mut result);
+        assert!(result.success);
+        // Only the valid batch is sent again
+        assert_eq!(mappings.count, 1);
+        let mapping = unsafe { *mappings.mappings };
+        assert_eq!(mapping.old_ack_id, valid);
+        zerobus_free_ack_id_mappings(mappings);
+        assert!(zerobus_stream_await_ack(new_stream, mapping.new_ack_id, &mut result) >= 0);
+        let sent = |record: &Vec<u8>| {
+            mock.batches()
+                .iter()
+                .filter(|batch| {
+                    matches!(batch, Batch::ProtoEncodedBatch(batch) if batch.records.contains(record))
+                })
+                .count()
+        };
+        assert_eq!(sent(&rejected_record), 1);
+        assert_eq!(sent(&valid_record), 2);
+
+        // Only the rejected batch's records are dead letters
+        let contents = std::fs::read_to_string(&dead_letters).unwrap();
+        assert_eq!(contents.lines().count(), 2);
+        assert!(contents
+            .lines()
+            .all(|line| line.contains(&format!("\"ack_id\":{}", rejected))));
+        zerobus_stream_free(stream);
+        close_mock_stream(sdk, new_stream);
+
+        let (_spill, leftovers) = SpillBuffer::open(spill_dir.to_str().unwrap(), 0).unwrap();
+        assert!(leftovers.is_empty());
+        drop(_spill);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_recreate_resends_batches_of_failed_stream() {
+        let failing_record = vec![0x08, 0x63];
+        let (_mock, sdk) = serve_mock(MockZerobus {
+            reject: Some(failing_record.clone()),
+            reject_code: Some(tonic::Code::PermissionDenied),
+            ..Default::default()
+        });
+        let dir = spill_test_dir("recreate-failed-stream");
+        std::fs::create_dir_all(&dir).unwrap();
+        let dead_letters = dir.join("dead.jsonl");
+        let dead_letter_path = CString::new(dead_letters.to_str().unwrap()).unwrap();
+        let mut options = zerobus_get_default_config();
+        options.dead_letter_path = dead_letter_path.as_ptr();
+        let stream = mock_stream(sdk, Some(&event_descriptor()), &options);
+
+        let mut result = CResult::success();
+        let (data, offsets) = batch_buffer(&[&[0x08, 0x02], &failing_record]);
+        let failed = zerobus_stream_ingest_proto_batch(
+            stream,
+            data.as_ptr(),
+            data.len(),
+            offsets.as_ptr(),
+            2,
+            &mut result,
+        );
+        assert!(result.success);
+        assert_eq!(zerobus_stream_await_ack(stream, failed, &mut result), -1);
+        assert_eq!(result.error_code, CErrorCode::AuthFailure);
+        assert!(!result.is_retryable);
+        zerobus_free_error_message(result.error_message);
+
+        for _ in 0..100 {
+            let unacked = zerobus_stream_get_unacked_records(stream, &mut result);
+            let count = unacked.count;
+            zerobus_free_unacked_records(unacked);
+            if count > 0 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        // The stream failed as a whole, so its batch is sent again rather than dead-lettered
+        let (healthy, healthy_sdk) = start_mock_server();
+        let mut mappings = CAckIdMappings::empty();
+        let new_stream =
+            zerobus_sdk_recreate_stream(healthy_sdk, stream, 5000, &mut mappings, &mut result);
+        assert!(result.success);
+        assert_eq!(mappings.count, 1);
+        let mapping = unsafe { &*mappings.mappings };
+        assert_eq!(mapping.old_ack_id, failed);
+        assert_eq!(
+            zerobus_stream_await_ack(new_stream, mapping.new_ack_id, &mut result),
+            0
+        );
+        zerobus_free_ack_id_mappings(mappings);
+        let resent = healthy.batches().iter().any(|batch| {
+            matches!(batch, Batch::ProtoEncodedBatch(batch) if batch.records.contains(&failing_record))
+        });
+        assert!(resent);
+        assert!(std::fs::read_to_string(&dead_letters).unwrap().is_empty());
+
+        zerobus_stream_free(stream);
+        zerobus_sdk_free(sdk);
+        close_mock_stream(healthy_sdk, new_stream);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     // ========================================================================
     // Thread Safety Tests
     // ========================================================================
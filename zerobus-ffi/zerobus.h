@@ -18,6 +18,125 @@
 namespace zerobus {
 #endif  // __cplusplus
 
+/**
+ * Stable numeric error codes reported in CResult
+ * Values are part of the C ABI: never renumber, only append
+ */
+enum CErrorCode
+#ifdef __cplusplus
+  : int32_t
+#endif // __cplusplus
+ {
+  CErrorCode_Ok = 0,
+  /**
+   * Failure inside the FFI layer itself (e.g. a panicked task or a runtime that cannot start)
+   */
+  CErrorCode_Internal = 1,
+  CErrorCode_InvalidArgument = 2,
+  /**
+   * Authentication or authorization failed (bad credentials, token or permissions)
+   */
+  CErrorCode_AuthFailure = 3,
+  /**
+   * The gRPC channel could not be opened
+   */
+  CErrorCode_ChannelError = 4,
+  CErrorCode_TlsError = 5,
+  CErrorCode_InvalidEndpoint = 6,
+  CErrorCode_InvalidTableName = 7,
+  /**
+   * The server refused to create the stream
+   */
+  CErrorCode_CreateStreamFailed = 8,
+  CErrorCode_StreamClosed = 9,
+  /**
+   * A deadline was exceeded (flush timeout, server ack timeout)
+   */
+  CErrorCode_Timeout = 10,
+  CErrorCode_UnexpectedResponse = 11,
+  /**
+   * The call is not allowed in the current state (e.g. the runtime has already started)
+   */
+  CErrorCode_InvalidState = 12,
+  /**
+   * The stream could not take the request without blocking or before the timeout, see
+   * zerobus_stream_try_ingest_proto_record
+   */
+  CErrorCode_WouldBlock = 13,
+  /**
+   * zerobus_stream_await_ack_timeout gave up; the acknowledgment is still pending
+   * Also returned by zerobus_sdk_recreate_stream when the old stream's requests did not settle
+   * within settle_timeout_ms
+   */
+  CErrorCode_WaitTimedOut = 14,
+  /**
+   * The stream had failed, but the records were persisted in its spill directory and are
+   * queued again when it is recreated: they must not be ingested again
+   */
+  CErrorCode_Spilled = 15,
+  /**
+   * The spill directory reached spill_max_bytes
+   */
+  CErrorCode_SpillFull = 16,
+};
+#ifndef __cplusplus
+typedef int32_t CErrorCode;
+#endif // __cplusplus
+
+/**
+ * Severity of a log event passed to the log callback
+ * Also used as the minimum level when registering the callback (Off disables logging)
+ */
+enum CLogLevel
+#ifdef __cplusplus
+  : int32_t
+#endif // __cplusplus
+ {
+  CLogLevel_Off = 0,
+  CLogLevel_Error = 1,
+  CLogLevel_Warn = 2,
+  CLogLevel_Info = 3,
+  CLogLevel_Debug = 4,
+  CLogLevel_Trace = 5,
+};
+#ifndef __cplusplus
+typedef int32_t CLogLevel;
+#endif // __cplusplus
+
+/**
+ * Configuration of the global Tokio runtime, see zerobus_runtime_init
+ * Zero / NULL fields keep Tokio's defaults
+ */
+typedef struct CRuntimeConfig {
+  /**
+   * Number of worker threads (0 = one per CPU core); ignored for a current-thread runtime
+   */
+  uintptr_t worker_threads;
+  /**
+   * Runtime threads are named "<prefix>-<n>" (NULL = Tokio's default names)
+   */
+  const char *thread_name_prefix;
+  /**
+   * Stack size of runtime threads in bytes (0 = Tokio default)
+   */
+  uintptr_t thread_stack_size;
+  /**
+   * Upper bound of the blocking thread pool (0 = Tokio default)
+   */
+  uintptr_t max_blocking_threads;
+  /**
+   * Run all tasks on a single background thread instead of a worker pool
+   */
+  bool current_thread;
+} CRuntimeConfig;
+
+typedef struct CResult {
+  bool success;
+  char *error_message;
+  bool is_retryable;
+  CErrorCode error_code;
+} CResult;
+
 /**
  * A single header key-value pair for C FFI
  */
@@ -39,16 +158,45 @@ typedef struct CZerobusSdk {
   uint8_t _private[0];
 } CZerobusSdk;
 
-typedef struct CResult {
-  bool success;
-  char *error_message;
-  bool is_retryable;
-} CResult;
+/**
+ * A byte buffer allocated by the FFI layer, released with zerobus_free_bytes
+ */
+typedef struct CBytes {
+  uint8_t *data;
+  uintptr_t len;
+} CBytes;
 
 typedef struct CZerobusStream {
   uint8_t _private[0];
 } CZerobusStream;
 
+/**
+ * Function pointer type for the acknowledgment callback (nullable)
+ * Invoked from a runtime worker thread as soon as a record is acknowledged or fails
+ * offset is -1 on failure; result (and its error_message) is only valid during the call
+ * Freeing the stream waits for callbacks in progress and drops later ones, so the callback
+ * must not free the stream itself
+ */
+typedef void (*AckCallback)(void *user_data,
+                            uint64_t ack_id,
+                            int64_t offset,
+                            const struct CResult *result);
+
+/**
+ * Function pointer type for the dead-letter callback (nullable)
+ * Invoked from a runtime worker thread once per record of a request the server rejected as
+ * invalid; record_type is 1 for proto and 2 for JSON records
+ * data and result (and its error_message) are only valid during the call
+ * Freeing the stream waits for callbacks in progress and drops later ones, so the callback
+ * must not free the stream itself
+ */
+typedef void (*DeadLetterCallback)(void *user_data,
+                                   uint64_t ack_id,
+                                   int32_t record_type,
+                                   const uint8_t *data,
+                                   uintptr_t data_len,
+                                   const struct CResult *result);
+
 typedef struct CStreamConfigurationOptions {
   uintptr_t max_inflight_requests;
   bool recovery;
@@ -58,6 +206,66 @@ typedef struct CStreamConfigurationOptions {
   uint64_t server_lack_of_ack_timeout_ms;
   uint64_t flush_timeout_ms;
   int32_t record_type;
+  /**
+   * Optional callback receiving every acknowledgment (NULL to await acks by ID instead)
+   */
+  AckCallback ack_callback;
+  /**
+   * Opaque pointer passed back to ack_callback
+   */
+  void *ack_callback_user_data;
+  /**
+   * Do not track per-record acknowledgments; ingest calls return ack ID 0 and
+   * record failures are reported by the next flush or close
+   */
+  bool fire_and_forget;
+  /**
+   * Check every record before queueing it and reject malformed records with an
+   * InvalidArgument error naming the offending field: proto records are decoded against the
+   * stream's descriptor, JSON records must be JSON objects matching table_schema_json if set
+   */
+  bool validate_records;
+  /**
+   * Accept JSON records on a proto stream: they are parsed with the proto3 JSON mapping of
+   * the stream's descriptor and sent as protobuf records; conversion errors fail the ingest call
+   */
+  bool transcode_json;
+  /**
+   * Columns JSON records are validated against (NULL = only require a JSON object), as a JSON
+   * array of {"name", "type", "nullable"} objects with Delta types such as "BIGINT" or
+   * "ARRAY<STRING>"; requires validate_records on a JSON stream. Only read during stream creation
+   */
+  const char *table_schema_json;
+  /**
+   * Directory records are persisted in before being queued (NULL = no spill buffer); records
+   * left there by an earlier stream are queued again when the stream is created, and records
+   * of a failed stream when it is recreated. One stream at a time may use a directory. Only
+   * read during stream creation
+   */
+  const char *spill_dir;
+  /**
+   * Size cap of the spill directory in bytes (0 = no cap); ingest calls fail with SpillFull
+   * once it is reached
+   */
+  uint64_t spill_max_bytes;
+  /**
+   * Optional callback receiving every record of a request the server rejected as invalid
+   * (NULL to not report them). Requests of a stream that failed as a whole are not reported,
+   * but re-ingested by zerobus_sdk_recreate_stream. Unless spill_dir is set, reporting
+   * requires a copy of every record ingested
+   */
+  DeadLetterCallback dead_letter_callback;
+  /**
+   * Opaque pointer passed back to dead_letter_callback
+   */
+  void *dead_letter_user_data;
+  /**
+   * JSON Lines file the same records are appended to (NULL to not write them), one object
+   * per record with ack_id, record_type ("proto" or "json"), payload (base64 for proto
+   * records, the JSON text for JSON records), error_code and error_message
+   * Only read during stream creation
+   */
+  const char *dead_letter_path;
 } CStreamConfigurationOptions;
 
 /**
@@ -67,10 +275,124 @@ typedef struct CStreamConfigurationOptions {
  */
 typedef struct CHeaders (*HeadersProviderCallback)(void *user_data);
 
+/**
+ * Maps the ack ID of a record on a failed stream to its ack ID on the recreated stream
+ */
+typedef struct CAckIdMapping {
+  uint64_t old_ack_id;
+  uint64_t new_ack_id;
+} CAckIdMapping;
+
+/**
+ * A list of ack ID mappings, in ingestion order
+ */
+typedef struct CAckIdMappings {
+  struct CAckIdMapping *mappings;
+  uintptr_t count;
+} CAckIdMappings;
+
+/**
+ * A record that was ingested but never acknowledged
+ */
+typedef struct CUnackedRecord {
+  /**
+   * Ack ID the record was ingested with; all records of a batch share one ID
+   * (in fire-and-forget mode these IDs were not handed out but still follow ingestion order)
+   */
+  uint64_t ack_id;
+  /**
+   * Same values as CStreamConfigurationOptions.record_type: 1 = protobuf, 2 = JSON
+   */
+  int32_t record_type;
+  /**
+   * Protobuf bytes or UTF-8 JSON (not NUL-terminated)
+   */
+  uint8_t *data;
+  uintptr_t data_len;
+} CUnackedRecord;
+
+/**
+ * A list of unacknowledged records, in ingestion order
+ */
+typedef struct CUnackedRecords {
+  struct CUnackedRecord *records;
+  uintptr_t count;
+} CUnackedRecords;
+
+/**
+ * Point-in-time snapshot of a stream's counters, see zerobus_stream_get_metrics
+ * Counters are cumulative since the stream was created
+ */
+typedef struct CStreamMetrics {
+  /**
+   * Records accepted by the stream (a batch counts each of its records)
+   */
+  uint64_t records_ingested;
+  /**
+   * Payload bytes of the accepted records, as encoded for the server (JSON records after
+   * transcoding); counted once per record, so gRPC framing, compression and records resent
+   * after a recovery are not included
+   */
+  uint64_t bytes_ingested;
+  /**
+   * Records acknowledged by the server
+   */
+  uint64_t records_acked;
+  /**
+   * Records whose acknowledgment failed
+   */
+  uint64_t records_failed;
+  /**
+   * Records accepted but neither acknowledged nor failed yet
+   */
+  uint64_t records_inflight;
+  /**
+   * Mean time from ingestion to server acknowledgment, in microseconds
+   */
+  uint64_t ack_latency_avg_us;
+  /**
+   * Longest time from ingestion to server acknowledgment, in microseconds
+   */
+  uint64_t ack_latency_max_us;
+  /**
+   * Times the SDK tried to reopen the stream after a retryable failure (see the recovery
+   * option), each retry of a recovery included; counted from the headers the SDK requests
+   * for every connection attempt
+   */
+  uint64_t recoveries;
+} CStreamMetrics;
+
+/**
+ * A key-value field attached to a log event
+ */
+typedef struct CLogField {
+  const char *key;
+  const char *value;
+} CLogField;
+
+/**
+ * Function pointer type for the log callback (nullable)
+ * Invoked on the thread that emitted the event; all strings are only valid during the call
+ * `fields` holds the event's fields followed by those of the spans it was emitted in
+ */
+typedef void (*LogCallback)(void *user_data,
+                            CLogLevel level,
+                            const char *target,
+                            const char *message,
+                            const struct CLogField *fields,
+                            uintptr_t field_count);
+
 #ifdef __cplusplus
 extern "C" {
 #endif // __cplusplus
 
+/**
+ * Configure the global Tokio runtime used by every SDK and stream
+ * Must be called before the first SDK is created; fails with an invalid state error once the
+ * runtime has started. A NULL config starts the runtime with Tokio's defaults.
+ */
+bool zerobus_runtime_init(const struct CRuntimeConfig *config, struct CResult *result);
+
 /**
  * Free headers returned from callback
  */
@@ -95,6 +417,29 @@ void zerobus_sdk_free(struct CZerobusSdk *sdk);
  */
 void zerobus_sdk_set_use_tls(struct CZerobusSdk *sdk, bool use_tls);
 
+/**
+ * Generate the protobuf-encoded DescriptorProto of a table's records from its column schema,
+ * ready to pass to zerobus_sdk_create_stream
+ * table_schema_json: JSON array of {"name", "type", "nullable"} column objects (or a Unity
+ * Catalog table info object with a "columns" array) using Delta types such as "BIGINT",
+ * "ARRAY<STRING>", "STRUCT<city: STRING, zip: INT NOT NULL>" or "MAP<STRING, DOUBLE>"
+ * message_name: name of the generated proto2 message
+ * Nullable columns become optional fields and NOT NULL columns required fields, numbered from 1
+ * in column order. Types map to BOOLEAN -> bool, TINYINT/SMALLINT/INT -> int32, BIGINT -> int64,
+ * FLOAT -> float, DOUBLE -> double, DECIMAL/STRING/VARIANT -> string, BINARY -> bytes,
+ * DATE -> int32 (days since epoch), TIMESTAMP/TIMESTAMP_NTZ -> int64 (microseconds since epoch),
+ * STRUCT -> nested message, ARRAY<T> -> repeated T, MAP<K, V> -> map<K, V>
+ * The returned bytes must be released with zerobus_free_bytes
+ */
+struct CBytes zerobus_descriptor_proto_from_table_schema(const char *table_schema_json,
+                                                         const char *message_name,
+                                                         struct CResult *result);
+
+/**
+ * Free bytes returned by the FFI layer, e.g. by zerobus_descriptor_proto_from_table_schema
+ */
+void zerobus_free_bytes(struct CBytes bytes);
+
 /**
  * Create a stream with OAuth authentication
  * descriptor_proto_bytes: protobuf-encoded DescriptorProto (can be NULL for JSON streams)
@@ -108,6 +453,22 @@ struct CZerobusStream *zerobus_sdk_create_stream(struct CZerobusSdk *sdk,
                                                  const struct CStreamConfigurationOptions *options,
                                                  struct CResult *result);
 
+/**
+ * Create a stream with OAuth authentication from a FileDescriptorSet
+ * descriptor_set_bytes: protobuf-encoded FileDescriptorSet holding the record message and all of
+ * its dependencies (protoc --include_imports); message_name: fully qualified record message name
+ * Referenced types declared outside the message are copied into it as nested types
+ */
+struct CZerobusStream *zerobus_sdk_create_stream_from_descriptor_set(struct CZerobusSdk *sdk,
+                                                                     const char *table_name,
+                                                                     const uint8_t *descriptor_set_bytes,
+                                                                     uintptr_t descriptor_set_len,
+                                                                     const char *message_name,
+                                                                     const char *client_id,
+                                                                     const char *client_secret,
+                                                                     const struct CStreamConfigurationOptions *options,
+                                                                     struct CResult *result);
+
 /**
  * Create a stream with a custom headers provider callback
  * This allows you to provide custom authentication headers via a Go callback function
@@ -121,35 +482,307 @@ struct CZerobusStream *zerobus_sdk_create_stream_with_headers_provider(struct CZ
                                                                        const struct CStreamConfigurationOptions *options,
                                                                        struct CResult *result);
 
+/**
+ * Create a stream with a custom headers provider callback from a FileDescriptorSet
+ * See zerobus_sdk_create_stream_from_descriptor_set for how the descriptor set is resolved
+ */
+struct CZerobusStream *zerobus_sdk_create_stream_with_headers_provider_from_descriptor_set(struct CZerobusSdk *sdk,
+                                                                                           const char *table_name,
+                                                                                           const uint8_t *descriptor_set_bytes,
+                                                                                           uintptr_t descriptor_set_len,
+                                                                                           const char *message_name,
+                                                                                           HeadersProviderCallback headers_callback,
+                                                                                           void *user_data,
+                                                                                           const struct CStreamConfigurationOptions *options,
+                                                                                           struct CResult *result);
+
+/**
+ * Open a new stream with the table, authentication and options of a failed stream
+ * and re-ingest everything the failed stream left unacknowledged, batch by batch
+ * old_stream must be failed or closed and stays owned by the caller (free it as usual);
+ * the new stream keeps using the old stream's headers provider, spill directory, dead-letter
+ * sink and callback user data
+ * Batches already reported to the dead-letter sink are not re-ingested: the failed requests of
+ * the old stream settle asynchronously, and recreation waits at most settle_timeout_ms for them
+ * (without blocking the old stream's ingest calls), failing with WaitTimedOut (retryable,
+ * nothing is recreated) if they do not settle in time
+ * If ack_id_mapping is not NULL it receives the old -> new ack ID of every re-ingested batch,
+ * to be released with zerobus_free_ack_id_mappings
+ * Returns NULL on error. Check the result parameter for error details.
+ */
+struct CZerobusStream *zerobus_sdk_recreate_stream(struct CZerobusSdk *sdk,
+                                                   struct CZerobusStream *old_stream,
+                                                   uint64_t settle_timeout_ms,
+                                                   struct CAckIdMappings *ack_id_mapping,
+                                                   struct CResult *result);
+
+/**
+ * Free ack ID mappings returned by zerobus_sdk_recreate_stream
+ */
+void zerobus_free_ack_id_mappings(struct CAckIdMappings mappings);
+
 /**
  * Free a stream instance
+ * Outstanding acknowledgments of the stream are dropped and can no longer be awaited
+ * Waits for ack and dead-letter callbacks in progress; none are invoked once this returns
+ * Concurrent or repeated calls for the same stream release it once; the others return at once
  */
 void zerobus_stream_free(struct CZerobusStream *stream);
 
 /**
  * Ingest a record (protobuf encoded)
  * Returns an acknowledgment ID that can be awaited later
- * Returns 0 on error
+ * Returns 0 on error, and always in fire-and-forget mode (check result)
  */
 uint64_t zerobus_stream_ingest_proto_record(struct CZerobusStream *stream,
                                             const uint8_t *data,
                                             uintptr_t data_len,
                                             struct CResult *result);
 
+/**
+ * Ingest a record (protobuf encoded) without blocking when the stream is saturated
+ * If max_inflight_requests requests are awaiting acknowledgment, or another ingest call on the
+ * stream is in progress, returns 0 immediately with error code WouldBlock (retryable) and the
+ * record is not queued
+ * Behaves like zerobus_stream_ingest_proto_record otherwise
+ */
+uint64_t zerobus_stream_try_ingest_proto_record(struct CZerobusStream *stream,
+                                                const uint8_t *data,
+                                                uintptr_t data_len,
+                                                struct CResult *result);
+
+/**
+ * Ingest a record (protobuf encoded), waiting at most `timeout_ms` for a saturated stream
+ * Fails with error code WouldBlock if the record could not be queued within the timeout
+ * Behaves like zerobus_stream_try_ingest_proto_record otherwise
+ */
+uint64_t zerobus_stream_ingest_proto_record_timeout(struct CZerobusStream *stream,
+                                                    const uint8_t *data,
+                                                    uintptr_t data_len,
+                                                    uint64_t timeout_ms,
+                                                    struct CResult *result);
+
+/**
+ * Allocate a zeroed buffer of `capacity` bytes to encode a proto record into
+ * Ingesting the buffer with zerobus_stream_ingest_proto_record_buffer hands it over to the
+ * stream, so the record is sent without being copied; buffers that are not ingested must be
+ * released with zerobus_record_buffer_free
+ * A stream with a dead-letter sink but no spill_dir still copies each record, to be able to
+ * report it; with spill_dir, dead letters are read back from disk instead
+ * Returns NULL if capacity is 0
+ */
+uint8_t *zerobus_record_buffer_alloc(uintptr_t capacity);
+
+/**
+ * Free a buffer from zerobus_record_buffer_alloc that was not ingested
+ */
+void zerobus_record_buffer_free(uint8_t *buffer, uintptr_t capacity);
+
+/**
+ * Ingest a proto record encoded into a buffer from zerobus_record_buffer_alloc, without copying it
+ * `len` is the size of the record at the start of the buffer, `capacity` the size it was
+ * allocated with. The buffer is owned by the stream afterwards, also when an error is returned,
+ * and is released once the record is acknowledged or dropped
+ * Returns an acknowledgment ID like zerobus_stream_ingest_proto_record
+ */
+uint64_t zerobus_stream_ingest_proto_record_buffer(struct CZerobusStream *stream,
+                                                   uint8_t *buffer,
+                                                   uintptr_t len,
+                                                   uintptr_t capacity,
+                                                   struct CResult *result);
+
+/**
+ * Ingest a record buffer like zerobus_stream_ingest_proto_record_buffer without blocking when
+ * the stream is saturated, see zerobus_stream_try_ingest_proto_record
+ * On WouldBlock the buffer stays with the caller, who can ingest it again or free it
+ */
+uint64_t zerobus_stream_try_ingest_proto_record_buffer(struct CZerobusStream *stream,
+                                                       uint8_t *buffer,
+                                                       uintptr_t len,
+                                                       uintptr_t capacity,
+                                                       struct CResult *result);
+
+/**
+ * Ingest a record buffer like zerobus_stream_ingest_proto_record_buffer, waiting at most
+ * `timeout_ms` for a saturated stream, see zerobus_stream_ingest_proto_record_timeout
+ * On WouldBlock the buffer stays with the caller, who can ingest it again or free it
+ */
+uint64_t zerobus_stream_ingest_proto_record_buffer_timeout(struct CZerobusStream *stream,
+                                                           uint8_t *buffer,
+                                                           uintptr_t len,
+                                                           uintptr_t capacity,
+                                                           uint64_t timeout_ms,
+                                                           struct CResult *result);
+
 /**
  * Ingest a JSON record
+ * On streams created with transcode_json the record is converted to protobuf first
  * Returns an acknowledgment ID that can be awaited later
- * Returns 0 on error
+ * Returns 0 on error, and always in fire-and-forget mode (check result)
  */
 uint64_t zerobus_stream_ingest_json_record(struct CZerobusStream *stream,
                                            const char *json_data,
                                            struct CResult *result);
 
+/**
+ * Ingest a JSON record given as a UTF-8 byte slice (no NUL terminator required)
+ * `json_data` may contain embedded NUL bytes and may be NULL when `json_len` is 0
+ * Behaves like zerobus_stream_ingest_json_record otherwise
+ */
+uint64_t zerobus_stream_ingest_json_record_bytes(struct CZerobusStream *stream,
+                                                 const uint8_t *json_data,
+                                                 uintptr_t json_len,
+                                                 struct CResult *result);
+
+/**
+ * Ingest a JSON record given as a UTF-8 byte slice without blocking when the stream is saturated
+ * Fails immediately with error code WouldBlock like zerobus_stream_try_ingest_proto_record
+ * Behaves like zerobus_stream_ingest_json_record_bytes otherwise
+ */
+uint64_t zerobus_stream_try_ingest_json_record_bytes(struct CZerobusStream *stream,
+                                                     const uint8_t *json_data,
+                                                     uintptr_t json_len,
+                                                     struct CResult *result);
+
+/**
+ * Ingest a JSON record given as a UTF-8 byte slice, waiting at most `timeout_ms` for a
+ * saturated stream before failing with error code WouldBlock
+ * Behaves like zerobus_stream_try_ingest_json_record_bytes otherwise
+ */
+uint64_t zerobus_stream_ingest_json_record_bytes_timeout(struct CZerobusStream *stream,
+                                                         const uint8_t *json_data,
+                                                         uintptr_t json_len,
+                                                         uint64_t timeout_ms,
+                                                         struct CResult *result);
+
+/**
+ * Ingest a batch of protobuf encoded records with a single call
+ * `data` holds all records back to back; `offsets` must contain `num_records + 1`
+ * entries, record `i` spanning `data[offsets[i]..offsets[i + 1]]`
+ * Returns one acknowledgment ID for the whole batch; awaiting it yields the batch offset
+ * Returns 0 on error, and always in fire-and-forget mode (check result)
+ */
+uint64_t zerobus_stream_ingest_proto_batch(struct CZerobusStream *stream,
+                                           const uint8_t *data,
+                                           uintptr_t data_len,
+                                           const uintptr_t *offsets,
+                                           uintptr_t num_records,
+                                           struct CResult *result);
+
+/**
+ * Ingest a batch of proto records without blocking when the stream is saturated
+ * Fails immediately with error code WouldBlock like zerobus_stream_try_ingest_proto_record,
+ * in which case no record of the batch is queued
+ * Behaves like zerobus_stream_ingest_proto_batch otherwise
+ */
+uint64_t zerobus_stream_try_ingest_proto_batch(struct CZerobusStream *stream,
+                                               const uint8_t *data,
+                                               uintptr_t data_len,
+                                               const uintptr_t *offsets,
+                                               uintptr_t num_records,
+                                               struct CResult *result);
+
+/**
+ * Ingest a batch of proto records, waiting at most `timeout_ms` for a saturated stream before
+ * failing with error code WouldBlock
+ * Behaves like zerobus_stream_try_ingest_proto_batch otherwise
+ */
+uint64_t zerobus_stream_ingest_proto_batch_timeout(struct CZerobusStream *stream,
+                                                   const uint8_t *data,
+                                                   uintptr_t data_len,
+                                                   const uintptr_t *offsets,
+                                                   uintptr_t num_records,
+                                                   uint64_t timeout_ms,
+                                                   struct CResult *result);
+
+/**
+ * Ingest a batch of JSON records with a single call
+ * `data` holds all UTF-8 encoded records back to back (no NUL terminators);
+ * `offsets` must contain `num_records + 1` entries, record `i` spanning
+ * `data[offsets[i]..offsets[i + 1]]`
+ * Returns one acknowledgment ID for the whole batch; awaiting it yields the batch offset
+ * Returns 0 on error, and always in fire-and-forget mode (check result)
+ */
+uint64_t zerobus_stream_ingest_json_batch(struct CZerobusStream *stream,
+                                          const uint8_t *data,
+                                          uintptr_t data_len,
+                                          const uintptr_t *offsets,
+                                          uintptr_t num_records,
+                                          struct CResult *result);
+
+/**
+ * Ingest a batch of JSON records without blocking when the stream is saturated
+ * Fails immediately with error code WouldBlock like zerobus_stream_try_ingest_proto_record,
+ * in which case no record of the batch is queued
+ * Behaves like zerobus_stream_ingest_json_batch otherwise
+ */
+uint64_t zerobus_stream_try_ingest_json_batch(struct CZerobusStream *stream,
+                                              const uint8_t *data,
+                                              uintptr_t data_len,
+                                              const uintptr_t *offsets,
+                                              uintptr_t num_records,
+                                              struct CResult *result);
+
+/**
+ * Ingest a batch of JSON records, waiting at most `timeout_ms` for a saturated stream before
+ * failing with error code WouldBlock
+ * Behaves like zerobus_stream_try_ingest_json_batch otherwise
+ */
+uint64_t zerobus_stream_ingest_json_batch_timeout(struct CZerobusStream *stream,
+                                                  const uint8_t *data,
+                                                  uintptr_t data_len,
+                                                  const uintptr_t *offsets,
+                                                  uintptr_t num_records,
+                                                  uint64_t timeout_ms,
+                                                  struct CResult *result);
+
 /**
  * Await an acknowledgment (BLOCKING)
  * Returns the offset on success, or -1 on error
+ * Streams created with an ack_callback deliver acknowledgments to it, so their ack IDs cannot be awaited
+ */
+int64_t zerobus_stream_await_ack(struct CZerobusStream *stream,
+                                 uint64_t ack_id,
+                                 struct CResult *result);
+
+/**
+ * Await an acknowledgment for at most `timeout_ms` milliseconds (BLOCKING)
+ * Returns the offset on success, or -1 on error
+ * If the acknowledgment is still pending when the timeout expires, fails with error code
+ * WaitTimedOut and the ack ID stays registered, so it can be awaited again later
+ */
+int64_t zerobus_stream_await_ack_timeout(struct CZerobusStream *stream,
+                                         uint64_t ack_id,
+                                         uint64_t timeout_ms,
+                                         struct CResult *result);
+
+/**
+ * Await several acknowledgments at once (BLOCKING)
+ * Writes the offset of `ack_ids[i]` to `offsets_out[i]` (-1 on error) and, unless
+ * `results_out` is NULL, its outcome to `results_out[i]`; error messages there must be freed
+ * with zerobus_free_error_message. Every given ack ID is consumed, unknown IDs fail individually
+ * Returns true if all acknowledgments succeeded; `result` only reports invalid arguments
  */
-int64_t zerobus_stream_await_ack(uint64_t ack_id, struct CResult *result);
+bool zerobus_stream_await_acks(struct CZerobusStream *stream,
+                               const uint64_t *ack_ids,
+                               uintptr_t count,
+                               int64_t *offsets_out,
+                               struct CResult *results_out,
+                               struct CResult *result);
+
+/**
+ * Await whichever of several acknowledgments completes first (BLOCKING)
+ * The completed ack ID is consumed and returned; its offset is written to `offset_out` (-1 on
+ * error) and its outcome to `result`. The other ack IDs stay registered throughout: they can be
+ * awaited from other threads during the call and still count as outstanding acknowledgments
+ * Returns 0 if nothing was awaited (empty or invalid arguments, unknown or repeated IDs, or all
+ * listed IDs consumed by concurrent calls before any completed)
+ */
+uint64_t zerobus_stream_await_any_ack(struct CZerobusStream *stream,
+                                      const uint64_t *ack_ids,
+                                      uintptr_t count,
+                                      int64_t *offset_out,
+                                      struct CResult *result);
 
 /**
  * Try to get an acknowledgment without blocking
@@ -158,10 +791,42 @@ int64_t zerobus_stream_await_ack(uint64_t ack_id, struct CResult *result);
  *   -1: Still pending (check is_ready)
  *   -2: Error occurred (check result)
  */
-int64_t zerobus_stream_try_get_ack(uint64_t ack_id, bool *is_ready, struct CResult *result);
+int64_t zerobus_stream_try_get_ack(struct CZerobusStream *stream,
+                                   uint64_t ack_id,
+                                   bool *is_ready,
+                                   struct CResult *result);
+
+/**
+ * Get the number of acknowledgments of a stream that have not been awaited or delivered yet
+ * Returns 0 for a NULL stream
+ */
+uintptr_t zerobus_stream_get_outstanding_acks(struct CZerobusStream *stream);
+
+/**
+ * Get the records of a failed or closed stream that were never acknowledged,
+ * e.g. to re-ingest them on a new stream or persist them elsewhere
+ * Fails with an invalid state error while the stream is still active
+ * The returned records must be released with zerobus_free_unacked_records
+ */
+struct CUnackedRecords zerobus_stream_get_unacked_records(struct CZerobusStream *stream,
+                                                          struct CResult *result);
+
+/**
+ * Free records returned by zerobus_stream_get_unacked_records
+ */
+void zerobus_free_unacked_records(struct CUnackedRecords records);
+
+/**
+ * Get a snapshot of the stream's ingestion metrics
+ * Writes zeroed metrics and returns false on error (check result)
+ */
+bool zerobus_stream_get_metrics(struct CZerobusStream *stream,
+                                struct CStreamMetrics *metrics,
+                                struct CResult *result);
 
 /**
  * Flush all pending records
+ * Also fails if a fire-and-forget record was rejected since the previous flush
  */
 bool zerobus_stream_flush(struct CZerobusStream *stream, struct CResult *result);
 
@@ -180,6 +845,19 @@ void zerobus_free_error_message(char *message);
  */
 struct CStreamConfigurationOptions zerobus_get_default_config(void);
 
+/**
+ * Forward log events of the SDK (recovery attempts, reconnects, token refreshes, ...) to a callback
+ * Events at `level` (a CLogLevel value) or more severe are delivered; a NULL callback or
+ * CLogLevel_Off stops forwarding. May be called again to replace the callback or change the level.
+ * `user_data` is passed to every call of the callback. Events already being delivered when the
+ * callback is replaced may still reach the previous callback with its user data.
+ * Fails if another tracing subscriber was already installed as the process-wide default.
+ */
+bool zerobus_set_log_callback(int32_t level,
+                              LogCallback callback,
+                              void *user_data,
+                              struct CResult *result);
+
 #ifdef __cplusplus
 }  // extern "C"
 #endif  // __cplusplus
@@ -118,7 +118,11 @@
 package zerobus
 
 import (
+	"errors"
 	"runtime"
+	"sync"
+	"sync/atomic"
+	"time"
 	"unsafe"
 )
 
@@ -132,6 +136,104 @@ type ZerobusSdk struct {
 // Records can be ingested concurrently and will be acknowledged asynchronously.
 type ZerobusStream struct {
 	ptr unsafe.Pointer
+	// Number of calls using ptr; Close frees the stream only once they have returned
+	inUse atomic.Int64
+	// Set by Close, so later calls fail right away instead of waiting for the stream
+	closed    atomic.Bool
+	closeOnce sync.Once
+	// Created by Close and closed by the last call to stop using the stream
+	drained   chan struct{}
+	drainOnce sync.Once
+}
+
+// errStreamClosed is returned by calls on a closed stream
+var errStreamClosed = &ZerobusError{Message: "Stream has been closed", Code: ErrorCodeStreamClosed}
+
+// newZerobusStream wraps a native stream, closing it when it is garbage collected
+func newZerobusStream(ptr unsafe.Pointer) *ZerobusStream {
+	stream := &ZerobusStream{ptr: ptr}
+
+	// Set up finalizer for automatic cleanup
+	runtime.SetFinalizer(stream, func(st *ZerobusStream) {
+		st.Close()
+	})
+
+	return stream
+}
+
+// acquire marks the native stream as used by the calling goroutine, or reports false once the
+// stream is closed. Every successful acquire must be paired with a release.
+func (st *ZerobusStream) acquire() bool {
+	st.inUse.Add(1)
+	if st.closed.Load() {
+		st.release()
+		return false
+	}
+	return true
+}
+
+// release ends a use of the native stream started by acquire
+func (st *ZerobusStream) release() {
+	if st.inUse.Add(-1) == 0 && st.closed.Load() {
+		st.drainOnce.Do(func() { close(st.drained) })
+	}
+}
+
+// InitRuntime configures the runtime shared by all SDK instances and streams.
+// It must be called before the first SDK is created; afterwards it returns an error
+// with ErrorCodeInvalidState because the runtime has already started.
+//
+// Example:
+//
+//	err := zerobus.InitRuntime(zerobus.RuntimeConfig{
+//	    WorkerThreads:    4,
+//	    ThreadNamePrefix: "zerobus",
+//	})
+func InitRuntime(config RuntimeConfig) error {
+	return runtimeInit(config)
+}
+
+// SetLogHandler forwards log events of the SDK at the given level or more severe to handler,
+// e.g. stream recovery attempts, reconnects and token refreshes.
+// Passing a nil handler or LogLevelOff stops forwarding.
+//
+// Example:
+//
+//	zerobus.SetLogHandler(zerobus.LogLevelInfo, func(e zerobus.LogEntry) {
+//	    slog.Info(e.Message, "target", e.Target, "level", e.Level, "fields", e.Fields)
+//	})
+func SetLogHandler(level LogLevel, handler LogHandler) error {
+	return setLogCallback(level, handler)
+}
+
+// DescriptorProtoFromTableSchema generates the protobuf-encoded DescriptorProto of a table's
+// records from its column schema, for TableProperties.DescriptorProto. The schema is a JSON
+// array of columns, e.g.
+//
+//	[{"name": "id", "type": "BIGINT", "nullable": false},
+//	 {"name": "address", "type": "STRUCT<city: STRING, zip: INT>"},
+//	 {"name": "tags", "type": "ARRAY<STRING>"}]
+//
+// or a Unity Catalog table info object with a "columns" array. The generated proto2 message
+// has one field per column, numbered from 1 in column order: optional when the column is
+// nullable, required otherwise. Delta types map as follows:
+//
+//	BOOLEAN                     bool
+//	TINYINT, SMALLINT, INT      int32
+//	BIGINT                      int64
+//	FLOAT, DOUBLE               float, double
+//	DECIMAL, STRING, VARIANT    string
+//	BINARY                      bytes
+//	DATE                        int32 (days since 1970-01-01)
+//	TIMESTAMP, TIMESTAMP_NTZ    int64 (microseconds since the epoch)
+//	STRUCT<...>                 nested message named after the column in PascalCase
+//	ARRAY<T>                    repeated T
+//	MAP<K, V>                   map<K, V>
+//
+// Arrays of arrays or maps, maps with array or map values and maps with non-integral,
+// non-string, non-bool keys cannot be represented and return an error.
+func DescriptorProtoFromTableSchema(tableSchemaJSON, messageName string) ([]byte, error) {
+	return descriptorProtoFromTableSchema(tableSchemaJSON, messageName)
 }
 
 // NewZerobusSdk creates a new SDK instance.
@@ -202,13 +304,12 @@ func (s *ZerobusSdk) CreateStream(
 	options *StreamConfigurationOptions,
 ) (*ZerobusStream, error) {
 	if s.ptr == nil {
-		return nil, &ZerobusError{Message: "SDK has been freed", IsRetryable: false}
+		return nil, &ZerobusError{Message: "SDK has been freed", Code: ErrorCodeInvalidState}
 	}
 
 	ptr, err := sdkCreateStream(
 		s.ptr,
-		tableProps.TableName,
-		tableProps.DescriptorProto,
+		tableProps,
 		clientID,
 		clientSecret,
 		options,
@@ -217,14 +318,56 @@ func (s *ZerobusSdk) CreateStream(
 		return nil, err
 	}
 
-	stream := &ZerobusStream{ptr: ptr}
+	return newZerobusStream(ptr), nil
+}
 
-	// Set up finalizer for automatic cleanup
-	runtime.SetFinalizer(stream, func(st *ZerobusStream) {
-		st.Close()
-	})
+// RecreateStream opens a new stream for the same table, with the same authentication and options
+// as a failed stream, and re-ingests every record the failed stream left unacknowledged.
+//
+// The returned map holds the acknowledgment of each re-ingested record on the new stream,
+// keyed by the ID of its original acknowledgment (see RecordAck.ID).
+// The old stream must have failed; close it afterwards as usual.
+//
+// The failed requests of the old stream settle in the background (see DeadLetterHandler);
+// RecreateStream waits at most settleTimeout for them and otherwise fails with
+// ErrorCodeWaitTimedOut (retryable) without recreating anything.
+//
+// Example:
+//
+//	if err := stream.Flush(); err != nil {
+//	    newStream, acks, err := sdk.RecreateStream(stream, 5*time.Second)
+//	    if err != nil {
+//	        log.Fatal(err)
+//	    }
+//	    stream.Close()
+//	    for oldID, ack := range acks {
+//	        if _, err := ack.Await(); err != nil {
+//	            log.Printf("record %d failed again: %v", oldID, err)
+//	        }
+//	    }
+//	    stream = newStream
+//	}
+func (s *ZerobusSdk) RecreateStream(oldStream *ZerobusStream, settleTimeout time.Duration) (*ZerobusStream, map[uint64]*RecordAck, error) {
+	if s.ptr == nil {
+		return nil, nil, &ZerobusError{Message: "SDK has been freed", Code: ErrorCodeInvalidState}
+	}
+	if !oldStream.acquire() {
+		return nil, nil, errStreamClosed
+	}
+
+	ptr, mappings, err := sdkRecreateStream(s.ptr, oldStream.ptr, settleTimeout)
+	oldStream.release()
+	if err != nil {
+		return nil, nil, err
+	}
 
-	return stream, nil
+	stream := newZerobusStream(ptr)
+	acks := make(map[uint64]*RecordAck, len(mappings))
+	for oldID, newID := range mappings {
+		acks[oldID] = newRecordAck(stream, newID)
+	}
+
+	return stream, acks, nil
 }
 
 // HeadersProvider is an interface for providing custom authentication headers.
@@ -273,13 +416,12 @@ func (s *ZerobusSdk) CreateStreamWithHeadersProvider(
 	options *StreamConfigurationOptions,
 ) (*ZerobusStream, error) {
 	if s.ptr == nil {
-		return nil, &ZerobusError{Message: "SDK has been freed", IsRetryable: false}
+		return nil, &ZerobusError{Message: "SDK has been freed", Code: ErrorCodeInvalidState}
 	}
 
 	ptr, err := sdkCreateStreamWithHeadersProvider(
 		s.ptr,
-		tableProps.TableName,
-		tableProps.DescriptorProto,
+		tableProps,
 		headersProvider,
 		options,
 	)
@@ -287,14 +429,7 @@ func (s *ZerobusSdk) CreateStreamWithHeadersProvider(
 		return nil, err
 	}
 
-	stream := &ZerobusStream{ptr: ptr}
-
-	// Set up finalizer for automatic cleanup
-	runtime.SetFinalizer(stream, func(st *ZerobusStream) {
-		st.Close()
-	})
-
-	return stream, nil
+	return newZerobusStream(ptr), nil
 }
 
 // IngestRecord ingests a record into the stream.
@@ -323,9 +458,10 @@ func (s *ZerobusSdk) CreateStreamWithHeadersProvider(
 //	offset2, err2 := ack2.Await()
 //	offset3, err3 := ack3.Await()
 func (st *ZerobusStream) IngestRecord(payload interface{}) (*RecordAck, error) {
-	if st.ptr == nil {
-		return nil, &ZerobusError{Message: "Stream has been closed", IsRetryable: false}
+	if !st.acquire() {
+		return nil, errStreamClosed
 	}
+	defer st.release()
 
 	var ackID uint64
 	var err error
@@ -335,10 +471,84 @@ func (st *ZerobusStream) IngestRecord(payload interface{}) (*RecordAck, error) {
 		ackID, err = streamIngestProtoRecord(st.ptr, v)
 	case string:
 		ackID, err = streamIngestJSONRecord(st.ptr, v)
+	case *RecordBuffer:
+		if v == nil || v.ptr == nil {
+			return nil, &ZerobusError{Message: "record buffer is empty or already used", Code: ErrorCodeInvalidArgument}
+		}
+		ackID, err = streamIngestProtoRecordBuffer(st.ptr, v.ptr, v.length, v.capacity)
+		// The native library owns the buffer from here on, whatever the outcome
+		v.ptr, v.data = nil, nil
+		runtime.SetFinalizer(v, nil)
+	default:
+		return nil, &ZerobusError{
+			Message:     "Invalid payload type: must be []byte, string or *RecordBuffer",
+			IsRetryable: false,
+			Code:        ErrorCodeInvalidArgument,
+		}
+	}
+
+	if err != nil {
+		return nil, err
+	}
+
+	return newRecordAck(st, ackID), nil
+}
+
+// TryIngestRecord ingests a record like IngestRecord, but never blocks on backpressure.
+//
+// If the stream already has MaxInflightRequests requests awaiting acknowledgment, or another
+// goroutine is queueing a record on it, the record is not queued and a retryable error with code
+// ErrorCodeWouldBlock is returned immediately.
+// The payload types are those of IngestRecord. A *RecordBuffer is handed over to the stream
+// unless ErrorCodeWouldBlock is returned, in which case it can be ingested again or freed.
+//
+// Example:
+//
+//	ack, err := stream.TryIngestRecord(data)
+//	var zerr *zerobus.ZerobusError
+//	if errors.As(err, &zerr) && zerr.Code == zerobus.ErrorCodeWouldBlock {
+//		// Stream is saturated: buffer the record, shed load or retry later
+//	}
+func (st *ZerobusStream) TryIngestRecord(payload interface{}) (*RecordAck, error) {
+	return st.IngestRecordTimeout(payload, 0)
+}
+
+// IngestRecordTimeout ingests a record like IngestRecord, waiting at most timeout for the stream
+// to drop below MaxInflightRequests and for other goroutines queueing records on it. If the
+// record cannot be queued by then, it is not queued and a retryable error with code
+// ErrorCodeWouldBlock is returned.
+// The payload types are those of IngestRecord, and a *RecordBuffer is handed over like with
+// TryIngestRecord.
+func (st *ZerobusStream) IngestRecordTimeout(payload interface{}, timeout time.Duration) (*RecordAck, error) {
+	if !st.acquire() {
+		return nil, errStreamClosed
+	}
+	defer st.release()
+
+	var ackID uint64
+	var err error
+
+	switch v := payload.(type) {
+	case []byte:
+		ackID, err = streamIngestProtoRecordTimeout(st.ptr, v, timeout)
+	case string:
+		ackID, err = streamIngestJSONRecordTimeout(st.ptr, v, timeout)
+	case *RecordBuffer:
+		if v == nil || v.ptr == nil {
+			return nil, &ZerobusError{Message: "record buffer is empty or already used", Code: ErrorCodeInvalidArgument}
+		}
+		ackID, err = streamIngestProtoRecordBufferTimeout(st.ptr, v.ptr, v.length, v.capacity, timeout)
+		// The buffer stays with the caller only if the stream had no room for it
+		var zerr *ZerobusError
+		if !errors.As(err, &zerr) || zerr.Code != ErrorCodeWouldBlock {
+			v.ptr, v.data = nil, nil
+			runtime.SetFinalizer(v, nil)
+		}
 	default:
 		return nil, &ZerobusError{
-			Message:     "Invalid payload type: must be []byte or string",
+			Message:     "Invalid payload type: must be []byte, string or *RecordBuffer",
 			IsRetryable: false,
+			Code:        ErrorCodeInvalidArgument,
 		}
 	}
 
@@ -346,9 +556,211 @@ func (st *ZerobusStream) IngestRecord(payload interface{}) (*RecordAck, error) {
 		return nil, err
 	}
 
-	return &RecordAck{
-		ackID: ackID,
-	}, nil
+	return newRecordAck(st, ackID), nil
+}
+
+// IngestBatch ingests several records as a single request and returns one acknowledgment for
+// all of them. Awaiting it yields the batch's offset; the batch is acknowledged or fails as a whole.
+//
+// The records must be a []string (JSON) or [][]byte (Protocol Buffers) with at least one record.
+// If a record is rejected before sending, the error names its index and no record is queued.
+//
+// Example:
+//
+//	ack, err := stream.IngestBatch([]string{`{"id": 1}`, `{"id": 2}`})
+//	if err != nil {
+//		log.Fatal(err)
+//	}
+//	offset, err := ack.Await()
+func (st *ZerobusStream) IngestBatch(records interface{}) (*RecordAck, error) {
+	if !st.acquire() {
+		return nil, errStreamClosed
+	}
+	defer st.release()
+
+	data, offsets, isJSON, err := batchBuffer(records)
+	if err != nil {
+		return nil, err
+	}
+	ackID, err := streamIngestBatch(st.ptr, data, offsets, isJSON)
+	if err != nil {
+		return nil, err
+	}
+
+	return newRecordAck(st, ackID), nil
+}
+
+// TryIngestBatch ingests a batch like IngestBatch, but never blocks on backpressure: if the
+// stream cannot take the request right away, no record is queued and a retryable error with
+// code ErrorCodeWouldBlock is returned, like TryIngestRecord does.
+func (st *ZerobusStream) TryIngestBatch(records interface{}) (*RecordAck, error) {
+	return st.IngestBatchTimeout(records, 0)
+}
+
+// IngestBatchTimeout ingests a batch like IngestBatch, waiting at most timeout for the stream
+// to take the request. If it cannot by then, no record is queued and a retryable error with
+// code ErrorCodeWouldBlock is returned.
+func (st *ZerobusStream) IngestBatchTimeout(records interface{}, timeout time.Duration) (*RecordAck, error) {
+	if !st.acquire() {
+		return nil, errStreamClosed
+	}
+	defer st.release()
+
+	data, offsets, isJSON, err := batchBuffer(records)
+	if err != nil {
+		return nil, err
+	}
+	ackID, err := streamIngestBatchTimeout(st.ptr, data, offsets, isJSON, timeout)
+	if err != nil {
+		return nil, err
+	}
+
+	return newRecordAck(st, ackID), nil
+}
+
+// batchBuffer concatenates the records of a batch into the buffer and record offsets taken by
+// the native library, and reports whether they are JSON records
+func batchBuffer(records interface{}) ([]byte, []uintptr, bool, error) {
+	var data []byte
+	offsets := []uintptr{0}
+
+	switch v := records.(type) {
+	case [][]byte:
+		for _, record := range v {
+			data = append(data, record...)
+			offsets = append(offsets, uintptr(len(data)))
+		}
+		return data, offsets, false, nil
+	case []string:
+		for _, record := range v {
+			data = append(data, record...)
+			offsets = append(offsets, uintptr(len(data)))
+		}
+		return data, offsets, true, nil
+	default:
+		return nil, nil, false, &ZerobusError{
+			Message:     "Invalid records type: must be [][]byte or []string",
+			IsRetryable: false,
+			Code:        ErrorCodeInvalidArgument,
+		}
+	}
+}
+
+// RecordBuffer is native memory a Protocol Buffer record is encoded into, so that ingesting it
+// hands the memory over to the stream instead of copying the record.
+//
+// A buffer can be ingested once. Buffers that are not ingested should be released with Free;
+// the garbage collector frees them otherwise.
+//
+// Example:
+//
+//	size := proto.Size(msg)
+//	buf := zerobus.NewRecordBuffer(size)
+//	out, err := proto.MarshalOptions{}.MarshalAppend(buf.Bytes()[:0], msg)
+//	if err != nil || len(out) != size {
+//		// MarshalAppend reallocated instead of encoding into the buffer
+//		buf.Free()
+//		return fmt.Errorf("encoding record: %v", err)
+//	}
+//	ack, err := stream.IngestRecord(buf)
+type RecordBuffer struct {
+	ptr      unsafe.Pointer
+	data     []byte
+	length   int
+	capacity int
+}
+
+// NewRecordBuffer allocates a zeroed buffer of the given size for a record.
+func NewRecordBuffer(size int) *RecordBuffer {
+	if size <= 0 {
+		return &RecordBuffer{}
+	}
+	ptr := recordBufferAlloc(size)
+	buf := &RecordBuffer{
+		ptr:      ptr,
+		data:     unsafe.Slice((*byte)(ptr), size),
+		length:   size,
+		capacity: size,
+	}
+
+	// Release buffers that are dropped without being ingested or freed
+	runtime.SetFinalizer(buf, func(b *RecordBuffer) {
+		b.Free()
+	})
+
+	return buf
+}
+
+// Bytes returns the record's bytes, to be filled in place.
+// The slice must not be used after the buffer has been ingested or freed, and does not keep
+// the buffer from being garbage collected.
+func (b *RecordBuffer) Bytes() []byte {
+	return b.data[:b.length]
+}
+
+// Truncate shortens the record to its first n bytes, for records smaller than the buffer.
+func (b *RecordBuffer) Truncate(n int) {
+	if n >= 0 && n < b.length {
+		b.length = n
+	}
+}
+
+// Free releases a buffer that was not ingested. It is a no-op after ingestion.
+func (b *RecordBuffer) Free() {
+	if b.ptr != nil {
+		recordBufferFree(b.ptr, b.capacity)
+		b.ptr, b.data = nil, nil
+		runtime.SetFinalizer(b, nil)
+	}
+}
+
+// OutstandingAcks returns the number of acknowledgments of this stream that have not been awaited yet.
+// Acknowledgments still outstanding when the stream is closed are discarded.
+func (st *ZerobusStream) OutstandingAcks() int {
+	if !st.acquire() {
+		return 0
+	}
+	defer st.release()
+
+	return streamOutstandingAcks(st.ptr)
+}
+
+// Metrics returns a snapshot of the stream's ingestion counters and acknowledgment latency.
+//
+// Example:
+//
+//	m, _ := stream.Metrics()
+//	recordsAcked.Set(float64(m.RecordsAcked))
+func (st *ZerobusStream) Metrics() (StreamMetrics, error) {
+	if !st.acquire() {
+		return StreamMetrics{}, errStreamClosed
+	}
+	defer st.release()
+
+	return streamGetMetrics(st.ptr)
+}
+
+// GetUnackedRecords returns the records that were never acknowledged after the stream failed,
+// in ingestion order, so they can be re-ingested on a new stream or stored elsewhere.
+// It must be called before Close, which releases the stream.
+//
+// Returns an error if the stream is still active.
+//
+// Example:
+//
+//	if err := stream.Flush(); err != nil {
+//	    unacked, _ := stream.GetUnackedRecords()
+//	    for _, rec := range unacked {
+//	        log.Printf("record %d was not acknowledged", rec.AckID)
+//	    }
+//	}
+func (st *ZerobusStream) GetUnackedRecords() ([]UnackedRecord, error) {
+	if !st.acquire() {
+		return nil, errStreamClosed
+	}
+	defer st.release()
+
+	return streamGetUnackedRecords(st.ptr)
 }
 
 // Flush blocks until all pending records have been acknowledged by the server.
@@ -364,9 +776,10 @@ func (st *ZerobusStream) IngestRecord(payload interface{}) (*RecordAck, error) {
 //	    log.Printf("Flush failed: %v", err)
 //	}
 func (st *ZerobusStream) Flush() error {
-	if st.ptr == nil {
-		return &ZerobusError{Message: "Stream has been closed", IsRetryable: false}
+	if !st.acquire() {
+		return errStreamClosed
 	}
+	defer st.release()
 
 	return streamFlush(st.ptr)
 }
@@ -374,7 +787,9 @@ func (st *ZerobusStream) Flush() error {
 // Close gracefully closes the stream after flushing all pending records.
 // This method ensures all records are durably stored before closing the connection.
 //
-// The stream cannot be used after calling Close().
+// The stream cannot be used after calling Close(): calls made afterwards fail right away with
+// ErrorCodeStreamClosed. Calls still in progress on other goroutines, such as Await, are waited
+// for; the records they wait on are flushed first, so they return within the flush timeout.
 // Note: This is automatically called by the garbage collector, but should be called explicitly
 // when done with the stream to ensure timely resource cleanup and proper error handling.
 //
@@ -386,13 +801,20 @@ func (st *ZerobusStream) Flush() error {
 //
 //	defer stream.Close()
 func (st *ZerobusStream) Close() error {
-	if st.ptr == nil {
-		return nil // Already closed
-	}
+	var err error
+	// Later calls return nil once the stream is closed
+	st.closeOnce.Do(func() {
+		st.drained = make(chan struct{})
+		st.closed.Store(true)
+		if st.inUse.Load() > 0 {
+			// Pending acks are settled by the flush, so calls awaiting them return
+			_ = streamFlush(st.ptr)
+			<-st.drained
+		}
 
-	err := streamClose(st.ptr)
-	streamFree(st.ptr)
-	st.ptr = nil
+		err = streamClose(st.ptr)
+		streamFree(st.ptr)
+	})
 
 	return err
 }